@@ -3,14 +3,19 @@ extern crate failure;
 
 use std::convert::TryFrom;
 use std::io::{BufReader, Read, Write};
+use std::time::Instant;
 
 use clap::{App, AppSettings, Arg};
 use conllx::io::{ReadSentence, Reader, Writer};
 use failure::Error;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use stdinout::{Input, OrExit, Output};
 
 use lumberjack::io::{
-    Decode, Encode, PTBFormat, PTBLineFormat, PTBWriter, TryFromConllx, WriteTree,
+    detect_format, CorpusStats, Decode, DetectedFormat, Encode, PTBFormat, PTBFormatter,
+    PTBLineFormat, PTBWriter, TryFromConllx, WriteTree,
 };
 use lumberjack::util::LabelSet;
 use lumberjack::{NegraReader, PTBReader, Projectivize, Tree, TreeOps};
@@ -24,14 +29,82 @@ fn main() {
     let in_format = matches.value_of(IN_FORMAT).unwrap();
     let in_format = InFormat::try_from(in_format).or_exit("Can't read input format.", 1);
     let in_path = matches.value_of(INPUT).map(ToOwned::to_owned);
+    let gzipped_input = is_gzipped(in_path.as_ref());
     let input = Input::from(in_path);
-    let reader = BufReader::new(input.buf_read().or_exit("Can't open input reader.", 1));
+    let input = input.buf_read().or_exit("Can't open input reader.", 1);
+    let reader: Box<dyn Read> = if gzipped_input {
+        Box::new(MultiGzDecoder::new(input))
+    } else {
+        Box::new(input)
+    };
+    let mut reader = BufReader::new(reader);
+    let skip_if_feature = matches.value_of(SKIP_IF_FEATURE);
+    let set_feature = matches.value_of(SET_FEATURE);
+    // markers are stored as root features, only the features of the root are read and written.
+    let markers = skip_if_feature.is_some() || set_feature.is_some();
+    let skip_errors = matches.is_present(SKIP_ERRORS);
+    let mut n_errors = 0;
+    let (in_format, multiline) = if in_format == InFormat::Auto {
+        match detect_format(&mut reader).or_exit("Can't detect input format.", 1) {
+            DetectedFormat::Negra => (InFormat::NEGRA, multiline),
+            DetectedFormat::Bracketed(line_format) => {
+                (InFormat::Simple, line_format == PTBLineFormat::MultiLine)
+            }
+        }
+    } else {
+        (in_format, multiline)
+    };
+
+    if matches.is_present(COUNT) {
+        let start = Instant::now();
+        let mut count = 0;
+        for tree in get_reader(in_format, reader, multiline, markers) {
+            if read_or_skip(tree, skip_errors, &mut n_errors).is_some() {
+                count += 1;
+            }
+        }
+        println!(
+            "Read {} trees in {:.2}s.",
+            count,
+            start.elapsed().as_secs_f64()
+        );
+        if skip_errors {
+            eprintln!("Skipped {} trees that could not be read.", n_errors);
+        }
+        return;
+    }
+
+    if matches.is_present(STATS) {
+        let mut stats = CorpusStats::new();
+        for tree in get_reader(in_format, reader, multiline, markers) {
+            if let Some(tree) = read_or_skip(tree, skip_errors, &mut n_errors) {
+                stats.observe(&tree);
+            }
+        }
+        print!("{}", stats);
+        if skip_errors {
+            eprintln!("Skipped {} trees that could not be read.", n_errors);
+        }
+        return;
+    }
 
     let out_format = matches.value_of(OUT_FORMAT).unwrap();
     let out_formatter = OutFormat::try_from(out_format).or_exit("Can't read output format.", 1);
+    if markers {
+        check_marker_support(in_format, out_formatter).or_exit("Can't use marker features.", 1);
+    }
     let out_path = matches.value_of(OUTPUT).map(ToOwned::to_owned);
+    let gzipped_output = is_gzipped(out_path.as_ref());
     let output = Output::from(out_path);
-    let writer = output.write().or_exit("Can't open output writer.", 1);
+    let output = output.write().or_exit("Can't open output writer.", 1);
+    // the encoder is kept to finish the gzip stream after the last tree was written.
+    let mut encoder = None;
+    let mut plain = None;
+    let writer: &mut dyn Write = if gzipped_output {
+        encoder.get_or_insert(GzEncoder::new(output, Compression::default()))
+    } else {
+        plain.get_or_insert(output)
+    };
 
     let parent_feature = if matches.is_present(PARENT) {
         Some(
@@ -47,33 +120,56 @@ fn main() {
     let projectivize = matches.is_present(PROJECTIVIZE);
     let filter_set = matches.value_of(FILTER_SET).map(get_set_from_file);
     let insertion_set = matches.value_of(INSERTION_SET).map(get_set_from_file);
-    let insertion_label = matches.value_of(INSERTION_LABEL).unwrap_or_else(|| "UNK");
+    let insertion_label = matches.value_of(INSERTION_LABEL).unwrap_or("UNK");
+    let flush_every = matches
+        .value_of(FLUSH_EVERY)
+        .map(|n| parse_flush_interval(n).or_exit("Can't parse flush interval.", 1));
+    if flush_every.is_some() {
+        check_flush_support(out_formatter).or_exit("Can't flush output.", 1);
+    }
 
-    let mut writer = get_writer(out_formatter, writer);
+    let mut writer = get_writer(out_formatter, writer, markers);
 
-    for tree in get_reader(in_format, reader, multiline) {
-        let mut tree = tree.or_exit("Could not read tree.", 1);
-        if projectivize {
-            tree.projectivize();
-        }
+    let mut n_written = 0;
+    for tree in get_reader(in_format, reader, multiline, markers) {
+        let mut tree = match read_or_skip(tree, skip_errors, &mut n_errors) {
+            Some(tree) => tree,
+            None => continue,
+        };
+        let marked = skip_if_feature.is_some_and(|key| {
+            tree[tree.root()]
+                .features()
+                .is_some_and(|features| features.inner().contains_key(key))
+        });
 
-        if remove_dummies {
-            tree.remove_dummy_nodes()
-                .or_exit("Can't remove dummy nopdes.", 1);
-        }
+        if !marked {
+            if projectivize {
+                tree.projectivize();
+            }
 
-        if let Some(filter_set) = filter_set.as_ref() {
-            tree.filter_nonterminals(filter_set).unwrap();
-        }
+            if remove_dummies {
+                tree.remove_dummy_nodes()
+                    .or_exit("Can't remove dummy nopdes.", 1);
+            }
 
-        if let Some(insertion_set) = insertion_set.as_ref() {
-            tree.insert_intermediate(insertion_set, insertion_label)
-                .or_exit("Can't insert nodes.", 1);
-        }
+            if let Some(filter_set) = filter_set.as_ref() {
+                tree.filter_nonterminals(filter_set).unwrap();
+            }
+
+            if let Some(insertion_set) = insertion_set.as_ref() {
+                tree.insert_intermediate(insertion_set, insertion_label)
+                    .or_exit("Can't insert nodes.", 1);
+            }
 
-        if let Some(name) = parent_feature.as_ref() {
-            tree.annotate_parent_tag(name)
-                .or_exit("Can't annotate parent tags.", 1);
+            if let Some(name) = parent_feature.as_ref() {
+                tree.annotate_parent_tag(name)
+                    .or_exit("Can't annotate parent tags.", 1);
+            }
+
+            if let Some(key) = set_feature {
+                let root = tree.root();
+                tree[root].features_mut().insert(key, Some("true"));
+            }
         }
 
         if out_formatter == OutFormat::Absolute {
@@ -90,7 +186,81 @@ fn main() {
 
         writer
             .write_tree(&tree)
-            .or_exit("Can't write to output.", 1)
+            .or_exit("Can't write to output.", 1);
+        n_written += 1;
+
+        if let Some(flush_every) = flush_every {
+            if n_written % flush_every == 0 {
+                writer.flush().or_exit("Can't flush output.", 1);
+            }
+        }
+    }
+    drop(writer);
+    if let Some(encoder) = encoder {
+        encoder
+            .finish()
+            .or_exit("Can't finish compressed output.", 1);
+    }
+    if skip_errors {
+        eprintln!("Skipped {} trees that could not be read.", n_errors);
+    }
+}
+
+// files are treated as gzipped based on their extension.
+fn is_gzipped(path: Option<&String>) -> bool {
+    path.map(|path| path.ends_with(".gz")).unwrap_or(false)
+}
+
+// Unwrap `tree`, unreadable trees are counted and skipped if `skip_errors` is set.
+fn read_or_skip(
+    tree: Result<Tree, Error>,
+    skip_errors: bool,
+    n_errors: &mut usize,
+) -> Option<Tree> {
+    match tree {
+        Ok(tree) => Some(tree),
+        Err(_) if skip_errors => {
+            *n_errors += 1;
+            None
+        }
+        Err(err) => Some(Err(err).or_exit("Could not read tree.", 1)),
+    }
+}
+
+fn parse_flush_interval(n: &str) -> Result<usize, Error> {
+    match n.parse::<usize>()? {
+        0 => Err(format_err!("Flush interval has to be greater than 0.")),
+        n => Ok(n),
+    }
+}
+
+// CONLLX writers don't give access to the wrapped writer and can't be flushed.
+fn check_flush_support(out_format: OutFormat) -> Result<(), Error> {
+    use OutFormat::*;
+    match out_format {
+        Absolute | Conllx | Relative => Err(format_err!(
+            "--flush_every is not supported for CONLLX output."
+        )),
+        PTB | Simple | TueBa => Ok(()),
+    }
+}
+
+// markers are root features, which are only represented by the bracketed formats.
+fn check_marker_support(in_format: InFormat, out_format: OutFormat) -> Result<(), Error> {
+    use InFormat::*;
+    match in_format {
+        Absolute | NEGRA | Relative => {
+            return Err(format_err!(
+                "--skip_if_feature and --set_feature require bracketed input."
+            ))
+        }
+        Auto | PTB | Simple | TueBa => (),
+    }
+    match out_format {
+        OutFormat::Absolute | OutFormat::Conllx | OutFormat::Relative => Err(format_err!(
+            "--skip_if_feature and --set_feature require bracketed output."
+        )),
+        OutFormat::PTB | OutFormat::Simple | OutFormat::TueBa => Ok(()),
     }
 }
 
@@ -104,9 +274,11 @@ fn get_set_from_file(path: &str) -> LabelSet {
     )
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Eq, PartialEq)]
 enum InFormat {
     Absolute,
+    Auto,
     Relative,
     NEGRA,
     PTB,
@@ -114,13 +286,14 @@ enum InFormat {
     TueBa,
 }
 
-impl<'a> TryFrom<&'a str> for InFormat {
+impl TryFrom<&str> for InFormat {
     type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         use InFormat::*;
         match value.to_lowercase().as_str() {
             "absolute" => Ok(Absolute),
+            "auto" => Ok(Auto),
             "relative" => Ok(Relative),
             "negra" => Ok(NEGRA),
             "ptb" => Ok(PTB),
@@ -131,6 +304,7 @@ impl<'a> TryFrom<&'a str> for InFormat {
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Copy, Clone, Eq, PartialEq)]
 enum OutFormat {
     Absolute,
@@ -141,7 +315,7 @@ enum OutFormat {
     TueBa,
 }
 
-impl<'a> TryFrom<&'a str> for OutFormat {
+impl TryFrom<&str> for OutFormat {
     type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
@@ -162,7 +336,8 @@ fn get_reader<'a, R>(
     in_format: InFormat,
     input: BufReader<R>,
     multiline: bool,
-) -> Box<Iterator<Item = Result<Tree, Error>> + 'a>
+    root_features: bool,
+) -> Box<dyn Iterator<Item = Result<Tree, Error>> + 'a>
 where
     R: Read + 'a,
 {
@@ -187,22 +362,47 @@ where
                 .map(|s| TryFromConllx::try_from_conllx_with_relative_encoding(&s)),
         ),
         NEGRA => Box::new(NegraReader::new(input)),
-        Simple => Box::new(PTBReader::new(input, PTBFormat::Simple, multiline)),
-        PTB => Box::new(PTBReader::new(input, PTBFormat::PTB, multiline)),
-        TueBa => Box::new(PTBReader::new(input, PTBFormat::TueBa, multiline)),
+        // resolved through format detection before constructing the reader.
+        Auto => unreachable!(),
+        Simple => Box::new(PTBReader::with_formatter(
+            input,
+            PTBFormatter::new(PTBFormat::Simple).root_features(root_features),
+            multiline,
+        )),
+        PTB => Box::new(PTBReader::with_formatter(
+            input,
+            PTBFormatter::new(PTBFormat::PTB).root_features(root_features),
+            multiline,
+        )),
+        TueBa => Box::new(PTBReader::with_formatter(
+            input,
+            PTBFormatter::new(PTBFormat::TueBa).root_features(root_features),
+            multiline,
+        )),
     }
 }
 
-fn get_writer<'a, W>(out_format: OutFormat, writer: W) -> Box<WriteTree + 'a>
+fn get_writer<'a, W>(
+    out_format: OutFormat,
+    writer: W,
+    root_features: bool,
+) -> Box<dyn WriteTree + 'a>
 where
     W: Write + 'a,
 {
+    let formatter = |format| PTBFormatter::new(format).root_features(root_features);
     use OutFormat::*;
     match out_format {
         Absolute | Conllx | Relative => Box::new(Writer::new(writer)),
-        PTB => Box::new(PTBWriter::new(writer, PTBFormat::PTB)),
-        Simple => Box::new(PTBWriter::new(writer, PTBFormat::Simple)),
-        TueBa => Box::new(PTBWriter::new(writer, PTBFormat::TueBa)),
+        PTB => Box::new(PTBWriter::with_formatter(writer, formatter(PTBFormat::PTB))),
+        Simple => Box::new(PTBWriter::with_formatter(
+            writer,
+            formatter(PTBFormat::Simple),
+        )),
+        TueBa => Box::new(PTBWriter::with_formatter(
+            writer,
+            formatter(PTBFormat::TueBa),
+        )),
     }
 }
 
@@ -211,6 +411,7 @@ static DEFAULT_CLAP_SETTINGS: &[AppSettings] = &[
     AppSettings::UnifiedHelpMessage,
 ];
 
+static COUNT: &str = "COUNT";
 static INPUT: &str = "INPUT";
 static OUTPUT: &str = "OUTPUT";
 static IN_FORMAT: &str = "IN_FORMAT";
@@ -219,9 +420,14 @@ static MULTILINE: &str = "MULTILINE";
 static INSERTION_LABEL: &str = "INSERTION_LABEL";
 static INSERTION_SET: &str = "INSERTION_SET";
 static FILTER_SET: &str = "FILTER_SET";
+static FLUSH_EVERY: &str = "FLUSH_EVERY";
 static PARENT: &str = "PARENT";
 static PROJECTIVIZE: &str = "PROJECTIVIZE";
 static REMOVE_DUMMIES: &str = "REMOVE_DUMMIES";
+static SET_FEATURE: &str = "SET_FEATURE";
+static SKIP_ERRORS: &str = "SKIP_ERRORS";
+static SKIP_IF_FEATURE: &str = "SKIP_IF_FEATURE";
+static STATS: &str = "STATS";
 
 fn build<'a, 'b>() -> App<'a, 'b> {
     App::new("lumberjack-convert")
@@ -231,15 +437,20 @@ fn build<'a, 'b>() -> App<'a, 'b> {
             Arg::with_name(INPUT)
                 .long("input_file")
                 .takes_value(true)
-                .help("Input file"),
+                .help("Input file, decompressed if the name ends with .gz"),
         )
         .arg(
             Arg::with_name(IN_FORMAT)
                 .long("input_format")
                 .takes_value(true)
-                .possible_values(&["absolute", "negra", "ptb", "relative", "simple", "tueba"])
+                .possible_values(&[
+                    "absolute", "auto", "negra", "ptb", "relative", "simple", "tueba",
+                ])
                 .default_value("tueba")
-                .help("Input format:"),
+                .help(
+                    "Input format. auto distinguishes NEGRA and bracketed trees, bracketed \
+                     trees are read as simple with the detected line format.",
+                ),
         )
         .arg(
             Arg::with_name(MULTILINE)
@@ -250,7 +461,7 @@ fn build<'a, 'b>() -> App<'a, 'b> {
             Arg::with_name(OUTPUT)
                 .long("output_file")
                 .takes_value(true)
-                .help("Output file"),
+                .help("Output file, compressed if the name ends with .gz"),
         )
         .arg(
             Arg::with_name(OUT_FORMAT)
@@ -260,6 +471,15 @@ fn build<'a, 'b>() -> App<'a, 'b> {
                 .default_value("simple")
                 .help("Output format:"),
         )
+        .arg(
+            Arg::with_name(FLUSH_EVERY)
+                .long("flush_every")
+                .takes_value(true)
+                .help(
+                    "Flush the output after every N trees. Compressed output is buffered \
+                     until flushed. Rejected for CONLLX output, which can't be flushed.",
+                ),
+        )
         .arg(
             Arg::with_name(PARENT)
                 .long("parent")
@@ -293,4 +513,38 @@ fn build<'a, 'b>() -> App<'a, 'b> {
                 .long("remove_dummies")
                 .help("Remove nodes with DUMMY label as introduced by incorrect tag sequences."),
         )
+        .arg(
+            Arg::with_name(SKIP_IF_FEATURE)
+                .long("skip_if_feature")
+                .takes_value(true)
+                .value_name("KEY")
+                .help(
+                    "Write trees whose root has the feature KEY without applying any \
+                     transformations. Requires bracketed input and output, the features of \
+                     the root are read and written.",
+                ),
+        )
+        .arg(
+            Arg::with_name(SET_FEATURE)
+                .long("set_feature")
+                .takes_value(true)
+                .value_name("KEY")
+                .help(
+                    "Mark transformed trees with the root feature KEY:true, e.g. to resume \
+                     with --skip_if_feature KEY. Requires bracketed input and output, the \
+                     features of the root are read and written.",
+                ),
+        )
+        .arg(Arg::with_name(SKIP_ERRORS).long("skip_errors").help(
+            "Skip trees that can't be read instead of aborting. The number of skipped trees \
+             is reported.",
+        ))
+        .arg(Arg::with_name(COUNT).long("count").help(
+            "Only read trees and print their number and the elapsed time to stdout. No \
+             transformations are applied and no trees are written, output options are ignored.",
+        ))
+        .arg(Arg::with_name(STATS).long("stats").help(
+            "Print corpus statistics to stdout instead of converting. No trees are written, \
+             output options are ignored.",
+        ))
 }
@@ -1,9 +1,20 @@
 use std::fmt;
 use std::mem;
 
+use crate::Features;
+
 /// Struct representing an edge in a constituency Tree.
-#[derive(Clone, Debug, PartialEq, Eq)]
-pub struct Edge(Option<String>);
+///
+/// An edge has an optional label, e.g. a grammatical function, and optional `Features` for
+/// further data such as confidence scores or secondary edge markers. Features of edges are
+/// considered in comparisons but are not written by any writer, bracketed formats only write
+/// edge labels and CONLLX has no edges. Trees written with edge features are therefore read
+/// back without them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Edge {
+    label: Option<String>,
+    features: Option<Features>,
+}
 
 // implementing display comes in handy for debugging using Dot Graphs
 impl fmt::Display for Edge {
@@ -17,7 +28,7 @@ impl fmt::Display for Edge {
 
 impl Edge {
     pub fn label(&self) -> Option<&str> {
-        self.0.as_ref().map(String::as_ref)
+        self.label.as_ref().map(String::as_ref)
     }
 
     pub fn set_label<S>(&mut self, new_label: Option<S>) -> Option<String>
@@ -25,7 +36,26 @@ impl Edge {
         S: Into<String>,
     {
         let new_label = new_label.map(Into::into);
-        mem::replace(&mut self.0, new_label)
+        mem::replace(&mut self.label, new_label)
+    }
+
+    /// Get this `Edge`'s `Features`.
+    pub fn features(&self) -> Option<&Features> {
+        self.features.as_ref()
+    }
+
+    /// Get this `Edge`'s `Features` mutably.
+    ///
+    /// This method initializes the features if they are `None`.
+    pub fn features_mut(&mut self) -> &mut Features {
+        self.features.get_or_insert_with(Features::default)
+    }
+
+    /// Set this `Edge`'s `Features`.
+    ///
+    /// Returns the replaced value.
+    pub fn set_features(&mut self, features: Option<Features>) -> Option<Features> {
+        mem::replace(&mut self.features, features)
     }
 }
 
@@ -34,12 +64,56 @@ where
     S: Into<String>,
 {
     fn from(label: Option<S>) -> Edge {
-        Edge(label.map(Into::into))
+        Edge {
+            label: label.map(Into::into),
+            features: None,
+        }
     }
 }
 
-impl Default for Edge {
-    fn default() -> Edge {
-        Edge(None)
+#[cfg(test)]
+mod tests {
+    use conllx::graph::Sentence;
+
+    use super::Edge;
+    use crate::io::PTBFormat;
+    use crate::Features;
+
+    #[test]
+    fn features() {
+        let mut edge = Edge::from(Some("SB"));
+        assert!(edge.features().is_none());
+        assert_eq!(edge, Edge::from(Some("SB")));
+
+        edge.features_mut().insert("secondary", Some("OA"));
+        assert_eq!(edge.features(), Some(&Features::from("secondary:OA")));
+        assert_ne!(edge, Edge::from(Some("SB")));
+        assert_eq!(edge.to_string(), "SB");
+
+        assert_eq!(
+            edge.set_features(None),
+            Some(Features::from("secondary:OA"))
+        );
+        assert_eq!(edge, Edge::from(Some("SB")));
+        assert_eq!(Edge::default().features(), None);
+    }
+
+    #[test]
+    fn features_are_not_written() {
+        let input = "(S:-- (NP:SB (NN:HD dog)) (VP:HD (VBZ:HD barks)))";
+        let tree = PTBFormat::TueBa.string_to_tree(input).unwrap();
+        let mut with_features = tree.clone();
+        let edges = with_features.graph().edge_indices().collect::<Vec<_>>();
+        for edge in edges {
+            with_features[edge]
+                .features_mut()
+                .insert("secondary", Some("OA"));
+        }
+        assert_ne!(with_features, tree);
+
+        let written = PTBFormat::TueBa.tree_to_string(&with_features).unwrap();
+        assert_eq!(written, PTBFormat::TueBa.tree_to_string(&tree).unwrap());
+        assert_eq!(PTBFormat::TueBa.string_to_tree(&written).unwrap(), tree);
+        assert_eq!(Sentence::from(&with_features), Sentence::from(&tree));
     }
 }
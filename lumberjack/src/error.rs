@@ -0,0 +1,148 @@
+use std::fmt;
+
+use failure::Fail;
+use petgraph::prelude::NodeIndex;
+
+/// Errors with a distinguishable kind.
+///
+/// Functions in this crate return `failure::Error`, errors of these kinds can be recovered
+/// through `Error::downcast_ref::<LumberjackError>()`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LumberjackError {
+    /// A `Terminal` without parent was found where a parent is required.
+    TerminalWithoutParent { terminal: String },
+    /// A node other than the root has no incoming edge.
+    NodeWithoutParent { node: String },
+    /// A `Terminal` was found above another node.
+    TerminalDominatingNonTerminal { terminal: String },
+    /// A `Terminal` was found as the parent of a node while climbing the tree.
+    TerminalAsParent { terminal: String },
+    /// The number of provided part-of-speech tags does not match the number of terminals.
+    PosCountMismatch { n_tags: usize, n_terminals: usize },
+    /// The number of provided forms does not match the number of terminals.
+    FormCountMismatch { n_forms: usize, n_terminals: usize },
+    /// A closing bracket without matching opening bracket.
+    ///
+    /// `line` and `column` are the 1-based position of the closing bracket.
+    UnexpectedCloseBracket { line: usize, column: usize },
+    /// A tree ended with `depth` brackets still open.
+    ///
+    /// `line` and `column` are the 1-based position of the innermost unclosed bracket.
+    UnterminatedTree {
+        line: usize,
+        column: usize,
+        depth: usize,
+    },
+    /// A tree with crossing brackets was passed to a writer that can only represent continuous
+    /// constituents.
+    ///
+    /// `nodes` holds the labels of the `NonTerminal`s covering discontinuous spans.
+    CrossingBrackets { nodes: Vec<String> },
+}
+
+impl Fail for LumberjackError {}
+
+impl fmt::Display for LumberjackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use LumberjackError::*;
+        match self {
+            TerminalWithoutParent { terminal } => {
+                write!(f, "Terminal without parent:\n{}", terminal)
+            }
+            NodeWithoutParent { node } => {
+                write!(f, "Non-root node without incoming edge: {}", node)
+            }
+            TerminalDominatingNonTerminal { .. } => write!(f, "Terminal dominating NT."),
+            TerminalAsParent { terminal } => write!(f, "Terminal as parent: {}", terminal),
+            PosCountMismatch {
+                n_tags,
+                n_terminals,
+            } => {
+                if n_tags < n_terminals {
+                    write!(f, "Not enough POS tags were provided")
+                } else {
+                    write!(f, "Number of POS tags is greater than number of terminals.")
+                }
+            }
+            FormCountMismatch {
+                n_forms,
+                n_terminals,
+            } => write!(
+                f,
+                "Number of forms ({}) doesn't match number of terminals ({}).",
+                n_forms, n_terminals
+            ),
+            UnexpectedCloseBracket { line, column } => write!(
+                f,
+                "Unexpected closing bracket in line {}, column {}.",
+                line, column
+            ),
+            UnterminatedTree {
+                line,
+                column,
+                depth,
+            } => write!(
+                f,
+                "Unterminated tree, {} bracket(s) still open. Innermost unclosed bracket in line \
+                 {}, column {}.",
+                depth, line, column
+            ),
+            CrossingBrackets { nodes } => write!(
+                f,
+                "Can't linearize tree with crossing brackets, discontinuous nodes: {}",
+                nodes.join(", ")
+            ),
+        }
+    }
+}
+
+/// Structural errors in the graph of a `Tree`.
+///
+/// Returned by `Tree::find_structural_errors`, nodes are identified by their indices.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StructuralError {
+    /// A node has an edge to itself.
+    SelfLoop { node: NodeIndex },
+    /// A node has more than one parent.
+    MultipleParents {
+        node: NodeIndex,
+        parents: Vec<NodeIndex>,
+    },
+    /// The root has a parent.
+    RootWithParent { parent: NodeIndex },
+    /// Nodes that can't be reached from the root.
+    Disconnected { nodes: Vec<NodeIndex> },
+}
+
+impl Fail for StructuralError {}
+
+impl fmt::Display for StructuralError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use StructuralError::*;
+        match self {
+            SelfLoop { node } => write!(f, "Self-loop at node {}.", node.index()),
+            MultipleParents { node, parents } => write!(
+                f,
+                "Node {} has multiple parents: {}.",
+                node.index(),
+                join_indices(parents)
+            ),
+            RootWithParent { parent } => {
+                write!(f, "Root is attached to parent {}.", parent.index())
+            }
+            Disconnected { nodes } => write!(
+                f,
+                "Nodes not reachable from the root: {}.",
+                join_indices(nodes)
+            ),
+        }
+    }
+}
+
+fn join_indices(nodes: &[NodeIndex]) -> String {
+    nodes
+        .iter()
+        .map(|node| node.index().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
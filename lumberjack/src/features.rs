@@ -1,4 +1,5 @@
-use std::collections::btree_map::BTreeMap;
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::fmt;
 use std::iter::FromIterator;
 
 use itertools::Itertools;
@@ -7,11 +8,24 @@ use itertools::Itertools;
 ///
 /// These can be e.g. morphological features on `Terminal`
 /// or syntactic-semantic labels on `NonTerminal` nodes.
+///
+/// Features are kept sorted by key, inserting an existing key replaces its value. Comparison and
+/// the `Display` representation are therefore independent of the order in which features were
+/// inserted and no normalization is necessary before comparing.
+///
+/// Multi-valued keys are supported through `Features::push`, which keeps the values of a key in
+/// the order they were pushed, `Features::get_val` returns the first value of a key.
 #[derive(Clone, Default, Debug, Eq, PartialEq)]
 pub struct Features {
     map: BTreeMap<String, Option<String>>,
+    // values pushed for keys that were already present
+    additional: BTreeMap<String, Vec<Option<String>>>,
 }
 
+/// Parse features from `key:value` pairs delimited by `"|"`.
+///
+/// Only the first `":"` of a pair separates key and value, e.g. `edeps:4:nsubj` is read as the
+/// key `edeps` with the value `4:nsubj`. If a key is repeated, the last value is kept.
 impl<S> From<S> for Features
 where
     S: AsRef<str>,
@@ -21,7 +35,7 @@ where
             .split('|')
             .filter(|s| !s.is_empty())
             .map(|f| {
-                let mut parts = f.split(':');
+                let mut parts = f.splitn(2, ':');
                 let k = parts.next().unwrap();
                 let v = parts.next();
                 (k, v)
@@ -30,6 +44,7 @@ where
     }
 }
 
+/// Collect features, if a key is repeated, the last value is kept.
 impl<K, V> FromIterator<(K, Option<V>)> for Features
 where
     K: Into<String>,
@@ -39,11 +54,11 @@ where
     where
         T: IntoIterator<Item = (K, Option<V>)>,
     {
-        let map = iter
-            .into_iter()
-            .map(|(k, v)| (k.into(), v.map(Into::into)))
-            .collect();
-        Features { map }
+        let mut features = Features::new();
+        for (k, v) in iter {
+            features.insert(k, v);
+        }
+        features
     }
 }
 
@@ -54,51 +69,120 @@ impl Features {
     }
 
     /// Get a slice of the backing `BTreeMap`.
+    ///
+    /// The map contains the first value of each key, further values added through
+    /// `Features::push` are only accessible through `Features::get_all`. Features are modified
+    /// through `Features::insert`, `Features::push`, `Features::remove` and `Features::retain`,
+    /// which keep all values of a key consistent.
     pub fn inner(&self) -> &BTreeMap<String, Option<String>> {
         &self.map
     }
 
     /// Get the backing `BTreeMap` mutably.
+    ///
+    /// The map only holds the first value of each key, further values added through
+    /// `Features::push` are discarded before the map is returned.
+    #[deprecated(note = "use `Features::insert`, `Features::remove` or `Features::retain`")]
     pub fn inner_mut(&mut self) -> &mut BTreeMap<String, Option<String>> {
+        self.additional.clear();
         &mut self.map
     }
 
     /// Insert `key` with `val`.
     ///
-    /// If `key` was present, the replaced value is returned, otherwise `None`.
+    /// If `key` was present, all of its values are replaced. The replaced first value is
+    /// returned, otherwise `None`.
     pub fn insert<K, V>(&mut self, key: K, val: Option<V>) -> Option<String>
     where
         K: Into<String>,
         V: Into<String>,
     {
-        self.map
-            .insert(key.into(), val.map(Into::into))
-            .and_then(|v| v)
+        let key = key.into();
+        self.additional.remove(&key);
+        self.map.insert(key, val.map(Into::into)).and_then(|v| v)
+    }
+
+    /// Add `val` to the values of `key`.
+    ///
+    /// Unlike `Features::insert`, existing values are kept, `val` is appended even if it is
+    /// already present.
+    pub fn push<K, V>(&mut self, key: K, val: Option<V>)
+    where
+        K: Into<String>,
+        V: Into<String>,
+    {
+        let key = key.into();
+        let val = val.map(Into::into);
+        match self.map.entry(key) {
+            Entry::Occupied(entry) => {
+                let key = entry.key().clone();
+                self.additional.entry(key).or_default().push(val);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(val);
+            }
+        }
     }
 
     /// Get the value associated with `key`.
+    ///
+    /// If `key` has multiple values, the first value is returned.
     pub fn get_val(&self, key: &str) -> Option<&str> {
+        self.map.get(key).and_then(|v| v.as_deref())
+    }
+
+    /// Get all values associated with `key`.
+    ///
+    /// Values are returned in the order they were added, the vector is empty if `key` is not
+    /// present.
+    pub fn get_all(&self, key: &str) -> Vec<Option<&str>> {
         self.map
             .get(key)
-            .and_then(|v| v.as_ref().map(String::as_str))
+            .into_iter()
+            .chain(self.additional.get(key).into_iter().flatten())
+            .map(Option::as_deref)
+            .collect()
     }
 
     /// Remove the tuple associated with `key`.
     ///
-    /// Returns `None` if `key` was not found.
+    /// All values of `key` are removed, the first value is returned. Returns `None` if `key`
+    /// was not found.
     pub fn remove(&mut self, key: &str) -> Option<String> {
-        if let Some(rm) = self.map.remove(key) {
-            rm
-        } else {
-            None
+        self.additional.remove(key);
+        self.map.remove(key)?
+    }
+
+    /// Retain only the key-value pairs for which `f` returns `true`.
+    ///
+    /// `f` is called once for each value of multi-valued keys, the retained values keep their
+    /// order. If the first value of a key is removed, the next retained value takes its place.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str, Option<&str>) -> bool,
+    {
+        let map = std::mem::take(&mut self.map);
+        let mut additional = std::mem::take(&mut self.additional);
+        for (key, val) in map {
+            let rest = additional.remove(&key).unwrap_or_default();
+            for val in std::iter::once(val).chain(rest) {
+                if f(&key, val.as_deref()) {
+                    self.push(key.as_str(), val);
+                }
+            }
         }
     }
 }
 
-impl ToString for Features {
-    fn to_string(&self) -> String {
-        self.map
+impl fmt::Display for Features {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = self
+            .map
             .iter()
+            .flat_map(|(k, v)| {
+                let additional = self.additional.get(k).into_iter().flatten();
+                std::iter::once(v).chain(additional).map(move |v| (k, v))
+            })
             .map(|(k, v)| {
                 if let Some(v) = v {
                     format!("{}:{}", k, v)
@@ -106,7 +190,8 @@ impl ToString for Features {
                     k.to_owned()
                 }
             })
-            .join("|")
+            .join("|");
+        write!(f, "{}", s)
     }
 }
 
@@ -114,6 +199,96 @@ impl ToString for Features {
 mod test {
     use super::Features;
 
+    #[test]
+    fn insertion_order() {
+        let mut f1 = Features::new();
+        f1.insert("number", Some("sg"));
+        f1.insert("case", Some("nom"));
+        let mut f2 = Features::new();
+        f2.insert("case", Some("nom"));
+        f2.insert("number", Some("sg"));
+        assert_eq!(f1, f2);
+        assert_eq!(f1.to_string(), f2.to_string());
+        assert_eq!(f1.to_string(), "case:nom|number:sg");
+    }
+
+    #[test]
+    fn push() {
+        let mut features = Features::from("secedge:refvc:500|case:nom");
+        features.push("secedge", Some("refint:501"));
+        features.push("secedge", Some("refvc:500"));
+        features.push("head", None::<String>);
+        assert_eq!(
+            features.get_all("secedge"),
+            vec![Some("refvc:500"), Some("refint:501"), Some("refvc:500")]
+        );
+        assert_eq!(features.get_val("secedge"), Some("refvc:500"));
+        assert_eq!(features.get_all("head"), vec![None]);
+        assert!(features.get_all("nonsense").is_empty());
+        assert_eq!(
+            features.to_string(),
+            "case:nom|head|secedge:refvc:500|secedge:refint:501|secedge:refvc:500"
+        );
+        assert_eq!(
+            Features::from(features.to_string()),
+            Features::from("case:nom|head|secedge:refvc:500")
+        );
+        assert_eq!(Features::from("a:1|a:2").get_all("a"), vec![Some("2")]);
+
+        assert_eq!(
+            features.insert("secedge", Some("refmod:502")),
+            Some("refvc:500".into())
+        );
+        assert_eq!(features.get_all("secedge"), vec![Some("refmod:502")]);
+        features.push("secedge", Some("refvc:500"));
+        assert_eq!(features.remove("secedge"), Some("refmod:502".into()));
+        assert!(features.get_all("secedge").is_empty());
+
+        let mut features = Features::new();
+        features.push("a", Some("1"));
+        features.push("a", Some("2"));
+        features.remove("a");
+        assert_eq!(features.to_string(), "");
+        assert!(features.get_all("a").is_empty());
+        assert_eq!(features, Features::new());
+
+        features.push("a", Some("1"));
+        features.push("a", Some("2"));
+        #[allow(deprecated)]
+        features.inner_mut().remove("a");
+        assert!(features.get_all("a").is_empty());
+        assert_eq!(features, Features::new());
+    }
+
+    #[test]
+    fn retain() {
+        let mut features = Features::from("secedge:refvc:500|case:nom|head|lemma:");
+        features.push("secedge", Some("refint:501"));
+        features.push("secedge", Some("refvc:502"));
+        features.retain(|_, val| val.is_some_and(|val| !val.is_empty()));
+        assert_eq!(
+            features.to_string(),
+            "case:nom|secedge:refvc:500|secedge:refint:501|secedge:refvc:502"
+        );
+
+        features.retain(|key, val| key != "secedge" || val != Some("refvc:500"));
+        assert_eq!(
+            features.get_all("secedge"),
+            vec![Some("refint:501"), Some("refvc:502")]
+        );
+        assert_eq!(features.get_val("secedge"), Some("refint:501"));
+
+        features.retain(|key, _| !key.starts_with("sec"));
+        assert_eq!(features, Features::from("case:nom"));
+    }
+
+    #[test]
+    fn value_with_colon() {
+        let features = Features::from("edeps:2:nsubj;4:nsubj|case");
+        assert_eq!(features.get_val("edeps"), Some("2:nsubj;4:nsubj"));
+        assert_eq!(features.to_string(), "case|edeps:2:nsubj;4:nsubj");
+    }
+
     #[test]
     fn features_test() {
         let mut features = Features::from("key:value|some_feature|another:one");
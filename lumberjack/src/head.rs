@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+
+use petgraph::prelude::NodeIndex;
+
+use crate::Tree;
+
+/// Feature key marking head children.
+pub(crate) static HEAD_FEATURE_KEY: &str = "head";
+
+/// Direction in which the children of a node are searched for the head.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HeadDirection {
+    /// Search from the leftmost child to the rightmost child.
+    LeftToRight,
+    /// Search from the rightmost child to the leftmost child.
+    RightToLeft,
+}
+
+#[derive(Clone, Debug)]
+struct HeadRule {
+    direction: HeadDirection,
+    labels: Vec<String>,
+}
+
+/// Head rules.
+///
+/// Head rules select the head child of a `NonTerminal` in the style of Collins' head rules.
+/// Each `NonTerminal` label is associated with a list of rules which are tried in order. A rule
+/// consists of a search direction and a priority list of labels. For each label in the priority
+/// list, the children are searched in the rule's direction, the first child with a matching label
+/// is the head.
+///
+/// If no rule matches, the first child in the direction of the label's first rule is the head.
+/// For labels without rules, the default direction is used.
+#[derive(Clone, Debug)]
+pub struct HeadRules {
+    rules: HashMap<String, Vec<HeadRule>>,
+    default_direction: HeadDirection,
+}
+
+impl HeadRules {
+    /// Construct head rules without any rules.
+    ///
+    /// The head of nodes without rules is the first child in `default_direction`.
+    pub fn new(default_direction: HeadDirection) -> Self {
+        HeadRules {
+            rules: HashMap::new(),
+            default_direction,
+        }
+    }
+
+    /// Add a rule for nodes labeled `label`.
+    ///
+    /// Rules for the same label are tried in the order they were added.
+    pub fn rule<S>(
+        mut self,
+        label: &str,
+        direction: HeadDirection,
+        labels: impl IntoIterator<Item = S>,
+    ) -> Self
+    where
+        S: Into<String>,
+    {
+        self.rules
+            .entry(label.to_owned())
+            .or_default()
+            .push(HeadRule {
+                direction,
+                labels: labels.into_iter().map(Into::into).collect(),
+            });
+        self
+    }
+
+    /// Get the head child of `node`.
+    ///
+    /// Returns `None` if `node` has no children.
+    pub fn head_child(&self, tree: &Tree, node: NodeIndex) -> Option<NodeIndex> {
+        let mut children = tree.children(node).collect::<Vec<_>>();
+        children.sort_by(|&c1, &c2| tree[c1].span().cmp(tree[c2].span()));
+        let reversed = children.iter().rev().cloned().collect::<Vec<_>>();
+        let ordered = |direction| match direction {
+            HeadDirection::LeftToRight => &children,
+            HeadDirection::RightToLeft => &reversed,
+        };
+
+        let rules = self.rules.get(tree[node].label());
+        for rule in rules.into_iter().flatten() {
+            for label in rule.labels.iter() {
+                let head = ordered(rule.direction)
+                    .iter()
+                    .find(|&&child| tree[child].label() == label);
+                if let Some(&head) = head {
+                    return Some(head);
+                }
+            }
+        }
+
+        let direction = rules
+            .and_then(|rules| rules.first())
+            .map(|rule| rule.direction)
+            .unwrap_or(self.default_direction);
+        ordered(direction).first().cloned()
+    }
+
+    /// Get the lexical head of `node`.
+    ///
+    /// The lexical head is the `Terminal` reached by following head children from `node`. The
+    /// lexical head of a `Terminal` is the terminal itself.
+    pub fn head_terminal(&self, tree: &Tree, node: NodeIndex) -> Option<NodeIndex> {
+        let mut cur = node;
+        while !tree[cur].is_terminal() {
+            cur = self.head_child(tree, cur)?;
+        }
+        Some(cur)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeadDirection, HeadRules};
+    use crate::io::PTBFormat;
+
+    #[test]
+    fn head_child() {
+        let tree = PTBFormat::Simple
+            .string_to_tree(
+                "(S (NP (DT the) (JJ big) (NN dog)) (VP (VBZ barks) (ADVP (RB loudly))))",
+            )
+            .unwrap();
+        let rules = HeadRules::new(HeadDirection::LeftToRight)
+            .rule("S", HeadDirection::LeftToRight, vec!["VP"])
+            .rule("NP", HeadDirection::RightToLeft, vec!["NN", "NNS"])
+            .rule("VP", HeadDirection::LeftToRight, vec!["VBZ", "VB"]);
+        let root = tree.root();
+        let vp = rules.head_child(&tree, root).unwrap();
+        assert_eq!(tree[vp].label(), "VP");
+        let barks = rules.head_terminal(&tree, root).unwrap();
+        assert_eq!(tree[barks].terminal().unwrap().form(), "barks");
+
+        let np = tree.children(root).find(|&c| c != vp).unwrap();
+        let dog = rules.head_terminal(&tree, np).unwrap();
+        assert_eq!(tree[dog].terminal().unwrap().form(), "dog");
+
+        // without rules, the first child in the default direction is the head
+        let rules = HeadRules::new(HeadDirection::RightToLeft);
+        let head = rules.head_terminal(&tree, np).unwrap();
+        assert_eq!(tree[head].terminal().unwrap().form(), "dog");
+        let head = rules.head_terminal(&tree, root).unwrap();
+        assert_eq!(tree[head].terminal().unwrap().form(), "loudly");
+        assert_eq!(rules.head_terminal(&tree, dog), Some(dog));
+    }
+}
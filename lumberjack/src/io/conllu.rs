@@ -0,0 +1,223 @@
+use std::io::{BufRead, Lines};
+
+use failure::Error;
+use petgraph::prelude::StableGraph;
+
+use crate::{Node, NonTerminal, Projectivity, Span, Terminal, Tree};
+
+/// Feature key of the enhanced dependencies of a `Terminal`.
+pub(crate) static ENHANCED_DEPS_FEATURE_KEY: &str = "edeps";
+
+/// Iterator over flat trees in a CoNLL-U file.
+///
+/// Each sentence is read into a `Tree` with a single `NonTerminal` root labeled `ROOT` that
+/// directly dominates all terminals. For each token, a `Terminal` is constructed:
+///
+/// * The universal part-of-speech tag is used as part-of-speech tag. If it is `_`, the
+///   language-specific tag is used.
+/// * Morphological features `Key=Value` are read into the terminal's `Features`.
+/// * Enhanced dependencies are stored under the feature key `edeps` as `head:relation` pairs
+///   delimited by `";"`, e.g. `edeps:4:nsubj;6:nsubj:xsubj`. The feature is not set if the column
+///   is `_`.
+///
+/// The identifier of a `# sent_id = <id>` comment is read as the tree's sentence identifier,
+/// see `Tree::sentence_id`. Other comments, multiword tokens and empty nodes are skipped. The
+/// basic dependencies and the MISC column are not read.
+pub struct ConlluReader<R> {
+    inner: Lines<R>,
+    line_no: usize,
+}
+
+impl<R> ConlluReader<R>
+where
+    R: BufRead,
+{
+    /// Construct a new reader.
+    pub fn new(read: R) -> Self {
+        ConlluReader {
+            inner: read.lines(),
+            line_no: 0,
+        }
+    }
+}
+
+impl<R> Iterator for ConlluReader<R>
+where
+    R: BufRead,
+{
+    type Item = Result<Tree, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut terminals = Vec::new();
+        let mut sentence_id = None;
+        for line in self.inner.by_ref() {
+            self.line_no += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err.into())),
+            };
+            if line.trim().is_empty() {
+                if terminals.is_empty() {
+                    continue;
+                }
+                return Some(Ok(flat_tree(terminals, sentence_id)));
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(id) = parse_sentence_id(comment) {
+                    sentence_id = Some(id.to_owned());
+                }
+                continue;
+            }
+            match process_token(&line, terminals.len()) {
+                Ok(Some(terminal)) => terminals.push(terminal),
+                Ok(None) => (),
+                Err(err) => {
+                    return Some(Err(format_err!("Line {}: {}", self.line_no, err)));
+                }
+            }
+        }
+        if terminals.is_empty() {
+            None
+        } else {
+            Some(Ok(flat_tree(terminals, sentence_id)))
+        }
+    }
+}
+
+// Read the token in `line` as the terminal at index `idx`.
+//
+// Returns `None` for multiword tokens and empty nodes.
+fn process_token(line: &str, idx: usize) -> Result<Option<Terminal>, Error> {
+    let fields = line.split('\t').collect::<Vec<_>>();
+    if fields.len() != 10 {
+        return Err(format_err!(
+            "Expected 10 columns, found {}: {}",
+            fields.len(),
+            line
+        ));
+    }
+    if fields[0].contains('-') || fields[0].contains('.') {
+        return Ok(None);
+    }
+
+    let pos = if fields[3] != "_" {
+        fields[3]
+    } else {
+        fields[4]
+    };
+    let mut terminal = Terminal::new(fields[1], pos, idx);
+    if fields[2] != "_" {
+        terminal.set_lemma(Some(fields[2]));
+    }
+    if fields[5] != "_" {
+        for feature in fields[5].split('|') {
+            let mut parts = feature.splitn(2, '=');
+            let key = parts.next().unwrap();
+            terminal.features_mut().insert(key, parts.next());
+        }
+    }
+    if fields[8] != "_" {
+        terminal
+            .features_mut()
+            .insert(ENHANCED_DEPS_FEATURE_KEY, Some(fields[8].replace('|', ";")));
+    }
+    Ok(Some(terminal))
+}
+
+// Get the identifier from a `sent_id = <id>` comment.
+fn parse_sentence_id(comment: &str) -> Option<&str> {
+    let id = comment
+        .trim_start()
+        .strip_prefix("sent_id")?
+        .trim_start()
+        .strip_prefix('=')?
+        .trim();
+    if id.is_empty() {
+        None
+    } else {
+        Some(id)
+    }
+}
+
+fn flat_tree(terminals: Vec<Terminal>, sentence_id: Option<String>) -> Tree {
+    let mut graph = StableGraph::new();
+    let n_terminals = terminals.len();
+    let root = graph.add_node(Node::NonTerminal(NonTerminal::new(
+        "ROOT",
+        Span::new_continuous(0, n_terminals),
+    )));
+    for terminal in terminals {
+        let terminal = graph.add_node(Node::Terminal(terminal));
+        graph.add_edge(root, terminal, Default::default());
+    }
+    let mut tree = Tree::new(graph, n_terminals, root, Projectivity::Projective);
+    tree.set_sentence_id(sentence_id);
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConlluReader;
+    use crate::Features;
+
+    static SENTENCES: &str = "# sent_id = 1
+# text = They buy and sell books.
+1\tThey\tthey\tPRON\tPRP\tCase=Nom|Number=Plur\t2\tnsubj\t2:nsubj|4:nsubj\t_
+2\tbuy\tbuy\tVERB\tVBP\tNumber=Plur|Person=3|Tense=Pres\t0\troot\t0:root\t_
+3\tand\tand\tCCONJ\tCC\t_\t4\tcc\t4:cc\t_
+4\tsell\tsell\tVERB\tVBP\tNumber=Plur|Person=3|Tense=Pres\t2\tconj\t2:conj:and\t_
+5-6\tbooks.\t_\t_\t_\t_\t_\t_\t_\t_
+5\tbooks\tbook\tNOUN\tNNS\tNumber=Plur\t2\tobj\t2:obj|4:obj\tSpaceAfter=No
+5.1\tbought\tbuy\tVERB\tVBD\t_\t_\t_\t4:conj\t_
+6\t.\t.\tPUNCT\t.\t_\t2\tpunct\t_\t_
+
+1\tYes\t_\t_\tUH\t_\t0\troot\t_\t_
+";
+
+    #[test]
+    fn read() {
+        let mut reader = ConlluReader::new(SENTENCES.as_bytes());
+        let tree = reader.next().unwrap().unwrap();
+        assert_eq!(tree.n_terminals(), 6);
+        assert_eq!(tree.sentence_id(), Some("1"));
+        assert_eq!(tree[tree.root()].label(), "ROOT");
+        assert_eq!(tree.children(tree.root()).count(), 6);
+        tree.validate().unwrap();
+
+        let they = tree.nth_terminal(0).unwrap();
+        assert_eq!(they.form(), "They");
+        assert_eq!(they.label(), "PRON");
+        assert_eq!(they.lemma(), Some("they"));
+        assert_eq!(
+            they.features(),
+            Some(&Features::from(
+                "Case:Nom|Number:Plur|edeps:2:nsubj;4:nsubj"
+            ))
+        );
+        let and = tree.nth_terminal(2).unwrap();
+        assert_eq!(and.features(), Some(&Features::from("edeps:4:cc")));
+        let books = tree.nth_terminal(4).unwrap();
+        assert_eq!(books.form(), "books");
+        assert_eq!(
+            books.features().unwrap().get_val("edeps"),
+            Some("2:obj;4:obj")
+        );
+        let punct = tree.nth_terminal(5).unwrap();
+        assert_eq!(punct.features(), None);
+
+        let tree = reader.next().unwrap().unwrap();
+        assert_eq!(tree.n_terminals(), 1);
+        assert_eq!(tree.sentence_id(), None);
+        let yes = tree.nth_terminal(0).unwrap();
+        assert_eq!(yes.label(), "UH");
+        assert_eq!(yes.lemma(), None);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn malformed() {
+        let mut reader = ConlluReader::new("1\tYes\t_\t_\tUH\t_\t0\troot\n".as_bytes());
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(err.to_string().starts_with("Line 1: Expected 10 columns"));
+    }
+}
@@ -7,6 +7,7 @@ use failure::Error;
 
 use crate::io::encode::{AbsoluteAncestor, ConversionResult, RelativeAncestor};
 use crate::io::{AbsoluteEncoding, Decode, RelativeEncoding};
+use crate::tree::is_placeholder_pos;
 use crate::tree_modification::TreeOps;
 use crate::{Terminal, Tree, WriteTree};
 use std::io::Write;
@@ -24,12 +25,88 @@ where
 ///
 /// Creates a `Token` for each `Terminal` in the tree.
 /// Form, POS and Features are carried over.
+///
+/// Tokens are ordered by the spans of their terminals. Token IDs are contiguous and start at
+/// `1`, regardless of gaps in the terminal indices, e.g. in trees with removed terminals.
 pub trait ToConllx {
     /// Nonconsuming conversion to CONLLX.
+    ///
+    /// Forms containing whitespace are kept as they are, i.e. `MultiwordForms::Keep`.
     fn to_conllx(&self) -> Sentence;
+
+    /// Nonconsuming conversion to CONLLX configured through `options`.
+    ///
+    /// Returns `Error` if `options` reject multiword forms and a form contains whitespace or if
+    /// `options` require tags and a terminal lacks a part-of-speech tag.
+    fn to_conllx_with(&self, options: &ConllxOptions) -> Result<Sentence, Error>;
+}
+
+/// Options for the conversion to CONLLX through `ToConllx::to_conllx_with`.
+///
+/// By default, multiword forms are kept, no index feature is added and terminals without
+/// part-of-speech tag are written with the placeholder tag. This is the output of
+/// `ToConllx::to_conllx`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ConllxOptions {
+    multiword: MultiwordForms,
+    index_feature: Option<String>,
+    require_tags: bool,
+}
+
+impl ConllxOptions {
+    /// Construct the default options.
+    pub fn new() -> Self {
+        ConllxOptions::default()
+    }
+
+    /// Set the handling of forms containing whitespace.
+    pub fn multiword(mut self, multiword: MultiwordForms) -> Self {
+        self.multiword = multiword;
+        self
+    }
+
+    /// Add the index of each terminal as feature `feature`.
+    ///
+    /// The index is the lower bound of the terminal's span. This makes tokens traceable to the
+    /// terminals of the tree if the indices are not contiguous.
+    pub fn index_feature(mut self, feature: impl Into<String>) -> Self {
+        self.index_feature = Some(feature.into());
+        self
+    }
+
+    /// Return an error for terminals without part-of-speech tag, see `Tree::is_fully_tagged`.
+    pub fn require_tags(mut self, require_tags: bool) -> Self {
+        self.require_tags = require_tags;
+        self
+    }
+}
+
+/// Handling of forms containing whitespace in the conversion to CONLLX.
+///
+/// A form containing whitespace can't be represented by a single CONLLX token without making
+/// the sentence ambiguous to readers splitting on whitespace.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum MultiwordForms {
+    /// Keep the form as it is.
+    #[default]
+    Keep,
+    /// Replace each run of whitespace in forms and lemmas by the separator.
+    ///
+    /// CONLLX treebanks conventionally use `"_"`, e.g. `Tribunal_Supremo`.
+    Join(String),
+    /// Split the form on whitespace into multiple tokens.
+    ///
+    /// Each token gets the part-of-speech tag and features of the terminal. If the lemma splits
+    /// into the same number of parts, the parts are distributed over the tokens, otherwise each
+    /// token gets the complete lemma.
+    Split,
+    /// Return an error.
+    Reject,
 }
 
 /// Conversion Trait from CONLLX to `Tree`.
+///
+/// Sentences without tokens can't be converted, trees contain at least one terminal.
 pub trait TryFromConllx: Sized {
     /// Attempt to construct a tree from labels annotated on a `Sentence`.
     ///
@@ -78,6 +155,9 @@ impl TryFromConllx for Tree {
             };
             encoding.push((ancestor, unary_chain))
         }
+        if terminals.is_empty() {
+            return Err(format_err!("Can't construct tree from empty sentence."));
+        }
         let mut tree = Tree::decode(AbsoluteEncoding::new(encoding), terminals);
         tree.restore_unary_chains("_")?;
         Ok(tree)
@@ -114,6 +194,9 @@ impl TryFromConllx for Tree {
             };
             encoding.push((ancestor, unary_chain))
         }
+        if terminals.is_empty() {
+            return Err(format_err!("Can't construct tree from empty sentence."));
+        }
         let encoding = match AbsoluteEncoding::try_from_relative(RelativeEncoding::new(encoding)) {
             ConversionResult::Success(encoding) => encoding,
             ConversionResult::Error(err) => err.fix(),
@@ -128,58 +211,160 @@ impl ToConllx for Tree {
     fn to_conllx(&self) -> Sentence {
         self.into()
     }
+
+    fn to_conllx_with(&self, options: &ConllxOptions) -> Result<Sentence, Error> {
+        let mut terminals = self
+            .terminals()
+            .filter_map(|t| self[t].terminal())
+            .collect::<Vec<_>>();
+        terminals.sort_by_key(|terminal| terminal.span().lower());
+        if options.require_tags {
+            if let Some(terminal) = terminals.iter().find(|t| is_placeholder_pos(t.label())) {
+                return Err(format_err!(
+                    "Terminal without part-of-speech tag: {}",
+                    terminal.describe()
+                ));
+            }
+        }
+
+        let mut sentence = Sentence::new();
+        for terminal in terminals {
+            let token = match options.index_feature {
+                Some(ref feature) => {
+                    let mut features = terminal.features().cloned().unwrap_or_default();
+                    features.insert(feature.as_str(), Some(terminal.span().lower().to_string()));
+                    build_token(
+                        terminal.form().to_owned(),
+                        terminal.lemma().map(ToOwned::to_owned),
+                        terminal.label().to_owned(),
+                        Some(&features),
+                    )
+                }
+                None => Token::from(terminal),
+            };
+            if !token.form().contains(char::is_whitespace) {
+                sentence.push(token);
+                continue;
+            }
+            match options.multiword {
+                MultiwordForms::Keep => {
+                    sentence.push(token);
+                }
+                MultiwordForms::Join(ref separator) => {
+                    sentence.push(join_multiword(token, separator));
+                }
+                MultiwordForms::Split => {
+                    for token in split_multiword(token) {
+                        sentence.push(token);
+                    }
+                }
+                MultiwordForms::Reject => {
+                    return Err(format_err!(
+                        "Form of terminal {} contains whitespace: \"{}\"",
+                        terminal.span().lower(),
+                        token.form()
+                    ));
+                }
+            }
+        }
+        Ok(sentence)
+    }
+}
+
+fn join_multiword(mut token: Token, separator: &str) -> Token {
+    let join = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(separator);
+    let form = join(token.form());
+    token.set_form(form);
+    let lemma = token.lemma().map(join);
+    token.set_lemma(lemma);
+    token
 }
 
-impl<'a> From<&'a Terminal> for Token {
+fn split_multiword(token: Token) -> Vec<Token> {
+    let forms = token.form().split_whitespace().collect::<Vec<_>>();
+    let lemmas = token
+        .lemma()
+        .map(|lemma| lemma.split_whitespace().collect::<Vec<_>>())
+        .filter(|lemmas| lemmas.len() == forms.len());
+    forms
+        .iter()
+        .enumerate()
+        .map(|(idx, &form)| {
+            let mut part = token.clone();
+            part.set_form(form);
+            if let Some(lemmas) = lemmas.as_ref() {
+                part.set_lemma(Some(lemmas[idx]));
+            }
+            part
+        })
+        .collect()
+}
+
+impl From<&Terminal> for Token {
     fn from(terminal: &Terminal) -> Self {
-        let mut token = Token::new(terminal.form());
-        token.set_lemma(terminal.lemma());
-        token.set_pos(Some(terminal.label()));
-        if let Some(features) = terminal.features() {
-            token.set_features(Some(Features::from_string(features.to_string())));
-        }
-        token
+        build_token(
+            terminal.form().to_owned(),
+            terminal.lemma().map(ToOwned::to_owned),
+            terminal.label().to_owned(),
+            terminal.features(),
+        )
     }
 }
 
+/// Consuming conversion to CONLLX.
+///
+/// Produces the same `Sentence` as `ToConllx::to_conllx` but moves forms, lemmas and tags out
+/// of the tree instead of copying them.
 impl From<Tree> for Sentence {
-    fn from(mut tree: Tree) -> Self {
+    fn from(tree: Tree) -> Self {
         let mut tokens = Vec::with_capacity(tree.n_terminals());
 
-        let terminals = tree.terminals().collect::<Vec<_>>();
-        for terminal in terminals {
-            let terminal = tree[terminal].terminal_mut().unwrap();
-            let mut token = Token::new(terminal.set_form(String::new()));
+        for mut terminal in tree.into_terminals() {
+            let form = terminal.set_form(String::new());
             let lemma = terminal.set_lemma::<String>(None);
-            token.set_lemma(lemma);
-            token.set_pos(Some(terminal.set_label(String::new())));
-            if let Some(morph) = terminal.set_features(None) {
-                token.set_features(Some(Features::from_string(morph.to_string())));
-            }
+            let pos = terminal.set_label(String::new());
+            let features = terminal.set_features(None);
+            let token = build_token(form, lemma, pos, features.as_ref());
             tokens.push((token, terminal.span().lower()));
         }
-        tokens.sort_by(|t0, t1| t0.1.cmp(&t1.1));
-        let mut sentence = Sentence::new();
-        for (token, _) in tokens {
-            sentence.push(token);
-        }
-        sentence
+        tokens_to_sentence(tokens)
     }
 }
+
 impl<'a> From<&'a Tree> for Sentence {
     fn from(tree: &'a Tree) -> Self {
-        let mut tokens = tree
+        let tokens = tree
             .terminals()
             .filter_map(|t| tree[t].terminal().map(|t| (t.into(), t.span().lower())))
             .collect::<Vec<_>>();
+        tokens_to_sentence(tokens)
+    }
+}
 
-        tokens.sort_by(|t0, t1| t0.1.cmp(&t1.1));
-        let mut sentence = Sentence::new();
-        for (token, _) in tokens {
-            sentence.push(token);
-        }
-        sentence
+// shared by the borrowing and consuming conversions to keep their output identical.
+fn build_token(
+    form: String,
+    lemma: Option<String>,
+    pos: String,
+    features: Option<&crate::Features>,
+) -> Token {
+    let mut token = Token::new(form);
+    token.set_lemma(lemma);
+    token.set_pos(Some(pos));
+    if let Some(features) = features {
+        token.set_features(Some(Features::from_string(features.to_string())));
+    }
+    token
+}
+
+// sort tokens by their position in the sentence.
+fn tokens_to_sentence(mut tokens: Vec<(Token, usize)>) -> Sentence {
+    tokens.sort_by_key(|t| t.1);
+    let mut sentence = Sentence::new();
+    for (token, _) in tokens {
+        sentence.push(token);
     }
+    sentence
 }
 
 #[cfg(test)]
@@ -189,9 +374,128 @@ mod tests {
     use conllx::graph::Sentence;
     use conllx::token::{Features, Token, TokenBuilder};
 
-    use crate::io::conllx::ToConllx;
+    use crate::io::conllx::{ConllxOptions, MultiwordForms, ToConllx, TryFromConllx};
     use crate::io::negra::negra_to_tree;
-    use crate::io::ptb::PTBFormat;
+    use crate::io::ptb::{PTBFormat, PTBFormatter};
+    use crate::tree_modification::TreeOps;
+    use crate::Tree;
+
+    #[test]
+    fn empty_sentence() {
+        let sentence = Sentence::new();
+        assert!(Tree::try_from_conllx_with_absolute_encoding(&sentence).is_err());
+        assert!(Tree::try_from_conllx_with_relative_encoding(&sentence).is_err());
+    }
+
+    #[test]
+    fn tagged() {
+        let input = "(S (NP the (NN dog)) (VP (VBZ barks)))";
+        let formatter = PTBFormatter::new(PTBFormat::Simple).bare_terminals(true);
+        let tree = formatter.string_to_tree(input).unwrap();
+        assert!(!tree.is_fully_tagged());
+        let tagged = ConllxOptions::new().require_tags(true);
+        assert!(tree.to_conllx_with(&tagged).is_err());
+        assert_eq!(tree.to_conllx()[1].token().unwrap().pos(), Some("_"));
+
+        let tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (NN dog)) (VP (VBZ barks)))")
+            .unwrap();
+        assert!(tree.is_fully_tagged());
+        assert_eq!(tree.to_conllx_with(&tagged).unwrap(), tree.to_conllx());
+    }
+
+    #[test]
+    fn terminal_indices() {
+        let mut tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (NN dog)) (, ,) (VP (VBZ barks)))")
+            .unwrap();
+        // remove the comma without reindexing the remaining terminals.
+        let comma = tree.terminal_at(2).unwrap();
+        tree.graph_mut().remove_node(comma);
+        assert_eq!(tree.n_terminals(), 4);
+
+        let sentence = tree.to_conllx();
+        assert_eq!(sentence.len(), 4);
+        assert_eq!(sentence[3].token().unwrap().form(), "barks");
+
+        let with_indices = ConllxOptions::new().index_feature("idx");
+        let sentence = tree.to_conllx_with(&with_indices).unwrap();
+        let indices = (1..sentence.len())
+            .map(|id| {
+                let token = sentence[id].token().unwrap();
+                let features = token.features().unwrap().as_map();
+                (token.form(), features["idx"].clone().unwrap())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            indices,
+            vec![
+                ("the", "0".to_owned()),
+                ("dog", "1".to_owned()),
+                ("barks", "3".to_owned())
+            ]
+        );
+
+        // pruning reindexes the terminals
+        let mut tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (NN dog)) (, ,) (VP (VBZ barks)))")
+            .unwrap();
+        tree.prune(|node| node.label() != ",").unwrap();
+        let sentence = tree.to_conllx_with(&with_indices).unwrap();
+        let barks = sentence[3].token().unwrap();
+        assert_eq!(barks.form(), "barks");
+        assert_eq!(barks.features(), Some(&Features::from_string("idx:2")));
+    }
+
+    #[test]
+    fn multiword_forms() {
+        let mut tree = PTBFormat::Simple
+            .string_to_tree("(S (NE Tribunal_Supremo) (VVFIN entscheidet))")
+            .unwrap();
+        tree.for_each_terminal_mut(|terminal| {
+            if terminal.form() == "Tribunal_Supremo" {
+                terminal.set_form("Tribunal  Supremo");
+                terminal.set_lemma(Some("tribunal supremo"));
+            }
+        });
+
+        let options = ConllxOptions::new();
+        let sentence = tree.to_conllx_with(&options).unwrap();
+        assert_eq!(sentence, tree.to_conllx());
+        assert_eq!(sentence[1].token().unwrap().form(), "Tribunal  Supremo");
+
+        let options = options.multiword(MultiwordForms::Join("_".to_owned()));
+        let sentence = tree.to_conllx_with(&options).unwrap();
+        assert_eq!(
+            sentence[1].token().unwrap(),
+            &Token::from(
+                TokenBuilder::new("Tribunal_Supremo")
+                    .lemma("tribunal_supremo")
+                    .pos("NE")
+            )
+        );
+
+        let options = options.multiword(MultiwordForms::Split);
+        let sentence = tree.to_conllx_with(&options).unwrap();
+        let mut target = Sentence::new();
+        target.push(
+            TokenBuilder::new("Tribunal")
+                .lemma("tribunal")
+                .pos("NE")
+                .into(),
+        );
+        target.push(
+            TokenBuilder::new("Supremo")
+                .lemma("supremo")
+                .pos("NE")
+                .into(),
+        );
+        target.push(TokenBuilder::new("entscheidet").pos("VVFIN").into());
+        assert_eq!(sentence, target);
+
+        let options = options.multiword(MultiwordForms::Reject);
+        assert!(tree.to_conllx_with(&options).is_err());
+    }
 
     #[test]
     fn to_conllx() {
@@ -256,6 +560,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn borrowed_and_owned_conversion_agree() {
+        let input = fs::read_to_string("testdata/long_single.negra").unwrap();
+        let tree = negra_to_tree(&input).unwrap();
+        let borrowed = Sentence::from(&tree);
+        assert_eq!(borrowed, tree.to_conllx());
+        assert_eq!(borrowed, Sentence::from(tree));
+    }
+
     #[test]
     fn into_conllx() {
         let input = "(NX (NN Nounphrase) (PX (PP on) (NX (DET a) (ADJ single) (NX line))))";
@@ -0,0 +1,88 @@
+use std::collections::HashSet;
+
+use failure::Error;
+
+use crate::Tree;
+
+/// Remove duplicate trees from a stream of trees.
+///
+/// Yields only the first occurrence of each distinct tree. Trees are distinct if their
+/// `Tree::structural_hash` differs, trees that only differ in features, lemmas, edge labels or
+/// metadata are duplicates. Since only the hashes are kept, a hash collision would drop a
+/// distinct tree.
+///
+/// Errors are passed through.
+pub fn dedup_trees(
+    iter: impl Iterator<Item = Result<Tree, Error>>,
+) -> impl Iterator<Item = Result<Tree, Error>> {
+    let mut seen = HashSet::new();
+    iter.filter(move |tree| match tree {
+        Ok(tree) => seen.insert(tree.structural_hash()),
+        Err(_) => true,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use super::dedup_trees;
+    use crate::io::{PTBFormat, PTBLineFormat, PTBReader};
+    use crate::NegraReader;
+
+    #[test]
+    fn dedup() {
+        let input = "(S (NP (DT the) (NN dog)) (VP (VBZ barks)))
+(S (NP:SB (DT the) (NN dog)) (VP:HD (VBZ barks)))
+(S (NP (DT the)) (NP (NN dog)) (VP (VBZ barks)))
+(S (NP (DT the) (NN dog)) (VP (VBZ barks)))
+(S (NP (DT a) (NN dog)) (VP (VBZ barks)))
+(S (NP (DT the) (NN dog))
+";
+        let reader = PTBReader::new(
+            input.as_bytes(),
+            PTBFormat::TueBa,
+            PTBLineFormat::SingleLine,
+        );
+        let trees = dedup_trees(reader).collect::<Vec<_>>();
+        assert_eq!(trees.len(), 4);
+        assert!(trees[3].is_err());
+        let trees = trees
+            .into_iter()
+            .filter_map(Result::ok)
+            .map(|tree| PTBFormat::Simple.tree_to_string(&tree).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            trees,
+            vec![
+                "(S (NP (DT the) (NN dog)) (VP (VBZ barks)))",
+                "(S (NP (DT the)) (NP (NN dog)) (VP (VBZ barks)))",
+                "(S (NP (DT a) (NN dog)) (VP (VBZ barks)))",
+            ]
+        );
+    }
+
+    #[test]
+    fn structural_hash() {
+        let input = File::open("testdata/10.negra").unwrap();
+        let trees = NegraReader::new(BufReader::new(input))
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+        let mut copy = trees[0].clone();
+        assert_eq!(copy.structural_hash(), trees[0].structural_hash());
+        let terminal = copy.terminal_at(0).unwrap();
+        copy[terminal].features_mut().insert("new", Some("feature"));
+        assert_eq!(copy.structural_hash(), trees[0].structural_hash());
+        copy[terminal].set_label("OTHER");
+        assert_ne!(copy.structural_hash(), trees[0].structural_hash());
+
+        let input = File::open("testdata/10.negra").unwrap();
+        let reread = NegraReader::new(BufReader::new(input)).map(Result::unwrap);
+        for (tree, reread) in trees.iter().zip(reread) {
+            assert_eq!(tree.structural_hash(), reread.structural_hash());
+        }
+        let n_distinct = dedup_trees(trees.into_iter().map(Ok)).count();
+        assert_eq!(n_distinct, 10);
+    }
+}
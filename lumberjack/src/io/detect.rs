@@ -0,0 +1,114 @@
+use std::io::BufRead;
+
+use failure::Error;
+
+use crate::io::PTBLineFormat;
+
+/// Formats distinguished by `detect_format`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DetectedFormat {
+    /// NEGRA export format.
+    Negra,
+    /// Bracketed trees in the given line format.
+    ///
+    /// The flavor of the bracketing, i.e. the `PTBFormat`, can't be detected.
+    Bracketed(PTBLineFormat),
+}
+
+/// Detect the format of a treebank.
+///
+/// Inspects the first non-whitespace bytes of `read`:
+///
+/// * Input starting with `#BOS` or a `%%` comment is NEGRA. Other input starting with `#`, e.g.
+///   CoNLL-U comments, is not detected as NEGRA.
+/// * Input starting with `(` is bracketed. If the first line contains a complete tree, the
+///   line format is `PTBLineFormat::SingleLine`, otherwise `PTBLineFormat::MultiLine`.
+///
+/// Leading whitespace is consumed, everything else is left in `read` for the actual reader.
+/// Detection is limited to the contents of `read`'s buffer.
+///
+/// Returns `Error` if the input is empty or doesn't start like one of the formats above.
+pub fn detect_format<R>(read: &mut R) -> Result<DetectedFormat, Error>
+where
+    R: BufRead,
+{
+    loop {
+        let buf = read.fill_buf()?;
+        if buf.is_empty() {
+            return Err(format_err!("Can't detect format of empty input."));
+        }
+        let n_whitespace = buf
+            .iter()
+            .take_while(|byte| byte.is_ascii_whitespace())
+            .count();
+        if n_whitespace < buf.len() {
+            read.consume(n_whitespace);
+            break;
+        }
+        let len = buf.len();
+        read.consume(len);
+    }
+
+    let buf = read.fill_buf()?;
+    if buf.starts_with(b"#BOS") || buf.starts_with(b"%%") {
+        return Ok(DetectedFormat::Negra);
+    }
+    if !buf.starts_with(b"(") {
+        return Err(format_err!(
+            "Can't detect format, input neither NEGRA nor bracketed."
+        ));
+    }
+
+    let line = buf.split(|&byte| byte == b'\n').next().unwrap();
+    let mut depth = 0;
+    for &byte in line {
+        match byte {
+            b'(' => depth += 1,
+            b')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(DetectedFormat::Bracketed(PTBLineFormat::SingleLine));
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(DetectedFormat::Bracketed(PTBLineFormat::MultiLine))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Cursor};
+
+    use super::{detect_format, DetectedFormat};
+    use crate::io::PTBLineFormat;
+
+    #[test]
+    fn detect() {
+        let mut negra = Cursor::new("#BOS 1  2 1202391857 0 %% HEADLINE\n");
+        assert_eq!(detect_format(&mut negra).unwrap(), DetectedFormat::Negra);
+        let mut line = String::new();
+        negra.read_line(&mut line).unwrap();
+        assert!(line.starts_with("#BOS 1"));
+
+        let mut single = Cursor::new("\n  (S (NN a))\n(S (NN b))");
+        assert_eq!(
+            detect_format(&mut single).unwrap(),
+            DetectedFormat::Bracketed(PTBLineFormat::SingleLine)
+        );
+        let mut line = String::new();
+        single.read_line(&mut line).unwrap();
+        assert_eq!(line, "(S (NN a))\n");
+
+        let mut multi = BufReader::new("(S\n  (NN a))\n".as_bytes());
+        assert_eq!(
+            detect_format(&mut multi).unwrap(),
+            DetectedFormat::Bracketed(PTBLineFormat::MultiLine)
+        );
+
+        assert!(detect_format(&mut Cursor::new("  \n")).is_err());
+        assert!(detect_format(&mut Cursor::new("1\tdog\t_")).is_err());
+        let mut conllu = Cursor::new("# sent_id = 1\n# text = dog\n1\tdog\tdog\tNOUN\n");
+        assert!(detect_format(&mut conllu).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt::Write;
+
+use petgraph::prelude::NodeIndex;
+
+use crate::Tree;
+
+/// Configuration of the Graphviz DOT representation.
+///
+/// By default nodes are not colored. Colors are assigned through `DotConfig::feature_color`
+/// and `DotConfig::nonprojective_color`, any color name understood by Graphviz can be used.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DotConfig {
+    feature_colors: Vec<(String, String)>,
+    nonprojective_color: Option<String>,
+}
+
+impl DotConfig {
+    /// Construct a configuration without coloring.
+    pub fn new() -> Self {
+        DotConfig::default()
+    }
+
+    /// Color nodes that have the feature `key`.
+    ///
+    /// The value of the feature is ignored. If a node has multiple features with colors, the
+    /// color that was added first is used.
+    pub fn feature_color(mut self, key: impl Into<String>, color: impl Into<String>) -> Self {
+        self.feature_colors.push((key.into(), color.into()));
+        self
+    }
+
+    /// Color `NonTerminal`s with discontinuous spans.
+    ///
+    /// Feature colors take precedence over this color.
+    pub fn nonprojective_color(mut self, color: impl Into<String>) -> Self {
+        self.nonprojective_color = Some(color.into());
+        self
+    }
+
+    /// Get the DOT representation of `tree`.
+    ///
+    /// `NonTerminal`s are labeled with their label, `Terminal`s with their part-of-speech tag and
+    /// form. Edges are labeled with their edge labels, edges attaching discontinuous nodes are
+    /// dashed. Terminals are placed on the same rank in surface order.
+    pub fn tree_to_dot(&self, tree: &Tree) -> String {
+        let mut dot = String::from("digraph tree {\n");
+        for node in tree.preorder() {
+            let label = match tree[node].terminal() {
+                Some(terminal) => {
+                    format!("{}\\n{}", escape(terminal.label()), escape(terminal.form()))
+                }
+                None => escape(tree[node].label()),
+            };
+            let shape = if tree[node].is_terminal() {
+                "box"
+            } else {
+                "ellipse"
+            };
+            write!(
+                dot,
+                "  n{} [label=\"{}\", shape={}",
+                node.index(),
+                label,
+                shape
+            )
+            .unwrap();
+            if let Some(color) = self.node_color(tree, node) {
+                write!(dot, ", style=filled, fillcolor=\"{}\"", escape(color)).unwrap();
+            }
+            dot.push_str("];\n");
+        }
+
+        for node in tree.preorder() {
+            let (parent, edge) = match tree.parent(node) {
+                Some(parent) => parent,
+                None => continue,
+            };
+            let mut attributes = Vec::new();
+            if let Some(label) = tree[edge].label() {
+                attributes.push(format!("label=\"{}\"", escape(label)));
+            }
+            if tree[node].span().discontinuous().is_some() {
+                attributes.push("style=dashed".to_owned());
+            }
+            writeln!(
+                dot,
+                "  n{} -> n{} [{}];",
+                parent.index(),
+                node.index(),
+                attributes.join(", ")
+            )
+            .unwrap();
+        }
+
+        let terminals = (0..tree.n_terminals())
+            .filter_map(|idx| tree.terminal_at(idx))
+            .map(|terminal| format!("n{}", terminal.index()))
+            .collect::<Vec<_>>();
+        if terminals.len() > 1 {
+            writeln!(
+                dot,
+                "  {{ rank=same; {} [style=invis]; }}",
+                terminals.join(" -> ")
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn node_color<'a>(&'a self, tree: &Tree, node: NodeIndex) -> Option<&'a str> {
+        let feature_color = tree[node].features().and_then(|features| {
+            self.feature_colors
+                .iter()
+                .find(|(key, _)| features.inner().contains_key(key))
+                .map(|(_, color)| color.as_str())
+        });
+        feature_color.or_else(|| {
+            if tree[node].span().discontinuous().is_some() {
+                self.nonprojective_color.as_deref()
+            } else {
+                None
+            }
+        })
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DotConfig;
+    use crate::io::PTBFormat;
+    use crate::tree_modification::TreeOps;
+    use crate::{HeadDirection, HeadRules, NegraReader};
+    use std::fs::File;
+    use std::io::BufReader;
+
+    #[test]
+    fn plain() {
+        let tree = PTBFormat::TueBa
+            .string_to_tree("(S (NP:SB (NN \"dog\")) (VBZ:HD barks))")
+            .unwrap();
+        let dot = DotConfig::new().tree_to_dot(&tree);
+        assert!(dot.starts_with("digraph tree {\n"));
+        assert!(dot.contains("[label=\"NN\\n\\\"dog\\\"\", shape=box];"));
+        assert!(dot.contains("[label=\"SB\"];"));
+        assert!(!dot.contains("fillcolor"));
+        assert!(!dot.contains("dashed"));
+    }
+
+    #[test]
+    fn colored() {
+        let mut tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (NN dog)) (VP (VBZ barks)))")
+            .unwrap();
+        tree.annotate_head(&HeadRules::new(HeadDirection::RightToLeft))
+            .unwrap();
+        let dot = DotConfig::new()
+            .feature_color("head", "red")
+            .tree_to_dot(&tree);
+        // VP, NN and VBZ are heads.
+        assert_eq!(dot.matches("fillcolor=\"red\"").count(), 3);
+
+        let input = File::open("testdata/10.negra").unwrap();
+        let tree = NegraReader::new(BufReader::new(input))
+            .map(Result::unwrap)
+            .find(|tree| !tree.projective())
+            .unwrap();
+        let n_discontinuous = tree
+            .nonterminals()
+            .filter(|&nt| tree[nt].span().discontinuous().is_some())
+            .count();
+        let dot = DotConfig::new()
+            .nonprojective_color("blue")
+            .tree_to_dot(&tree);
+        assert_eq!(dot.matches("fillcolor=\"blue\"").count(), n_discontinuous);
+        assert_eq!(dot.matches("style=dashed").count(), n_discontinuous);
+    }
+}
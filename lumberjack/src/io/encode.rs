@@ -1,14 +1,15 @@
-///! Encoding Module
-///
-/// This module contains structs, traits and methods required to:
-///
-/// * convert trees to a sequence of labels
-/// * construct trees from such sequences.
-///
-/// The trees can be encoded on an absolute and relative scale.
-///
-/// See Gómez-Rodríguez and Vilares (2018).
+//! Encoding Module
+//!
+//! This module contains structs, traits and methods required to:
+//!
+//! * convert trees to a sequence of labels
+//! * construct trees from such sequences.
+//!
+//! The trees can be encoded on an absolute and relative scale.
+//!
+//! See Gómez-Rodríguez and Vilares (2018).
 use std::convert::TryFrom;
+use std::fmt;
 use std::ops::Deref;
 use std::vec::IntoIter;
 
@@ -17,7 +18,7 @@ use failure::Error;
 use crate::tree::Tree;
 use crate::tree_modification::TreeOps;
 use crate::util::{Climber, LabelSet};
-use crate::{Edge, Node, NonTerminal, Projectivity, Terminal};
+use crate::{Edge, LumberjackError, Node, NonTerminal, Projectivity, Terminal};
 use petgraph::prelude::{NodeIndex, StableGraph};
 use petgraph::Direction;
 
@@ -169,9 +170,11 @@ fn get_common(tree: &Tree, terminal: NodeIndex) -> Result<Option<(String, usize)
     let mut climber = Climber::new(terminal);
     while let Some(parent) = climber.next(tree) {
         if tree[parent].span().contains(idx + 1) && common.is_none() {
-            let common_nt = tree[parent]
-                .nonterminal()
-                .ok_or_else(|| format_err!("Terminal without parent:\n{}", tree[parent]))?;
+            let common_nt = tree[parent].nonterminal().ok_or_else(|| {
+                LumberjackError::TerminalWithoutParent {
+                    terminal: tree[parent].to_string(),
+                }
+            })?;
             let common_label =
                 if let Some(chain) = common_nt.features().and_then(|f| f.get_val("unary_chain")) {
                     format!("{}_{}", chain, common_nt.label())
@@ -275,20 +278,22 @@ impl Decode for Tree {
                     }
 
                     // '_' is used to delimit unary chains from actual label
-                    if let Some(idx) = common_nt.rfind('_') {
-                        let (chain, label) = common_nt.split_at(idx);
-                        // Nodes in previous for loop are inserted with dummy label, cur indexes the
-                        // nonterminal receiving the current label.
-                        graph[cur].set_label(&label[1..]);
+                    // Nodes in previous for loop are inserted with dummy label, cur indexes the
+                    // nonterminal receiving the current label.
+                    let mut parts = common_nt.rsplitn(2, '_');
+                    let label = parts.next().unwrap();
+                    graph[cur].set_label(label);
+                    if let Some(chain) = parts.next() {
                         graph[cur].features_mut().insert("unary_chain", Some(chain));
-                    } else {
-                        graph[cur].set_label(common_nt);
                     }
 
-                    if prev.is_none() || n_common > prev_n {
-                        graph.add_edge(cur, term_idx, Edge::default());
-                    } else {
-                        graph.add_edge(prev.unwrap(), term_idx, Edge::default());
+                    match prev {
+                        Some(prev) if n_common <= prev_n => {
+                            graph.add_edge(prev, term_idx, Edge::default());
+                        }
+                        _ => {
+                            graph.add_edge(cur, term_idx, Edge::default());
+                        }
                     }
 
                     prev = Some(cur);
@@ -314,7 +319,7 @@ impl Decode for Tree {
                     .ok_or_else(|| format_err!("Root without outgoing edge"))?;
                 let root = self.root();
                 self.graph_mut().remove_node(root);
-                self.set_root(child);
+                self.set_root(child)?;
             }
         }
         Ok(())
@@ -327,9 +332,9 @@ pub struct AbsoluteAncestor {
     common: usize,
     label: String,
 }
-impl ToString for AbsoluteAncestor {
-    fn to_string(&self) -> String {
-        format!("{}+{}", self.label, self.common)
+impl fmt::Display for AbsoluteAncestor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}+{}", self.label, self.common)
     }
 }
 
@@ -361,7 +366,7 @@ impl AbsoluteAncestor {
     }
 }
 
-impl<'a> TryFrom<&'a str> for AbsoluteAncestor {
+impl TryFrom<&str> for AbsoluteAncestor {
     type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
@@ -396,16 +401,16 @@ impl RelativeAncestor {
     }
 }
 
-impl ToString for RelativeAncestor {
-    fn to_string(&self) -> String {
+impl fmt::Display for RelativeAncestor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            RelativeAncestor::Regular { label, offset } => format!("{}+{}", label, offset),
-            RelativeAncestor::Root(label) => label.to_string(),
+            RelativeAncestor::Regular { label, offset } => write!(f, "{}+{}", label, offset),
+            RelativeAncestor::Root(label) => write!(f, "{}", label),
         }
     }
 }
 
-impl<'a> TryFrom<&'a str> for RelativeAncestor {
+impl TryFrom<&str> for RelativeAncestor {
     type Error = Error;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
@@ -427,7 +432,7 @@ impl<'a> TryFrom<&'a str> for RelativeAncestor {
 ///
 /// Trees are encoded as a sequence of tuples:
 ///  * For each token a the common nonterminal label with the following token and the number
-///     of common nonterminal nodes.
+///    of common nonterminal nodes.
 ///  * Optional leaf unary chains.
 #[derive(Clone, Debug, PartialEq)]
 pub struct AbsoluteEncoding(Vec<(Option<AbsoluteAncestor>, Option<String>)>);
@@ -753,6 +758,24 @@ mod test {
         assert_eq!(reconstructed, tree);
     }
 
+    #[test]
+    fn multibyte_unary_chain() {
+        let ptb = "(Ä (Ü (NÖ (T1 t1) (T2 t2))))";
+        let mut tree = PTBFormat::Simple.string_to_tree(ptb).unwrap();
+        let terminals = tree
+            .terminals()
+            .filter_map(|t| tree[t].terminal().cloned())
+            .collect::<Vec<_>>();
+        tree.collapse_unary_chains("_").unwrap();
+        let encoding = tree.encode_absolute().unwrap();
+        let mut reconstructed = Tree::decode(encoding, terminals);
+        reconstructed.restore_unary_chains("_").unwrap();
+        assert_eq!(
+            ptb,
+            PTBFormat::Simple.tree_to_string(&reconstructed).unwrap()
+        );
+    }
+
     #[test]
     fn encode() {
         let ptb = "(S (NP (PRP My) (NN daughter)) (VP (VBD broke) (NP (NP (DET the) (JJ red) \
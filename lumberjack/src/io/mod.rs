@@ -1,21 +1,83 @@
+mod conllu;
+pub use crate::io::conllu::ConlluReader;
 mod conllx;
-pub use crate::io::conllx::{ToConllx, TryFromConllx};
+pub use crate::io::conllx::{ConllxOptions, MultiwordForms, ToConllx, TryFromConllx};
+mod dedup;
+pub use crate::io::dedup::dedup_trees;
+mod detect;
+pub use crate::io::detect::{detect_format, DetectedFormat};
+mod dot;
+pub use crate::io::dot::DotConfig;
 mod encode;
 pub use crate::io::encode::{AbsoluteEncoding, Decode, Encode, RelativeEncoding};
 mod negra;
-pub use crate::io::negra::{negra_to_tree, NegraReader};
+pub use crate::io::negra::{negra_to_tree, negra_to_tree_with_morph, NegraMorph, NegraReader};
 mod ptb;
-pub use crate::io::ptb::{PTBFormat, PTBLineFormat, PTBReader, PTBWriter};
+pub(crate) use crate::io::ptb::encode_empty_element;
+pub use crate::io::ptb::{LabelCase, PTBFormat, PTBFormatter, PTBLineFormat, PTBReader, PTBWriter};
+mod stats;
+pub use crate::io::stats::CorpusStats;
 
 use crate::tree::Tree;
 
 use failure::Error;
 
 pub(crate) static NODE_ANNOTATION_FEATURE_KEY: &str = "node_annotation";
+/// Feature key of the empty elements removed by `TreeOps::remove_traces`.
+pub(crate) static EMPTY_ELEMENTS_FEATURE_KEY: &str = "empty_elements";
 
 /// Trait to write a `Tree`.
+///
+/// Writers in this crate pass each tree to the wrapped `Write` as soon as it is written and do
+/// not buffer on their own. Whether output is buffered depends on the wrapped `Write`, e.g. a
+/// `BufWriter` keeps output in memory until it is flushed.
 pub trait WriteTree {
+    /// Write a tree.
     fn write_tree(&mut self, tree: &Tree) -> Result<(), Error>;
+
+    /// Flush the wrapped `Write`.
+    ///
+    /// The default implementation does nothing. It is used where the wrapped `Write` is not
+    /// accessible, e.g. for `conllx::io::Writer`.
+    fn flush(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Assert that `tree` is unchanged after writing and reading it in `format`.
+///
+/// The read tree is compared to the original tree including features, edge labels are only
+/// compared for the formats that write them. Lemmas are not part of the bracketed formats and
+/// brackets in forms and part-of-speech tags are written as `LBR` and `RBR`, the original tree
+/// is normalized accordingly. Nonprojective trees are projectivized before writing since the
+/// bracketed formats can't represent discontinuities.
+///
+/// Panics if the trees differ. Only available in tests and with the feature `test-utils`.
+#[cfg(any(test, feature = "test-utils"))]
+pub fn assert_roundtrip(tree: &Tree, format: PTBFormat) {
+    use crate::tree_modification::Projectivize;
+
+    let formatter = PTBFormatter::new(format).features(true);
+    let mut expected = tree.clone();
+    expected.for_each_terminal_mut(|terminal| {
+        terminal.set_lemma::<String>(None);
+        let form = terminal.form().replace('(', "LBR").replace(')', "RBR");
+        terminal.set_form(form);
+        let pos = terminal.label().replace('(', "LBR").replace(')', "RBR");
+        terminal.set_label(pos);
+    });
+    if let PTBFormat::Simple | PTBFormat::PTB = format {
+        let edges = expected.graph().edge_indices().collect::<Vec<_>>();
+        for edge in edges {
+            expected[edge].set_label::<String>(None);
+        }
+    }
+    if !expected.projective() {
+        expected.projectivize();
+    }
+    let string = formatter.tree_to_string(&expected).unwrap();
+    let read = formatter.string_to_tree(&string).unwrap();
+    assert_eq!(read, expected);
 }
 
 #[cfg(test)]
@@ -24,11 +86,47 @@ mod tests {
     use std::io::{BufRead, BufReader};
 
     use conllx::graph::Sentence;
+    use rayon::iter::{ParallelBridge, ParallelIterator};
 
-    use crate::io::{Decode, Encode, PTBFormat, TryFromConllx};
+    use crate::io::{assert_roundtrip, Decode, Encode, PTBFormat, TryFromConllx};
     use crate::tree_modification::{Projectivize, TreeOps};
     use crate::{NegraReader, Tree};
 
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    pub fn negra_roundtrip() {
+        for file in &[
+            "testdata/10.negra",
+            "testdata/long_single.negra",
+            "testdata/test.negra",
+        ] {
+            let input = File::open(file).unwrap();
+            for tree in NegraReader::new(BufReader::new(input)) {
+                let tree = tree.unwrap();
+                let n_discontinuous = tree
+                    .nonterminals()
+                    .filter(|&nt| tree[nt].span().discontinuous().is_some())
+                    .count();
+                assert_eq!(tree.projective(), n_discontinuous == 0);
+                assert_roundtrip(&tree, PTBFormat::TueBa);
+                assert_roundtrip(&tree, PTBFormat::Simple);
+            }
+        }
+    }
+
+    #[test]
+    pub fn parallel_reading() {
+        assert_send_sync::<Tree>();
+        let input = File::open("testdata/10.negra").unwrap();
+        let reader = NegraReader::new(BufReader::new(input));
+        let n_terminals = reader
+            .par_bridge()
+            .map(|tree| tree.unwrap().n_terminals())
+            .collect::<Vec<_>>();
+        assert_eq!(n_terminals.len(), 10);
+    }
+
     #[test]
     pub fn roundtrip() {
         let input = File::open("testdata/test.ptb").unwrap();
@@ -5,16 +5,27 @@ use failure::Error;
 use pest::iterators::Pair;
 use pest::Parser;
 use petgraph::stable_graph::StableGraph;
+use petgraph::visit::Dfs;
+use petgraph::Direction;
 
 use crate::io::NODE_ANNOTATION_FEATURE_KEY;
 use crate::{Edge, Node, NonTerminal, Projectivity, Span, Terminal, Tree};
 
+/// Metadata keys of the fields following the sentence ID in a `#BOS` line.
+const BOS_METADATA_KEYS: [&str; 3] = ["editor", "date", "origin"];
+
+/// Metadata key of the comment of a `#BOS` line.
+const BOS_COMMENT_KEY: &str = "comment";
+
 /// Iterator over constituency trees in a NEGRA export file.
 ///
 /// `next()` moves the reader until the first `#BOS` is found, then collects lines until `#EOS` is
 /// found. Returns `Some(Error)` if overlapping sentences are found, `'#BOS` -> `#EOS` is violated
 /// or if the collected lines are not a well formed NEGRA sentence.
 ///
+/// Sentences without any tokens are skipped. Comment lines starting with `%%` inside of
+/// sentences are ignored.
+///
 /// Note:   If the reader never encounters a line according to `Rule::bos`, `None` is returned.
 ///         `Rule::bos` expects a line starting with `#BOS SENT_ID` followed by optional comments.
 pub struct NegraReader<R>
@@ -22,6 +33,7 @@ where
     R: BufRead,
 {
     inner: Lines<R>,
+    morph: NegraMorph,
 }
 
 impl<R> NegraReader<R>
@@ -30,11 +42,36 @@ where
 {
     /// Creates a new `NegraTreeIter` over the NEGRA trees in the reader.
     pub fn new(reader: R) -> NegraReader<R> {
+        NegraReader::with_morph(reader, NegraMorph::default())
+    }
+
+    /// Creates a new `NegraTreeIter` reading the morphology column according to `morph`.
+    pub fn with_morph(reader: R, morph: NegraMorph) -> NegraReader<R> {
         NegraReader {
             inner: reader.lines(),
+            morph,
         }
     }
 }
+
+/// `NegraMorph`
+///
+/// Specifies where the content of the morphology column of NEGRA terminals ends up. NEGRA
+/// exports differ in what they store in that column, some contain morphological tags, others
+/// contain lemmas.
+///
+/// A morphology column containing `"--"` is treated as empty.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NegraMorph {
+    /// Morphology is read as a valueless feature, the lemma column is read as lemma.
+    #[default]
+    Features,
+    /// Morphology is read as lemma, the lemma column is ignored.
+    Lemma,
+    /// Morphology is read both as lemma and as valueless feature, the lemma column is ignored.
+    LemmaAndFeatures,
+}
+
 impl<R> Iterator for NegraReader<R>
 where
     R: BufRead,
@@ -44,8 +81,9 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         let mut inside = false;
         let mut buffer = String::new();
+        let mut n_lines = 0;
 
-        while let Some(line) = self.inner.next() {
+        for line in self.inner.by_ref() {
             let line = match line {
                 Ok(line) => line,
                 Err(e) => return Some(Err(format_err!("{}", e))),
@@ -61,11 +99,20 @@ where
             if inside {
                 buffer.push_str(&line);
                 buffer.push('\n');
+                let line = line.trim();
+                if !line.is_empty() && !line.starts_with("%%") {
+                    n_lines += 1;
+                }
             }
 
             if line.starts_with("#EOS") {
-                if inside {
-                    return Some(negra_to_tree(&buffer));
+                if inside && n_lines == 2 {
+                    // sentence without tokens, only #BOS and #EOS
+                    inside = false;
+                    buffer.clear();
+                    n_lines = 0;
+                } else if inside {
+                    return Some(negra_to_tree_with_morph(&buffer, self.morph));
                 } else {
                     return Some(Err(format_err!("Found #EOS without #BOS\n{}", line)));
                 }
@@ -83,10 +130,24 @@ where
 /// NONTERMINAL
 /// #EOS
 ///
-/// Note: This method does not skip comments or newlines
+/// The ID following `#BOS` is stored as the tree's sentence ID. The editor, date and origin
+/// following the ID are stored in the tree's metadata under the keys `editor`, `date` and
+/// `origin`, a comment after `%%` under the key `comment`, e.g. `HEADLINE` in
+/// `#BOS 3 2 1070544990 0 %% HEADLINE`.
+///
+/// Comment lines starting with `%%` between `#BOS` and `#EOS` are skipped.
+///
+/// Note: This method does not skip newlines
 pub fn negra_to_tree(negra_string: &str) -> Result<Tree, Error> {
+    negra_to_tree_with_morph(negra_string, NegraMorph::default())
+}
+
+/// Builds `Tree` from `&str`, reading the morphology column according to `morph`.
+///
+/// See `negra_to_tree` for the expected structure.
+pub fn negra_to_tree_with_morph(negra_string: &str, morph: NegraMorph) -> Result<Tree, Error> {
     let mut parsed_line = NEGRAParser::parse(Rule::sent, negra_string)?;
-    build_tree(parsed_line.next().unwrap())
+    build_tree(parsed_line.next().unwrap(), morph)
 }
 
 // dummy struct required by pest
@@ -94,7 +155,7 @@ pub fn negra_to_tree(negra_string: &str) -> Result<Tree, Error> {
 #[grammar = "io/negra.pest"]
 struct NEGRAParser;
 
-fn build_tree(pair: Pair<Rule>) -> Result<Tree, Error> {
+fn build_tree(pair: Pair<Rule>, morph: NegraMorph) -> Result<Tree, Error> {
     let mut graph = StableGraph::new();
     match pair.as_rule() {
         Rule::sent => (),
@@ -102,55 +163,32 @@ fn build_tree(pair: Pair<Rule>) -> Result<Tree, Error> {
     }
     let mut pairs = pair.into_inner();
     let bos = pairs.next().unwrap();
+    let metadata = bos_metadata(bos.as_str());
 
     // safe to do since we only enter this method after matching on Rule::sent
     let start_id = bos.into_inner().next().unwrap().as_str().parse::<usize>()?;
 
-    // map parent_id -> [(edge, child_id),[..]]
-    let mut edges = HashMap::new();
+    // (parent_id, edge, child) for every node, edges are added once all nodes are known since
+    // nonterminals are not guaranteed to follow their children.
+    let mut attachments = Vec::new();
+    // map nonterminal_id -> node
+    let mut nonterminals = HashMap::new();
     let mut n_terminals = 0;
-    let mut projectivity = Projectivity::Projective;
     for pair in pairs {
         match pair.as_rule() {
             Rule::terminal => {
-                let (parent, edge, terminal) = process_terminal(pair, n_terminals)?;
+                let (parent, edge, terminal) = process_terminal(pair, n_terminals, morph)?;
                 let idx = graph.add_node(terminal);
-
-                edges
-                    .entry(parent)
-                    .or_insert_with(Vec::new)
-                    .push((edge, idx));
+                attachments.push((parent, edge, idx));
                 n_terminals += 1;
             }
             Rule::nonterminal => {
-                let (parent, self_id, edge, mut nonterminal) = process_nonterminal(pair)?;
-                let mut coverage = Vec::new();
-                // following nodes are higher in the tree, nonterminal is finished, just add all edges
-                let edge_list = edges.remove(&self_id).ok_or_else(|| {
-                    format_err!("Nonterminal without children:\n{:?}", nonterminal)
-                })?;
-
-                // iterate over children to collect indices covered by this nonterminal
-                for (_, node) in edge_list.iter() {
-                    coverage.extend(graph[*node].span());
-                }
-
-                let span = Span::from_vec(coverage)?;
-                if span.discontinuous().is_some() {
-                    projectivity = Projectivity::Nonprojective;
-                }
-                nonterminal.set_span(span);
-
-                // add nonterminal and outgoing edges to graph
+                let (parent, self_id, edge, nonterminal) = process_nonterminal(pair)?;
                 let idx = graph.add_node(Node::NonTerminal(nonterminal));
-                for (edge, node) in edge_list {
-                    graph.add_edge(idx, node, edge);
+                if nonterminals.insert(self_id, idx).is_some() {
+                    return Err(format_err!("Duplicate nonterminal ID: {}", self_id));
                 }
-                // every nonterminal has a parent, either root or other nonterminal
-                edges
-                    .entry(parent)
-                    .or_insert_with(Vec::new)
-                    .push((edge, idx));
+                attachments.push((parent, edge, idx));
             }
             Rule::sent_end => {
                 let end_id = pair
@@ -163,18 +201,63 @@ fn build_tree(pair: Pair<Rule>) -> Result<Tree, Error> {
                 if end_id != start_id {
                     return Err(format_err!("Mismatch in sentence ID"));
                 }
-                // return error if no node had 0 (root) as parent
-                let edge_list = edges
-                    .remove(&0)
-                    .ok_or_else(|| format_err!("Sentence without root"))?;
 
                 // root is guaranteed to cover sentence, thus span is 0..n_terminals
                 let span = Span::new_continuous(0, n_terminals);
                 let root = graph.add_node(Node::NonTerminal(NonTerminal::new("VROOT", span)));
-                for (edge, node) in edge_list {
-                    graph.add_edge(root, node, edge);
+                for (parent, edge, node) in attachments {
+                    let parent = if parent == 0 {
+                        root
+                    } else {
+                        *nonterminals
+                            .get(&parent)
+                            .ok_or_else(|| format_err!("Unknown parent ID: {}", parent))?
+                    };
+                    graph.add_edge(parent, node, edge);
                 }
-                return Ok(Tree::new(graph, n_terminals, root, projectivity));
+                // return error if no node had 0 (root) as parent
+                if graph
+                    .neighbors_directed(root, Direction::Outgoing)
+                    .next()
+                    .is_none()
+                {
+                    return Err(format_err!("Sentence without root"));
+                }
+                for &node in nonterminals.values() {
+                    if graph
+                        .neighbors_directed(node, Direction::Outgoing)
+                        .next()
+                        .is_none()
+                    {
+                        return Err(format_err!(
+                            "Nonterminal without children:\n{:?}",
+                            graph[node]
+                        ));
+                    }
+                }
+                // nodes that are not reachable from the root are part of a cycle
+                let mut dfs = Dfs::new(&graph, root);
+                let mut n_reachable = 0;
+                while dfs.next(&graph).is_some() {
+                    n_reachable += 1;
+                }
+                if n_reachable != graph.node_count() {
+                    return Err(format_err!("Nonterminals not attached to the root"));
+                }
+
+                let mut tree = Tree::new(graph, n_terminals, root, Projectivity::Projective);
+                // spans are computed from the children, a nonterminal whose children are not
+                // contiguous gets a discontinuous span.
+                tree.reset_nt_spans();
+                if tree
+                    .nonterminals()
+                    .any(|nt| tree[nt].span().discontinuous().is_some())
+                {
+                    tree.set_projectivity(Projectivity::Nonprojective);
+                }
+                tree.metadata_mut().extend(metadata);
+                tree.set_sentence_id(Some(start_id.to_string()));
+                return Ok(tree);
             }
             _ => unreachable!(),
         }
@@ -182,6 +265,23 @@ fn build_tree(pair: Pair<Rule>) -> Result<Tree, Error> {
     Err(format_err!("Tree without content"))
 }
 
+// Read the fields following the sentence ID of a #BOS line.
+fn bos_metadata(bos: &str) -> Vec<(String, String)> {
+    let mut parts = bos.splitn(2, "%%");
+    let mut metadata = parts
+        .next()
+        .unwrap()
+        .split_whitespace()
+        .skip(2)
+        .zip(BOS_METADATA_KEYS.iter())
+        .map(|(value, key)| ((*key).to_owned(), value.to_owned()))
+        .collect::<Vec<_>>();
+    if let Some(comment) = parts.next().map(str::trim).filter(|c| !c.is_empty()) {
+        metadata.push((BOS_COMMENT_KEY.to_owned(), comment.to_owned()));
+    }
+    metadata
+}
+
 // returns a tuple of parent_id, own_id, parent_edge, ntbuilder
 // NTBuilder is returned rather than NonTerminal because span depends on other nodes.
 fn process_nonterminal(pair: Pair<Rule>) -> Result<(usize, usize, Edge, NonTerminal), Error> {
@@ -216,7 +316,11 @@ fn process_nonterminal(pair: Pair<Rule>) -> Result<(usize, usize, Edge, NonTermi
 }
 
 // returns parent_id, parent_edge, terminal
-fn process_terminal(pair: Pair<Rule>, idx: usize) -> Result<(usize, Edge, Node), Error> {
+fn process_terminal(
+    pair: Pair<Rule>,
+    idx: usize,
+    morph_handling: NegraMorph,
+) -> Result<(usize, Edge, Node), Error> {
     // terminal rule is defined as:
     // form ~ lemma ~ pos ~ morph ~ edge_label ~ ID ~ consume_line? ~ NEWLINE
     // thus safe to unwrap up to ID
@@ -235,9 +339,23 @@ fn process_terminal(pair: Pair<Rule>, idx: usize) -> Result<(usize, Edge, Node),
     let edge = if edge == "--" { None } else { Some(edge) };
     let parent_id = parts.next().unwrap().as_str().parse::<usize>()?;
     let mut terminal = Terminal::new(form, pos, idx);
-    terminal.set_lemma(Some(lemma));
-    if morph != "--" {
-        terminal.features_mut().insert::<_, String>(morph, None);
+    let morph = if morph != "--" { Some(morph) } else { None };
+    match morph_handling {
+        NegraMorph::Features => {
+            terminal.set_lemma(Some(lemma));
+            if let Some(morph) = morph {
+                terminal.features_mut().insert::<_, String>(morph, None);
+            }
+        }
+        NegraMorph::Lemma => {
+            terminal.set_lemma(morph);
+        }
+        NegraMorph::LemmaAndFeatures => {
+            terminal.set_lemma(morph);
+            if let Some(morph) = morph {
+                terminal.features_mut().insert::<_, String>(morph, None);
+            }
+        }
     }
 
     Ok((parent_id, edge.into(), Node::Terminal(terminal)))
@@ -252,12 +370,52 @@ mod tests {
     use std::io::BufReader;
 
     use super::{
-        negra_to_tree, process_nonterminal, process_terminal, NEGRAParser, NegraReader, Rule,
+        negra_to_tree, process_nonterminal, process_terminal, NEGRAParser, NegraMorph, NegraReader,
+        Rule,
     };
 
     use crate::io::NODE_ANNOTATION_FEATURE_KEY;
     use crate::{Edge, Features, Node, NonTerminal, Projectivity, Span, Terminal, Tree};
 
+    #[test]
+    fn empty_sentence() {
+        let input = "#BOS 1\n#EOS 1\n#BOS 2\n\n#EOS 2\n#BOS 3\nA\ta\tNN\t--\t--\t0\n#EOS 3\n";
+        let trees = NegraReader::new(input.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(trees.len(), 1);
+        assert_eq!(trees[0].sentence_id(), Some("3"));
+        assert!(negra_to_tree("#BOS 1\n#EOS 1\n").is_err());
+    }
+
+    #[test]
+    fn comments() {
+        let input = "#BOS 7 2 1070544990 0 %% HEADLINE\n\
+                     %% checked\n\
+                     D\td\tART\tnsf\t--\t500\n\
+                     %% %% a comment line with enough columns to look like a terminal\n\
+                     A\ta\tNN\tnsf\tHD\t500\n\
+                     #500\t--\tNX\t--\t--\t0\n\
+                     #EOS 7\n\
+                     #BOS 8\n\
+                     %% only a comment\n\
+                     #EOS 8\n";
+        let trees = NegraReader::new(input.as_bytes())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(trees.len(), 1);
+        let tree = &trees[0];
+        assert_eq!(tree.n_terminals(), 2);
+        assert_eq!(tree.sentence_id(), Some("7"));
+        assert_eq!(tree.metadata()["editor"], "2");
+        assert_eq!(tree.metadata()["date"], "1070544990");
+        assert_eq!(tree.metadata()["origin"], "0");
+        assert_eq!(tree.metadata()["comment"], "HEADLINE");
+
+        let tree = negra_to_tree("#BOS 1\nA\ta\tNN\t--\t--\t0\n#EOS 1\n").unwrap();
+        assert_eq!(tree.metadata().len(), 1);
+    }
+
     #[test]
     fn test_first10_ok() {
         let input = File::open("testdata/10.negra").unwrap();
@@ -383,7 +541,8 @@ mod tests {
     fn terminal() {
         let term = "was etwas   PIS *** HD  502 %some random comment that gets ignored\n";
         let mut v = NEGRAParser::parse(Rule::terminal, term).unwrap();
-        let (parent_id, edge, terminal) = process_terminal(v.next().unwrap(), 0).unwrap();
+        let (parent_id, edge, terminal) =
+            process_terminal(v.next().unwrap(), 0, NegraMorph::Features).unwrap();
         assert_eq!(parent_id, 502);
         assert_eq!(edge, Edge::from(Some("HD")));
         let mut term = Terminal::new("was", "PIS", 0);
@@ -396,6 +555,54 @@ mod tests {
         assert!(NEGRAParser::parse(Rule::nonterminal, term).is_err());
     }
 
+    #[test]
+    fn terminal_morph() {
+        let term = "was etwas   PIS nom.sg HD  502\n";
+        let pair = NEGRAParser::parse(Rule::terminal, term)
+            .unwrap()
+            .next()
+            .unwrap();
+        let (_, _, terminal) = process_terminal(pair.clone(), 0, NegraMorph::Lemma).unwrap();
+        let mut target = Terminal::new("was", "PIS", 0);
+        target.set_lemma(Some("nom.sg"));
+        assert_eq!(terminal, Node::Terminal(target.clone()));
+
+        let (_, _, terminal) =
+            process_terminal(pair.clone(), 0, NegraMorph::LemmaAndFeatures).unwrap();
+        target.set_features(Some(Features::from("nom.sg")));
+        assert_eq!(terminal, Node::Terminal(target));
+
+        let term = "was etwas   PIS -- HD  502\n";
+        let pair = NEGRAParser::parse(Rule::terminal, term)
+            .unwrap()
+            .next()
+            .unwrap();
+        let (_, _, terminal) = process_terminal(pair, 0, NegraMorph::Lemma).unwrap();
+        assert_eq!(terminal, Node::Terminal(Terminal::new("was", "PIS", 0)));
+    }
+
+    #[test]
+    fn sentence_id() {
+        let f = File::open("testdata/10.negra").unwrap();
+        let ids = NegraReader::new(BufReader::new(f))
+            .map(|tree| tree.unwrap().sentence_id().map(ToOwned::to_owned))
+            .collect::<Vec<_>>();
+        assert_eq!(ids[0].as_deref(), Some("1"));
+        assert!(ids.iter().all(Option::is_some));
+    }
+
+    #[test]
+    fn reader_morph() {
+        let f = File::open("testdata/single.negra").unwrap();
+        let tree = NegraReader::with_morph(BufReader::new(f), NegraMorph::Lemma)
+            .next()
+            .unwrap()
+            .unwrap();
+        let v = tree.terminals().next().unwrap();
+        assert_eq!(tree[v].terminal().unwrap().lemma(), Some("3sit"));
+        assert_eq!(tree[v].features(), None);
+    }
+
     #[test]
     fn nonterminal() {
         let nt = "#502			--			NX	--		ON	503\n";
@@ -508,4 +715,44 @@ mod tests {
         #EOS 1";
         NEGRAParser::parse(Rule::sent, s).unwrap();
     }
+
+    #[test]
+    fn discontinuous() {
+        let s = "#BOS 1
+A	a	NN	--	HD	501
+B	b	VVFIN	--	HD	500
+C	c	NN	--	--	501
+D	d	$.	--	--	0
+#500	--	S	--	--	0
+#501	--	NP	--	SB	500
+#EOS 1
+";
+        let tree = negra_to_tree(s).unwrap();
+        assert_eq!(tree.projectivity(), Projectivity::Nonprojective);
+        assert!(!tree.projective());
+        let np = tree
+            .nonterminals()
+            .find(|&nt| tree[nt].label() == "NP")
+            .unwrap();
+        assert_eq!(tree[np].span().into_iter().collect::<Vec<_>>(), vec![0, 2]);
+        assert!(tree[np].span().discontinuous().is_some());
+        let (s_node, _) = tree.parent(np).unwrap();
+        assert_eq!(tree[s_node].label(), "S");
+        assert_eq!(tree[s_node].span(), &Span::new_continuous(0, 3));
+        assert_eq!(tree.n_terminals(), 4);
+        tree.validate().unwrap();
+
+        let cycle = "#BOS 1
+A	a	NN	--	HD	500
+#500	--	NP	--	--	501
+#501	--	NP	--	--	500
+#EOS 1
+";
+        assert!(negra_to_tree(cycle).is_err());
+        let unknown_parent = "#BOS 1
+A	a	NN	--	HD	502
+#EOS 1
+";
+        assert!(negra_to_tree(unknown_parent).is_err());
+    }
 }
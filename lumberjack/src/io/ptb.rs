@@ -1,19 +1,29 @@
+use std::borrow::Cow;
 use std::io::{BufRead, Lines, Write};
 
 use failure::Error;
 use pest::iterators::Pair;
 use pest::Parser;
 use petgraph::prelude::{Direction, EdgeRef, NodeIndex, StableGraph};
+use petgraph::stable_graph::EdgeReference;
 
-use crate::io::{WriteTree, NODE_ANNOTATION_FEATURE_KEY};
-use crate::{Edge, Node, NonTerminal, Projectivity, Span, Terminal, Tree};
+use crate::head::HEAD_FEATURE_KEY;
+use crate::io::{WriteTree, EMPTY_ELEMENTS_FEATURE_KEY, NODE_ANNOTATION_FEATURE_KEY};
+use crate::{
+    Edge, Features, LumberjackError, Node, NonTerminal, Projectivity, Span, Terminal, Tree,
+};
+
+// key of the lemma among the features of SPMRL preterminals.
+static SPMRL_LEMMA_KEY: &str = "lem";
+// label assigned to unlabeled roots of SPMRL trees.
+static SPMRL_ROOT_LABEL: &str = "VROOT";
 
 /// PTBWriter.
 ///
 /// Struct to write trees to the bracketed formats defined in `PTBFormat`.
 pub struct PTBWriter<W> {
     writer: W,
-    format: PTBFormat,
+    formatter: PTBFormatter,
 }
 
 impl<W> PTBWriter<W>
@@ -22,7 +32,12 @@ where
 {
     /// Construct a new writer.
     pub fn new(writer: W, format: PTBFormat) -> Self {
-        PTBWriter { writer, format }
+        PTBWriter::with_formatter(writer, PTBFormatter::new(format))
+    }
+
+    /// Construct a new writer using a configured `PTBFormatter`.
+    pub fn with_formatter(writer: W, formatter: PTBFormatter) -> Self {
+        PTBWriter { writer, formatter }
     }
 }
 
@@ -31,10 +46,15 @@ where
     W: Write,
 {
     fn write_tree(&mut self, tree: &Tree) -> Result<(), Error> {
-        let s = self.format.tree_to_string(tree)?;
+        let s = self.formatter.tree_to_string(tree)?;
         writeln!(self.writer, "{}", s)?;
         Ok(())
     }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
 }
 
 // dummy struct required by pest
@@ -43,16 +63,22 @@ where
 struct PTBParser;
 
 /// `PTBFormat`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum PTBFormat {
     /// PTB V2 Format.
     ///
     /// Trees don't include edge labels, some nodes contain additional tags. Node labels are split
     /// on the first `"-"`, additional tags are put together into the same `annotation` field.
+    /// Labels starting with `"-"` such as `-NONE-` are not split.
     ///
     /// E.g. `"(TAG-annotation1-annotation2 (POS terminal))"` results in a non-terminal with:
     /// * `label == "TAG"`
-    /// * `annotation == Some("annotation1-annotation2")`
+    /// * `annotation == Some("-annotation1-annotation2")`
     /// * `edge == None`
+    ///
+    /// The leading delimiter is part of the annotation, annotations are written as they are.
+    /// Annotations without leading delimiter, e.g. read from other formats, are joined with the
+    /// label by `"-"`. Annotations of preterminals are not stored.
     PTB,
     /// Simple Format.
     ///
@@ -74,98 +100,281 @@ pub enum PTBFormat {
     /// * `annotation == Some("annotation")`
     /// * `edge == Some("edge_label")`
     TueBa,
+    /// SPMRL shared task format.
+    ///
+    /// Trees include grammatical functions and morphological features. Node labels are split on
+    /// the first `"-"` into tag and edge label, labels starting with `"-"` are not split.
+    /// Features follow the label as `"key=value"` pairs delimited by `"|"` and enclosed in
+    /// `"##"`, the lemma of a terminal is the value of the key `"lem"`. Unlabeled roots, e.g. in
+    /// `"( (S ...))"`, are labeled `"VROOT"`.
+    ///
+    /// E.g. `"(ART-NK##lem=der|case=nom## Der)"` results in a terminal with:
+    /// * `label == "ART"`
+    /// * `lemma == Some("der")`
+    /// * `features == "case:nom"`
+    /// * `edge == Some("NK")`
+    Spmrl,
 }
 
 impl PTBFormat {
     /// Convert the tree into a bracketed string according to the format.
+    ///
+    /// Uses the default `PTBFormatter` for the format.
     pub fn tree_to_string(&self, tree: &Tree) -> Result<String, Error> {
-        if tree.projective() {
-            Ok(self.format_sub_tree(tree, tree.root(), None))
-        } else {
-            Err(format_err!("Can't linearize nonprojective tree"))
-        }
+        PTBFormatter::new(*self).tree_to_string(tree)
     }
 
     /// Construct a tree from a bracketed representation according to the format.
+    ///
+    /// Uses the default `PTBFormatter` for the format.
     pub fn string_to_tree(&self, string: &str) -> Result<Tree, Error> {
-        let mut graph = StableGraph::new();
-        let mut n_terminals = 0;
-        let mut parsed_line = PTBParser::parse(Rule::tree, string)?;
-        let (_, root, _) =
-            self.parse_value(parsed_line.next().unwrap(), &mut graph, &mut n_terminals)?;
-        Ok(Tree::new(
-            graph,
-            n_terminals,
-            root,
-            Projectivity::Projective,
-        ))
+        PTBFormatter::new(*self).string_to_tree(string)
     }
+}
 
-    fn format_sub_tree(&self, sentence: &Tree, position: NodeIndex, edge: Option<&str>) -> String {
-        let sent = sentence.graph();
+/// `PTBFormatter`.
+///
+/// Reads and writes trees in a `PTBFormat` while controlling which annotations are part of the
+/// bracketed representation.
+///
+/// * Edge labels are appended to node labels, delimited by `":"` in `PTBFormat::TueBa` and by
+///   `"-"` otherwise. Missing edge labels are written as `"--"` in `PTBFormat::TueBa` and
+///   omitted otherwise.
+/// * Features are appended to node labels after `"##"`. The feature holding the node annotation
+///   is only written for `PTBFormat::Simple`, other formats write it as part of the label.
+/// * Terminal indices are appended to forms after `"_"`, e.g. `(DT the_0)`.
+///
+/// When reading with features enabled, everything after the first `"#"` of a label is read into
+/// the node's `Features` and stripped from the label, e.g. `(NN:HD#case:nom|num:sg dog)`. One or
+/// more `"#"` are accepted as delimiter. A leading `"#"` belongs to the label, so that the PTB
+/// tag `#` is read as such, e.g. `(###case:nom #)` is a terminal tagged `#` with features.
+///
+/// When reading with bare terminals enabled, tokens without part-of-speech tag such as `the` and
+/// `dog` in `(S (NP the dog))` are read as terminals with a placeholder part-of-speech tag. A
+/// bracket with a label and a single token, e.g. `(NP dog)`, is always read as a preterminal.
+///
+/// With quoted terminals enabled, double-quoted forms may contain whitespace, e.g.
+/// `(NE "New York")`.
+///
+/// Labels and part-of-speech tags can be normalized to a `LabelCase` when reading. Edge labels,
+/// annotations and features are not normalized.
+///
+/// By default, edge labels are written for `PTBFormat::TueBa` and `PTBFormat::Spmrl`, features
+/// are only read and written for `PTBFormat::Spmrl`, no terminal indices are written, bare
+/// terminals are rejected, quotes are part of forms, trees are written on a single line and
+/// labels are read as they are.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PTBFormatter {
+    format: PTBFormat,
+    edge_labels: bool,
+    features: bool,
+    root_features: bool,
+    terminal_indices: bool,
+    bare_terminals: bool,
+    bare_terminal_pos: String,
+    quoted_terminals: bool,
+    indent: Option<usize>,
+    label_case: LabelCase,
+    pos_case: LabelCase,
+    empty_elements: bool,
+    head_marker: Option<String>,
+    function_labels: Option<String>,
+}
 
-        match &sent[position] {
-            Node::Terminal(terminal) => self.fmt_term(terminal.form(), terminal.label(), edge),
-            Node::NonTerminal(nt) => {
-                let mut nodes: Vec<_> = sent
-                    .edges_directed(position, Direction::Outgoing)
-                    .collect::<Vec<_>>();
-                // sort child nodes by covered span
-                nodes.sort_by(|edge_ref_1, edge_ref_2| {
-                    let span_1 = sentence.graph()[edge_ref_1.target()].span();
-                    let span_2 = sentence.graph()[edge_ref_2.target()].span();
-                    span_1.cmp(&span_2)
-                });
-                let mut sub_tree_rep = Vec::with_capacity(nodes.len());
-                sub_tree_rep.push(self.fmt_inner(nt, edge));
-                sub_tree_rep.extend(nodes.into_iter().map(|edge_ref| {
-                    self.format_sub_tree(sentence, edge_ref.target(), edge_ref.weight().label())
-                }));
-                let node_sep = if let PTBFormat::TueBa = self { "" } else { " " };
-                format!("({})", sub_tree_rep.join(node_sep))
-            }
+impl PTBFormatter {
+    /// Construct a new formatter with the defaults of `format`.
+    pub fn new(format: PTBFormat) -> Self {
+        PTBFormatter {
+            format,
+            edge_labels: format == PTBFormat::TueBa || format == PTBFormat::Spmrl,
+            features: format == PTBFormat::Spmrl,
+            root_features: false,
+            terminal_indices: false,
+            bare_terminals: false,
+            bare_terminal_pos: "_".to_owned(),
+            quoted_terminals: false,
+            indent: None,
+            label_case: LabelCase::Keep,
+            pos_case: LabelCase::Keep,
+            empty_elements: false,
+            head_marker: None,
+            function_labels: None,
         }
     }
 
-    fn fmt_inner(&self, nt: &NonTerminal, edge: Option<&str>) -> String {
-        let mut representation = nt.label().to_string();
-        let annotation = if let Some(annotation) = nt
-            .features()
-            .map(|f| f.get_val(NODE_ANNOTATION_FEATURE_KEY))
-        {
-            annotation
+    /// Set whether edge labels are written.
+    pub fn edge_labels(mut self, edge_labels: bool) -> Self {
+        self.edge_labels = edge_labels;
+        self
+    }
+
+    /// Set whether features are read and written.
+    pub fn features(mut self, features: bool) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Set whether features of the root are read and written.
+    ///
+    /// This allows carrying sentence-level data such as processing markers without reading
+    /// features of all nodes, e.g. `(S##done:true (NP (# #)) ...)`. Has no effect if
+    /// `PTBFormatter::features` is set. Defaults to `false`.
+    pub fn root_features(mut self, root_features: bool) -> Self {
+        self.root_features = root_features;
+        self
+    }
+
+    /// Set whether terminal indices are written.
+    ///
+    /// The index of a terminal is the lower bound of its span. Trees written with terminal
+    /// indices can't be read back since the indices become part of the forms.
+    pub fn terminal_indices(mut self, terminal_indices: bool) -> Self {
+        self.terminal_indices = terminal_indices;
+        self
+    }
+
+    /// Set whether terminals without part-of-speech tag are read.
+    pub fn bare_terminals(mut self, bare_terminals: bool) -> Self {
+        self.bare_terminals = bare_terminals;
+        self
+    }
+
+    /// Set the part-of-speech tag assigned to bare terminals.
+    ///
+    /// Defaults to `"_"`.
+    pub fn bare_terminal_pos(mut self, pos: impl Into<String>) -> Self {
+        self.bare_terminal_pos = pos.into();
+        self
+    }
+
+    /// Set whether double-quoted terminals are read and written.
+    ///
+    /// If `true`, a double-quoted form such as `"New York"` in `(NE "New York")` is read as a
+    /// single form without quotes. Forms containing whitespace are quoted when writing.
+    ///
+    /// If `false`, quotes are part of the form, which is required for corpora using literal
+    /// quotes as tokens.
+    pub fn quoted_terminals(mut self, quoted_terminals: bool) -> Self {
+        self.quoted_terminals = quoted_terminals;
+        self
+    }
+
+    /// Set the indentation width of multi-line output.
+    ///
+    /// If `Some(width)`, each `NonTerminal` starts a new line indented by `width` spaces per
+    /// level of depth. `Terminal`s are written inline as long as no sibling before them started
+    /// a new line:
+    ///
+    /// ```text
+    /// (S
+    ///   (NP (DT the) (NN dog))
+    ///   (VBZ barks)
+    ///   (ADVP (RB very)
+    ///     (ADVP (RB loudly))))
+    /// ```
+    ///
+    /// Trees written with indentation are read with `PTBLineFormat::MultiLine`. If `None`, trees
+    /// are written on a single line.
+    pub fn indent(mut self, indent: Option<usize>) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// Set the case of `NonTerminal` labels when reading.
+    pub fn label_case(mut self, label_case: LabelCase) -> Self {
+        self.label_case = label_case;
+        self
+    }
+
+    /// Set the case of part-of-speech tags when reading.
+    ///
+    /// The part-of-speech tag of bare terminals is not affected.
+    pub fn pos_case(mut self, pos_case: LabelCase) -> Self {
+        self.pos_case = pos_case;
+        self
+    }
+
+    /// Set whether empty elements removed by `TreeOps::remove_traces` are written.
+    ///
+    /// If `true`, the removed empty elements are reinserted at their original positions and the
+    /// feature storing them is not written.
+    pub fn empty_elements(mut self, empty_elements: bool) -> Self {
+        self.empty_elements = empty_elements;
+        self
+    }
+
+    /// Set the marker of head children.
+    ///
+    /// If `Some(marker)`, a label ending with `marker` marks the node as head of its parent,
+    /// e.g. `(VP (VBZ* barks) (ADVP loudly))` with marker `*`. When reading, the marker is
+    /// removed from the label and the node is annotated with the feature `head:true` like
+    /// `TreeOps::annotate_head` does. In formats with edge labels or annotations, the marker
+    /// directly follows the label, e.g. `NP*:HD` for `PTBFormat::TueBa`.
+    ///
+    /// When writing, the marker is appended to the labels of nodes with the feature `head`, the
+    /// feature itself is not written. Defaults to `None`.
+    pub fn head_marker(mut self, marker: Option<&str>) -> Self {
+        self.head_marker = marker.map(ToOwned::to_owned);
+        self
+    }
+
+    /// Set the separator of function labels.
+    ///
+    /// If `Some(separator)`, the edge label of each `NonTerminal` is appended to its label after
+    /// `separator` when writing, e.g. `(S (NP-SB (NN dog)) (VP-HD (VBZ barks)))` with separator
+    /// `"-"`. Nodes without edge label, such as the root, get no suffix. Annotations are written
+    /// before the function label. This takes precedence over `PTBFormatter::edge_labels` for
+    /// `NonTerminal`s, the edge labels of `Terminal`s are not affected.
+    ///
+    /// When reading, the part of a `NonTerminal` label after the last `separator` is read as
+    /// edge label. Labels starting with `separator`, such as `-NONE-` for `"-"`, are not split.
+    /// Defaults to `None`.
+    pub fn function_labels(mut self, separator: Option<&str>) -> Self {
+        self.function_labels = separator.map(ToOwned::to_owned);
+        self
+    }
+
+    /// Convert the tree into a bracketed string.
+    ///
+    /// Returns `LumberjackError::CrossingBrackets` if the tree has crossing brackets, see
+    /// `Tree::has_crossing_brackets`. The stored projectivity of the tree is not trusted.
+    pub fn tree_to_string(&self, tree: &Tree) -> Result<String, Error> {
+        if tree.has_crossing_brackets() {
+            let nodes = tree
+                .nonprojective_nodes()
+                .into_iter()
+                .map(|nt| tree[nt].label().to_owned())
+                .collect();
+            Err(LumberjackError::CrossingBrackets { nodes }.into())
+        } else if let Some(indent) = self.indent {
+            Ok(self.format_indented(tree, tree.root(), None, 0, indent))
         } else {
-            None
-        };
-        match self {
-            PTBFormat::PTB => {
-                if let Some(annotation) = annotation {
-                    representation.push('-');
-                    representation.push_str(annotation);
-                }
-            }
-            PTBFormat::Simple => (),
-            PTBFormat::TueBa => {
-                if let Some(annotation) = annotation {
-                    representation.push('=');
-                    representation.push_str(annotation);
-                }
-                representation.push(':');
-                representation.push_str(edge.unwrap_or("--"));
-            }
+            Ok(self.format_sub_tree(tree, tree.root(), None))
         }
-        representation
     }
 
-    fn fmt_term(&self, form: &str, pos: &str, edge: Option<&str>) -> String {
-        let pos = pos.replace("(", "LBR").replace(")", "RBR");
-        let form = form.replace("(", "LBR").replace(")", "RBR");
-        if let PTBFormat::TueBa = self {
-            let edge = edge.unwrap_or("--");
-            format!("({}:{} {})", pos, edge, form)
+    /// Construct a tree from a bracketed representation.
+    pub fn string_to_tree(&self, string: &str) -> Result<Tree, Error> {
+        let mut graph = StableGraph::new();
+        let mut n_terminals = 0;
+        let string = if self.format == PTBFormat::Spmrl {
+            label_root(string, SPMRL_ROOT_LABEL)
         } else {
-            format!("({} {})", pos, form)
-        }
+            Cow::Borrowed(string)
+        };
+        let mut parsed_line = PTBParser::parse(Rule::tree, &string)?;
+        let (_, root, _) = self.parse_value(
+            parsed_line.next().unwrap(),
+            &mut graph,
+            &mut n_terminals,
+            true,
+        )?;
+        Ok(Tree::new(
+            graph,
+            n_terminals,
+            root,
+            Projectivity::Projective,
+        ))
     }
 
     // this method traverses the linearized tree and builds a StableGraph
@@ -174,13 +383,23 @@ impl PTBFormat {
         pair: Pair<Rule>,
         g: &mut StableGraph<Node, Edge>,
         terminals: &mut usize,
+        root: bool,
     ) -> Result<(Span, NodeIndex, Edge), Error> {
         match pair.as_rule() {
             Rule::nonterminal => {
                 let mut pairs = pair.into_inner();
                 // first rule after matching nonterminal will always be the label of the inner node
-                let (label, edge, annotation) = self.process_label(pairs.next().unwrap())?;
-                let mut nt = NonTerminal::new(label, 0);
+                let features = self.features || (root && self.root_features);
+                let (label, edge, annotation, features) =
+                    self.process_label(pairs.next().unwrap(), true, features)?;
+                let (label, head) = self.strip_head_marker(label);
+                let mut nt = NonTerminal::new(self.label_case.apply(label), 0);
+                if let Some(features) = features {
+                    nt.set_features(Some(self.read_features(features)));
+                }
+                if head {
+                    nt.features_mut().insert(HEAD_FEATURE_KEY, Some("true"));
+                }
                 if annotation.is_some() {
                     nt.features_mut().insert(
                         NODE_ANNOTATION_FEATURE_KEY,
@@ -192,7 +411,8 @@ impl PTBFormat {
                 let mut lower = 0;
                 let mut upper = 0;
                 for (idx, inner_pair) in pairs.enumerate() {
-                    let (span, child_idx, edge) = self.parse_value(inner_pair, g, terminals)?;
+                    let (span, child_idx, edge) =
+                        self.parse_value(inner_pair, g, terminals, false)?;
                     if idx == 0 {
                         lower = span.lower();
                     }
@@ -205,12 +425,37 @@ impl PTBFormat {
                 Ok((span, nt_idx, edge.into()))
             }
             Rule::preterminal => {
-                let (edge, pos, form) = self.process_preterminal(pair)?;
-                let term_idx = g.add_node(Node::Terminal(Terminal::new(form, pos, *terminals)));
+                let (edge, pos, form, features) = self.process_preterminal(pair)?;
+                let (pos, head) = self.strip_head_marker(pos);
+                let mut terminal = Terminal::new(form, self.pos_case.apply(pos), *terminals);
+                terminal.set_features(features.map(|features| self.read_features(features)));
+                if self.format == PTBFormat::Spmrl {
+                    move_lemma(&mut terminal);
+                }
+                if head {
+                    terminal
+                        .features_mut()
+                        .insert(HEAD_FEATURE_KEY, Some("true"));
+                }
+                let term_idx = g.add_node(Node::Terminal(terminal));
                 let span = Span::from(*terminals);
                 *terminals += 1;
                 Ok((span, term_idx, edge.into()))
             }
+            Rule::terminal => {
+                if !self.bare_terminals {
+                    return Err(format_err!(
+                        "Terminal without part-of-speech tag: {}",
+                        pair.as_str()
+                    ));
+                }
+                let form = self.process_form(pair.as_str())?;
+                let terminal = Terminal::new(form, self.bare_terminal_pos.as_str(), *terminals);
+                let term_idx = g.add_node(Node::Terminal(terminal));
+                let span = Span::from(*terminals);
+                *terminals += 1;
+                Ok((span, term_idx, Edge::default()))
+            }
             _ => {
                 eprintln!("{:?}", pair);
                 unreachable!()
@@ -218,18 +463,19 @@ impl PTBFormat {
         }
     }
 
-    // Preterminal consists of POS, optional Edge label and Terminal
+    // Preterminal consists of POS, optional Edge label, optional features and Terminal
+    #[allow(clippy::type_complexity)]
     fn process_preterminal<'a>(
         &self,
         pair: Pair<'a, Rule>,
-    ) -> Result<(Option<&'a str>, &'a str, &'a str), Error> {
+    ) -> Result<(Option<&'a str>, &'a str, &'a str, Option<&'a str>), Error> {
         let mut pairs = pair.into_inner();
         let pos = pairs.next().unwrap();
-        let (tag, edge, _) = self.process_label(pos)?;
+        let (tag, edge, _, features) = self.process_label(pos, false, self.features)?;
 
         let form = pairs.next().unwrap();
         if let Rule::terminal = form.as_rule() {
-            Ok((edge, tag, form.as_str()))
+            Ok((edge, tag, self.process_form(form.as_str())?, features))
         } else {
             Err(format_err!(
                 "Preterminal not starting with form: {}",
@@ -238,21 +484,105 @@ impl PTBFormat {
         }
     }
 
+    // SPMRL features are `key=value` pairs, all other formats use `key:value`.
+    fn read_features(&self, features: &str) -> Features {
+        match self.format {
+            PTBFormat::Spmrl => features
+                .split('|')
+                .filter(|f| !f.is_empty())
+                .map(|f| {
+                    let mut parts = f.splitn(2, '=');
+                    (parts.next().unwrap(), parts.next())
+                })
+                .collect(),
+            _ => Features::from(features),
+        }
+    }
+
+    // Remove the head marker from `label`, returns whether the label was marked.
+    fn strip_head_marker<'a>(&self, label: &'a str) -> (&'a str, bool) {
+        match self.head_marker.as_ref() {
+            Some(marker) if label.len() > marker.len() && label.ends_with(marker.as_str()) => {
+                (&label[..label.len() - marker.len()], true)
+            }
+            _ => (label, false),
+        }
+    }
+
+    // Append the head marker to `representation` if `features` mark a head.
+    fn push_head_marker(&self, representation: &mut String, features: Option<&Features>) {
+        if let Some(marker) = self.head_marker.as_ref() {
+            if features.is_some_and(|features| features.inner().contains_key(HEAD_FEATURE_KEY)) {
+                representation.push_str(marker);
+            }
+        }
+    }
+
+    // Double-quoted forms may contain whitespace, quotes are removed if quoted terminals are
+    // enabled. Otherwise, the quotes are part of the form.
+    fn process_form<'a>(&self, form: &'a str) -> Result<&'a str, Error> {
+        let is_quoted = form.len() > 2 && form.starts_with('"') && form.ends_with('"');
+        if is_quoted && self.quoted_terminals {
+            Ok(&form[1..form.len() - 1])
+        } else if form.contains(char::is_whitespace) {
+            Err(format_err!(
+                "Quoted terminal with whitespace while quoting is disabled: {}",
+                form
+            ))
+        } else {
+            Ok(form)
+        }
+    }
+
     // All nodes in the tree start with a label corresponding either to the parse tag or to the POS of
-    // a given token. The label is optionally followed by an edge label.
+    // a given token. The label is optionally followed by an edge label and features.
+    //
+    // Function labels are only split from `NonTerminal` labels.
+    //
+    // Returns label, edge label, annotation and features.
+    #[allow(clippy::type_complexity)]
     fn process_label<'a>(
         &self,
         pair: Pair<'a, Rule>,
-    ) -> Result<(&'a str, Option<&'a str>, Option<&'a str>), Error> {
+        nonterminal: bool,
+        read_features: bool,
+    ) -> Result<(&'a str, Option<&'a str>, Option<&'a str>, Option<&'a str>), Error> {
         if let Rule::node_label = pair.as_rule() {
-            let label = pair.as_str();
+            let mut label = pair.as_str();
+            let mut features = None;
+            if read_features {
+                // a leading '#' is the label itself, e.g. the PTB tag '#'.
+                let start = if label.starts_with('#') { 1 } else { 0 };
+                let split = label[start..].find('#').map(|idx| idx + start);
+                let rest = split.map(|idx| &label[idx..]);
+                label = split.map_or(label, |idx| &label[..idx]);
+                features = rest
+                    .map(|features| features.trim_start_matches('#'))
+                    .map(|features| match self.format {
+                        PTBFormat::Spmrl => features.trim_end_matches(['#', '|']),
+                        _ => features,
+                    })
+                    .filter(|features| !features.is_empty());
+            }
+            let mut function = None;
+            if let (true, Some(separator)) = (nonterminal, self.function_labels.as_ref()) {
+                if !label.starts_with(separator.as_str()) {
+                    if let Some(idx) = label.rfind(separator.as_str()) {
+                        function = Some(&label[idx + separator.len()..]);
+                        label = &label[..idx];
+                    }
+                }
+            }
             // split label and edge label
-            match self {
+            match self.format {
+                // labels such as -NONE- or -LRB- start with the delimiter and are not split.
+                PTBFormat::PTB if label.starts_with('-') => Ok((label, function, None, features)),
                 PTBFormat::PTB => {
+                    // find returns a char boundary, slicing there keeps multibyte labels intact.
                     if let Some(idx) = label.find('-') {
-                        Ok((&label[..idx], None, Some(&label[idx..])))
+                        Ok((&label[..idx], function, Some(&label[idx..]), features))
                     } else {
-                        Ok((label, None, None))
+                        Ok((label, function, None, features))
                     }
                 }
                 PTBFormat::TueBa => {
@@ -262,9 +592,15 @@ impl PTBFormat {
                     let mut label_parts = tag.split('=');
                     let label = label_parts.next().unwrap();
                     let annotation = label_parts.next();
-                    Ok((label, edge, annotation))
+                    Ok((label, function.or(edge), annotation, features))
+                }
+                PTBFormat::Simple => Ok((label, function, None, features)),
+                PTBFormat::Spmrl if label.starts_with('-') => Ok((label, function, None, features)),
+                PTBFormat::Spmrl => {
+                    let mut parts = label.splitn(2, '-');
+                    let tag = parts.next().unwrap();
+                    Ok((tag, function.or_else(|| parts.next()), None, features))
                 }
-                PTBFormat::Simple => Ok((label, None, None)),
             }
         } else {
             Err(format_err!(
@@ -273,21 +609,356 @@ impl PTBFormat {
             ))
         }
     }
+
+    pub(crate) fn format_sub_tree(
+        &self,
+        sentence: &Tree,
+        position: NodeIndex,
+        edge: Option<&str>,
+    ) -> String {
+        let sent = sentence.graph();
+
+        match &sent[position] {
+            Node::Terminal(terminal) => self.fmt_term(terminal, edge),
+            Node::NonTerminal(nt) => {
+                let nodes = sorted_children(sentence, position);
+                let empty_elements = self.removed_empty_elements(nt);
+                let mut sub_tree_rep = Vec::with_capacity(nodes.len() + empty_elements.len() + 1);
+                sub_tree_rep.push(self.fmt_inner(nt, edge, position == sentence.root()));
+                for (idx, edge_ref) in nodes.into_iter().enumerate() {
+                    sub_tree_rep.extend(empty_elements_at(&empty_elements, idx).map(str::to_owned));
+                    sub_tree_rep.push(self.format_sub_tree(
+                        sentence,
+                        edge_ref.target(),
+                        edge_ref.weight().label(),
+                    ));
+                }
+                let n_children = sentence.children(position).count();
+                sub_tree_rep
+                    .extend(empty_elements_at(&empty_elements, n_children).map(str::to_owned));
+                format!("({})", sub_tree_rep.join(self.node_sep()))
+            }
+        }
+    }
+
+    fn format_indented(
+        &self,
+        sentence: &Tree,
+        position: NodeIndex,
+        edge: Option<&str>,
+        depth: usize,
+        indent: usize,
+    ) -> String {
+        match &sentence[position] {
+            Node::Terminal(terminal) => self.fmt_term(terminal, edge),
+            Node::NonTerminal(nt) => {
+                let root = position == sentence.root();
+                let mut sub_tree_rep = format!("({}", self.fmt_inner(nt, edge, root));
+                let empty_elements = self.removed_empty_elements(nt);
+                let nodes = sorted_children(sentence, position);
+                let n_children = nodes.len();
+                let mut multi_line = false;
+                for (idx, edge_ref) in nodes.into_iter().enumerate() {
+                    for empty_element in empty_elements_at(&empty_elements, idx) {
+                        sub_tree_rep.push_str(self.node_sep());
+                        sub_tree_rep.push_str(empty_element);
+                    }
+                    let child = edge_ref.target();
+                    let edge = edge_ref.weight().label();
+                    if sentence[child].is_terminal() && !multi_line {
+                        sub_tree_rep.push_str(self.node_sep());
+                        sub_tree_rep.push_str(&self.format_sub_tree(sentence, child, edge));
+                    } else {
+                        // once a child started a new line, all following siblings do.
+                        multi_line = true;
+                        sub_tree_rep.push('\n');
+                        sub_tree_rep.push_str(&" ".repeat((depth + 1) * indent));
+                        sub_tree_rep.push_str(&self.format_indented(
+                            sentence,
+                            child,
+                            edge,
+                            depth + 1,
+                            indent,
+                        ));
+                    }
+                }
+                for empty_element in empty_elements_at(&empty_elements, n_children) {
+                    sub_tree_rep.push_str(self.node_sep());
+                    sub_tree_rep.push_str(empty_element);
+                }
+                sub_tree_rep.push(')');
+                sub_tree_rep
+            }
+        }
+    }
+
+    // Get the empty elements removed below `nt` with the number of preceding children.
+    fn removed_empty_elements(&self, nt: &NonTerminal) -> Vec<(usize, String)> {
+        if !self.empty_elements {
+            return Vec::new();
+        }
+        nt.features()
+            .and_then(|features| features.get_val(EMPTY_ELEMENTS_FEATURE_KEY))
+            .into_iter()
+            .flat_map(|empty_elements| empty_elements.split(';'))
+            .filter_map(|empty_element| {
+                let mut parts = empty_element.splitn(2, ':');
+                let idx = parts.next()?.parse().ok()?;
+                Some((idx, decode_empty_element(parts.next()?)?))
+            })
+            .collect()
+    }
+
+    fn node_sep(&self) -> &'static str {
+        if let PTBFormat::TueBa = self.format {
+            ""
+        } else {
+            " "
+        }
+    }
+
+    fn fmt_inner(&self, nt: &NonTerminal, edge: Option<&str>, root: bool) -> String {
+        let mut representation = nt.label().to_string();
+        self.push_head_marker(&mut representation, nt.features());
+        let annotation = nt
+            .features()
+            .and_then(|f| f.get_val(NODE_ANNOTATION_FEATURE_KEY));
+        match self.format {
+            PTBFormat::PTB => {
+                if let Some(annotation) = annotation {
+                    // annotations read from PTB start with the delimiter.
+                    if !annotation.starts_with('-') {
+                        representation.push('-');
+                    }
+                    representation.push_str(annotation);
+                }
+            }
+            PTBFormat::Simple | PTBFormat::Spmrl => (),
+            PTBFormat::TueBa => {
+                if let Some(annotation) = annotation {
+                    representation.push('=');
+                    representation.push_str(annotation);
+                }
+            }
+        }
+        match (self.function_labels.as_ref(), edge) {
+            (Some(separator), Some(edge)) => {
+                representation.push_str(separator);
+                representation.push_str(edge);
+            }
+            (Some(_), None) => (),
+            (None, _) => self.push_edge(&mut representation, edge),
+        }
+        self.push_features(&mut representation, nt.features(), root);
+        representation
+    }
+
+    fn fmt_term(&self, terminal: &Terminal, edge: Option<&str>) -> String {
+        let mut pos = terminal.label().replace("(", "LBR").replace(")", "RBR");
+        let mut form = terminal.form().replace("(", "LBR").replace(")", "RBR");
+        if self.quoted_terminals && form.contains(char::is_whitespace) {
+            form = format!("\"{}\"", form);
+        }
+        if self.terminal_indices {
+            form.push('_');
+            form.push_str(&terminal.span().lower().to_string());
+        }
+        self.push_head_marker(&mut pos, terminal.features());
+        self.push_edge(&mut pos, edge);
+        let features = match (self.format, terminal.lemma()) {
+            (PTBFormat::Spmrl, Some(lemma)) => {
+                let mut features = terminal.features().cloned().unwrap_or_default();
+                features.insert(SPMRL_LEMMA_KEY, Some(lemma));
+                Some(Cow::Owned(features))
+            }
+            _ => terminal.features().map(Cow::Borrowed),
+        };
+        self.push_features(&mut pos, features.as_deref(), false);
+        format!("({} {})", pos, form)
+    }
+
+    fn push_edge(&self, representation: &mut String, edge: Option<&str>) {
+        if !self.edge_labels {
+            return;
+        }
+        if let PTBFormat::TueBa = self.format {
+            representation.push(':');
+            representation.push_str(edge.unwrap_or("--"));
+        } else if let Some(edge) = edge {
+            representation.push('-');
+            representation.push_str(edge);
+        }
+    }
+
+    fn push_features(&self, representation: &mut String, features: Option<&Features>, root: bool) {
+        if !(self.features || (root && self.root_features)) {
+            return;
+        }
+        let mut features = match features {
+            Some(features) => features.clone(),
+            None => return,
+        };
+        if self.format != PTBFormat::Simple {
+            features.remove(NODE_ANNOTATION_FEATURE_KEY);
+        }
+        if self.empty_elements {
+            features.remove(EMPTY_ELEMENTS_FEATURE_KEY);
+        }
+        if self.head_marker.is_some() {
+            features.remove(HEAD_FEATURE_KEY);
+        }
+        if features.inner().is_empty() {
+            return;
+        }
+        representation.push_str("##");
+        if self.format == PTBFormat::Spmrl {
+            let pairs = features
+                .inner()
+                .keys()
+                .flat_map(|key| {
+                    features.get_all(key).into_iter().map(move |val| match val {
+                        Some(val) => format!("{}={}", key, val),
+                        None => key.to_owned(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            representation.push_str(&pairs.join("|"));
+            representation.push_str("##");
+        } else {
+            representation.push_str(&features.to_string());
+        }
+    }
+}
+
+// Label an unlabeled root, e.g. `( (S ...))` becomes `(VROOT (S ...))`.
+fn label_root<'a>(input: &'a str, label: &str) -> Cow<'a, str> {
+    let trimmed = input.trim_start();
+    match trimmed.strip_prefix('(') {
+        Some(rest) if rest.trim_start().starts_with('(') => {
+            Cow::Owned(format!("({}{}", label, rest))
+        }
+        _ => Cow::Borrowed(input),
+    }
+}
+
+// Move the SPMRL lemma from the features of `terminal` to its lemma.
+fn move_lemma(terminal: &mut Terminal) {
+    let lemma = terminal.features_mut().remove(SPMRL_LEMMA_KEY);
+    if terminal.features().is_some_and(|f| f.inner().is_empty()) {
+        terminal.set_features(None);
+    }
+    if lemma.is_some() {
+        terminal.set_lemma(lemma);
+    }
+}
+
+// Check whether `input` is blank or an empty bracket.
+fn is_empty_tree(input: &str) -> bool {
+    let mut chars = input.chars().filter(|c| !c.is_whitespace());
+    match chars.next() {
+        None => true,
+        Some('(') => chars.next() == Some(')') && chars.next().is_none(),
+        Some(_) => false,
+    }
+}
+
+// Get the empty elements that precede the child at `idx`.
+fn empty_elements_at(
+    empty_elements: &[(usize, String)],
+    idx: usize,
+) -> impl Iterator<Item = &str> + '_ {
+    empty_elements
+        .iter()
+        .filter(move |(position, _)| *position == idx)
+        .map(|(_, empty_element)| empty_element.as_str())
+}
+
+// Characters of removed empty elements that can't be part of a feature value in a label.
+const EMPTY_ELEMENT_ESCAPES: &str = "()|#%;:=";
+
+// Percent-encode the bracketed representation of a removed empty element, e.g.
+// `(-NONE- *T*-1)` becomes `%28-NONE-%20*T*-1%29`.
+pub(crate) fn encode_empty_element(empty_element: &str) -> String {
+    let mut encoded = String::with_capacity(empty_element.len());
+    for c in empty_element.chars() {
+        if c.is_ascii_whitespace() || EMPTY_ELEMENT_ESCAPES.contains(c) {
+            encoded.push_str(&format!("%{:02X}", c as u8));
+        } else {
+            encoded.push(c);
+        }
+    }
+    encoded
+}
+
+// Decode an empty element encoded by `encode_empty_element`.
+fn decode_empty_element(encoded: &str) -> Option<String> {
+    let mut decoded = String::with_capacity(encoded.len());
+    let mut chars = encoded.chars();
+    while let Some(c) = chars.next() {
+        if c == '%' {
+            let code = chars.by_ref().take(2).collect::<String>();
+            decoded.push(u8::from_str_radix(&code, 16).ok()? as char);
+        } else {
+            decoded.push(c);
+        }
+    }
+    Some(decoded)
+}
+
+// Get the outgoing edges of `node` sorted by the spans of their targets.
+fn sorted_children(tree: &Tree, node: NodeIndex) -> Vec<EdgeReference<'_, Edge>> {
+    let mut edges = tree
+        .graph()
+        .edges_directed(node, Direction::Outgoing)
+        .collect::<Vec<_>>();
+    edges.sort_by(|edge_ref_1, edge_ref_2| {
+        let span_1 = tree[edge_ref_1.target()].span();
+        let span_2 = tree[edge_ref_2.target()].span();
+        span_1.cmp(span_2)
+    });
+    edges
+}
+
+/// Case normalization of labels.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum LabelCase {
+    /// Leave labels as they are.
+    #[default]
+    Keep,
+    /// Convert labels to lowercase.
+    Lower,
+    /// Convert labels to uppercase.
+    Upper,
+}
+
+impl LabelCase {
+    fn apply(self, label: &str) -> String {
+        match self {
+            LabelCase::Keep => label.to_owned(),
+            LabelCase::Lower => label.to_lowercase(),
+            LabelCase::Upper => label.to_uppercase(),
+        }
+    }
 }
 
 /// `PTBLineFormat`.
 ///
 /// This enum specifies whether the trees are encoded in single-line or multi-line format.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum PTBLineFormat {
     SingleLine,
     MultiLine,
 }
 
 /// Iterator over trees in PTB format file.
+///
+/// Lines starting with `%` outside of trees are skipped. Empty inputs are skipped as well,
+/// i.e. blank lines and empty brackets such as `()`, no trees without terminals are returned.
 pub struct PTBReader<R> {
     inner: Lines<R>,
     line_format: PTBLineFormat,
-    format: PTBFormat,
+    formatter: PTBFormatter,
+    line_no: usize,
 }
 
 impl<R> Iterator for PTBReader<R>
@@ -298,36 +969,53 @@ where
 
     fn next(&mut self) -> Option<Self::Item> {
         if let PTBLineFormat::SingleLine = self.line_format {
-            while let Some(line) = self.inner.next() {
+            for line in self.inner.by_ref() {
+                self.line_no += 1;
                 let line = match line {
                     Ok(line) => line,
                     Err(err) => return Some(Err(err.into())),
                 };
-                if line.starts_with('%') {
+                if line.starts_with('%') || is_empty_tree(&line) {
                     continue;
                 }
-                return Some(self.format.string_to_tree(&line));
+                let mut open = Vec::new();
+                if let Err(err) = check_brackets(&line, self.line_no, &mut open) {
+                    return Some(Err(err.into()));
+                }
+                if let Some(err) = unterminated(&open) {
+                    return Some(Err(err.into()));
+                }
+                return Some(self.formatter.string_to_tree(&line));
             }
             return None;
         } else {
             let mut buffer = String::new();
-            let mut open = 0;
-            while let Some(line) = self.inner.next() {
+            let mut open = Vec::new();
+            for line in self.inner.by_ref() {
+                self.line_no += 1;
                 let line = match line {
                     Ok(line) => line,
                     Err(err) => return Some(Err(err.into())),
                 };
-                if (line.starts_with('%') && buffer.is_empty()) || line.is_empty() {
+                if (line.starts_with('%') && buffer.is_empty()) || line.trim().is_empty() {
                     continue;
                 }
-                let (line_open, line_closed) = count_pars(&line);
-                open += line_open;
-                open -= line_closed;
-                buffer.push_str(line.as_str());;
-                if open == 0 {
-                    return Some(self.format.string_to_tree(&buffer));
+                if let Err(err) = check_brackets(&line, self.line_no, &mut open) {
+                    return Some(Err(err.into()));
+                }
+                buffer.push_str(line.as_str());
+                if open.is_empty() {
+                    if is_empty_tree(&buffer) {
+                        buffer.clear();
+                        continue;
+                    }
+                    return Some(self.formatter.string_to_tree(&buffer));
                 }
             }
+            // input ended inside of a tree
+            if let Some(err) = unterminated(&open) {
+                return Some(Err(err.into()));
+            }
         }
         None
     }
@@ -339,38 +1027,213 @@ where
 {
     /// Constructs a new tree iterator.
     pub fn new(read: R, format: PTBFormat, line_format: PTBLineFormat) -> Self {
+        PTBReader::with_formatter(read, PTBFormatter::new(format), line_format)
+    }
+
+    /// Constructs a new tree iterator using a configured `PTBFormatter`.
+    pub fn with_formatter(read: R, formatter: PTBFormatter, line_format: PTBLineFormat) -> Self {
         PTBReader {
             inner: read.lines(),
-            format,
+            formatter,
             line_format,
+            line_no: 0,
         }
     }
 }
 
-fn count_pars(line: &str) -> (usize, usize) {
-    let mut open = 0;
-    let mut closed = 0;
-    for c in line.chars() {
+// Track the brackets in `line`, `open` holds the 1-based positions of unclosed brackets.
+//
+// Returns `Error` on a closing bracket without an open bracket.
+fn check_brackets(
+    line: &str,
+    line_no: usize,
+    open: &mut Vec<(usize, usize)>,
+) -> Result<(), LumberjackError> {
+    for (idx, c) in line.chars().enumerate() {
         if c == '(' {
-            open += 1
-        }
-        if c == ')' {
-            closed += 1
+            open.push((line_no, idx + 1));
+        } else if c == ')' && open.pop().is_none() {
+            return Err(LumberjackError::UnexpectedCloseBracket {
+                line: line_no,
+                column: idx + 1,
+            });
         }
     }
-    (open, closed)
+    Ok(())
+}
+
+// Get the error for a tree ending with the brackets in `open` still open.
+fn unterminated(open: &[(usize, usize)]) -> Option<LumberjackError> {
+    open.last()
+        .map(|&(line, column)| LumberjackError::UnterminatedTree {
+            line,
+            column,
+            depth: open.len(),
+        })
 }
 
 #[cfg(test)]
 mod tests {
     use std::fs::File;
-    use std::io::BufReader;
+    use std::io::{self, BufReader, Write};
 
     use petgraph::prelude::NodeIndex;
     use petgraph::stable_graph::StableGraph;
 
-    use crate::io::ptb::{PTBFormat, PTBLineFormat, PTBReader};
-    use crate::{Edge, Node, NonTerminal, Projectivity, Span, Terminal, Tree};
+    use crate::io::ptb::{LabelCase, PTBFormat, PTBFormatter, PTBLineFormat, PTBReader, PTBWriter};
+    use crate::io::WriteTree;
+    use crate::io::NODE_ANNOTATION_FEATURE_KEY;
+    use crate::{
+        Edge, Features, LumberjackError, Node, NonTerminal, Projectivity, Span, Terminal, Tree,
+    };
+
+    #[test]
+    fn function_labels() {
+        let input = "(S(NP:SB(DT:-- the)(NN:HD dog))(VP:HD(VBZ:HD barks)))";
+        let tree = PTBFormat::TueBa.string_to_tree(input).unwrap();
+        let written = tree.to_ptb_with_function_labels("-").unwrap();
+        assert_eq!(written, "(S (NP-SB (DT the) (NN dog)) (VP-HD (VBZ barks)))");
+
+        let formatter = PTBFormatter::new(PTBFormat::Simple).function_labels(Some("-"));
+        let read = formatter.string_to_tree(&written).unwrap();
+        let edges = |tree: &Tree| {
+            tree.nonterminals()
+                .map(|nt| {
+                    let edge = tree.parent(nt).and_then(|(_, edge)| tree[edge].label());
+                    (tree[nt].label().to_owned(), edge.map(ToOwned::to_owned))
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut expected = edges(&tree);
+        expected.sort();
+        let mut actual = edges(&read);
+        actual.sort();
+        assert_eq!(actual, expected);
+        assert_eq!(formatter.tree_to_string(&read).unwrap(), written);
+
+        // annotations precede function labels, labels starting with the separator are kept.
+        let formatter = PTBFormatter::new(PTBFormat::PTB).function_labels(Some("-"));
+        let tree = formatter
+            .string_to_tree("(S (NP-SBJ-SB (-NONE- *)) (-LRB- (-LRB- -LRB-)))")
+            .unwrap();
+        let np = tree
+            .children(tree.root())
+            .find(|&c| tree[c].label() == "NP");
+        let (_, edge) = tree.parent(np.unwrap()).unwrap();
+        assert_eq!(tree[edge].label(), Some("SB"));
+        assert_eq!(
+            formatter.tree_to_string(&tree).unwrap(),
+            "(S (NP-SBJ-SB (-NONE- *)) (-LRB- (-LRB- -LRB-)))"
+        );
+    }
+
+    #[test]
+    fn crossing_brackets() {
+        // (S (NP (DT the) (NN dog)) (VBZ barks)) with "barks" between "the" and "dog", the stored
+        // projectivity is wrong on purpose.
+        let mut graph = StableGraph::new();
+        let root = graph.add_node(Node::NonTerminal(NonTerminal::new(
+            "S",
+            Span::new_continuous(0, 3),
+        )));
+        let np = graph.add_node(Node::NonTerminal(NonTerminal::new(
+            "NP",
+            Span::from_vec(vec![0, 2]).unwrap(),
+        )));
+        let the = graph.add_node(Node::Terminal(Terminal::new("the", "DT", 0)));
+        let barks = graph.add_node(Node::Terminal(Terminal::new("barks", "VBZ", 1)));
+        let dog = graph.add_node(Node::Terminal(Terminal::new("dog", "NN", 2)));
+        graph.add_edge(root, np, Edge::default());
+        graph.add_edge(root, barks, Edge::default());
+        graph.add_edge(np, the, Edge::default());
+        graph.add_edge(np, dog, Edge::default());
+        let tree = Tree::new(graph, 3, root, Projectivity::Projective);
+        assert!(tree.projective());
+        assert!(tree.has_crossing_brackets());
+
+        let err = PTBFormat::Simple.tree_to_string(&tree).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LumberjackError>(),
+            Some(&LumberjackError::CrossingBrackets {
+                nodes: vec!["NP".to_owned()]
+            })
+        );
+        let mut writer = PTBWriter::new(Vec::new(), PTBFormat::Simple);
+        assert!(writer.write_tree(&tree).is_err());
+        assert!(writer.writer.is_empty());
+
+        let tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (NN dog)) (VBZ barks))")
+            .unwrap();
+        assert!(!tree.has_crossing_brackets());
+    }
+
+    #[test]
+    fn head_marker() {
+        let formatter = PTBFormatter::new(PTBFormat::TueBa).head_marker(Some("*"));
+        let input = "(S(NP*:SB(DT:-- the)(NN*:HD dog))(VP:--(VBZ* barks)))";
+        let tree = formatter.string_to_tree(input).unwrap();
+        let heads = tree
+            .preorder()
+            .filter(|&node| {
+                tree[node]
+                    .features()
+                    .and_then(|features| features.get_val("head"))
+                    == Some("true")
+            })
+            .map(|node| tree[node].label())
+            .collect::<Vec<_>>();
+        assert_eq!(heads, vec!["NP", "NN", "VBZ"]);
+        assert_eq!(
+            formatter.tree_to_string(&tree).unwrap(),
+            "(S:--(NP*:SB(DT:-- the)(NN*:HD dog))(VP:--(VBZ*:-- barks)))"
+        );
+        assert_eq!(
+            PTBFormatter::new(PTBFormat::TueBa)
+                .features(true)
+                .tree_to_string(&tree)
+                .unwrap(),
+            "(S:--(NP:SB##head:true(DT:-- the)(NN:HD##head:true dog))\
+             (VP:--(VBZ:--##head:true barks)))"
+        );
+
+        // without marker, the marker is part of the label
+        let tree = PTBFormat::Simple
+            .string_to_tree("(S (NP* (NN dog)) (* *))")
+            .unwrap();
+        assert_eq!(tree[tree.root()].label(), "S");
+        let formatter = PTBFormatter::new(PTBFormat::Simple).head_marker(Some("*"));
+        let tree = formatter
+            .string_to_tree("(S (NP* (NN dog)) (* *))")
+            .unwrap();
+        let star = tree.terminal_at(1).unwrap();
+        assert_eq!(tree[star].label(), "*");
+        assert!(tree[star].features().is_none());
+    }
+
+    #[test]
+    fn empty_inputs() {
+        let input = "()\n\n  \n( )\n(S (NN a))\n\n(S (NN b))\n";
+        for line_format in [PTBLineFormat::SingleLine, PTBLineFormat::MultiLine] {
+            let trees = PTBReader::new(input.as_bytes(), PTBFormat::Simple, line_format)
+                .map(|tree| PTBFormat::Simple.tree_to_string(&tree.unwrap()).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(trees, vec!["(S (NN a))", "(S (NN b))"]);
+        }
+
+        let input = "(\n)\n\n(S\n  (NN a))\n";
+        let trees = PTBReader::new(
+            input.as_bytes(),
+            PTBFormat::Simple,
+            PTBLineFormat::MultiLine,
+        )
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+        assert_eq!(trees.len(), 1);
+
+        assert!(PTBFormat::Simple.string_to_tree("()").is_err());
+        assert!(PTBFormat::Simple.string_to_tree("").is_err());
+    }
 
     #[test]
     pub fn test_multiline() {
@@ -384,6 +1247,68 @@ mod tests {
         assert_eq!(tree.n_terminals(), 4);
     }
 
+    #[test]
+    fn unbalanced_brackets() {
+        let input = "(S (NP (NN a))\n(S (NP (NN b)))\n(S (NN c)))\n";
+        let mut reader = PTBReader::new(
+            input.as_bytes(),
+            PTBFormat::Simple,
+            PTBLineFormat::SingleLine,
+        );
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LumberjackError>(),
+            Some(&LumberjackError::UnterminatedTree {
+                line: 1,
+                column: 1,
+                depth: 1
+            })
+        );
+        assert!(reader.next().unwrap().is_ok());
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LumberjackError>(),
+            Some(&LumberjackError::UnexpectedCloseBracket {
+                line: 3,
+                column: 11
+            })
+        );
+
+        let input = "(S\n(NN a)))\n";
+        let mut reader = PTBReader::new(
+            input.as_bytes(),
+            PTBFormat::Simple,
+            PTBLineFormat::MultiLine,
+        );
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected closing bracket in line 2, column 8."
+        );
+
+        let input = "(S\n  (NP (NN a)\n  (VP (VB b))\n";
+        let mut reader = PTBReader::new(
+            input.as_bytes(),
+            PTBFormat::Simple,
+            PTBLineFormat::MultiLine,
+        );
+        let err = reader.next().unwrap().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LumberjackError>(),
+            Some(&LumberjackError::UnterminatedTree {
+                line: 2,
+                column: 3,
+                depth: 2
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "Unterminated tree, 2 bracket(s) still open. Innermost unclosed bracket in line 2, \
+             column 3."
+        );
+        assert!(reader.next().is_none());
+    }
+
     #[test]
     pub fn test_with_edge_labels() {
         let input = "(NX:edge (NN Nounphrase) (PX:edge (PP on) (NX:another (DET a ) \
@@ -391,6 +1316,233 @@ mod tests {
         PTBFormat::TueBa.string_to_tree(input).unwrap();
     }
 
+    #[test]
+    fn formatter_flags() {
+        let input = "(NX=sem:--(NN:HD dog)(ADJX:--(ADJA:HD old)))";
+        let mut tree = PTBFormat::TueBa.string_to_tree(input).unwrap();
+        let dog = tree
+            .terminals()
+            .find(|&t| tree[t].terminal().unwrap().form() == "dog")
+            .unwrap();
+        tree[dog].features_mut().insert("case", Some("nom"));
+
+        let tueba = PTBFormatter::new(PTBFormat::TueBa);
+        assert_eq!(input, tueba.tree_to_string(&tree).unwrap());
+        assert_eq!(
+            "(NX=sem(NN dog)(ADJX(ADJA old)))",
+            tueba
+                .clone()
+                .edge_labels(false)
+                .tree_to_string(&tree)
+                .unwrap()
+        );
+        assert_eq!(
+            "(NX=sem:--(NN:HD##case:nom dog)(ADJX:--(ADJA:HD old)))",
+            tueba.clone().features(true).tree_to_string(&tree).unwrap()
+        );
+        assert_eq!(
+            "(NX=sem(NN##case:nom dog)(ADJX(ADJA old)))",
+            tueba
+                .edge_labels(false)
+                .features(true)
+                .tree_to_string(&tree)
+                .unwrap()
+        );
+
+        let ptb = PTBFormatter::new(PTBFormat::PTB);
+        assert_eq!(
+            "(NX-sem (NN dog) (ADJX (ADJA old)))",
+            ptb.tree_to_string(&tree).unwrap()
+        );
+        assert_eq!(
+            "(NX-sem (NN-HD dog) (ADJX (ADJA-HD old)))",
+            ptb.clone().edge_labels(true).tree_to_string(&tree).unwrap()
+        );
+        assert_eq!(
+            "(NX-sem (NN##case:nom dog) (ADJX (ADJA old)))",
+            ptb.features(true).tree_to_string(&tree).unwrap()
+        );
+
+        let simple = PTBFormatter::new(PTBFormat::Simple)
+            .edge_labels(true)
+            .features(true);
+        assert_eq!(
+            "(NX##node_annotation:sem (NN-HD##case:nom dog) (ADJX (ADJA-HD old)))",
+            simple.tree_to_string(&tree).unwrap()
+        );
+    }
+
+    #[test]
+    fn spmrl() {
+        let input = "( (S (NP-SB (ART-NK##lem=der|case=nom|number=sg|gender=masc|## Der) \
+                     (NN-NK##lem=Hund|case=nom|number=sg|gender=masc|## Hund)) \
+                     (VVFIN-HD##lem=bellen|person=3|number=sg|tense=pres|## bellt) \
+                     ($.##lem=.## .)))";
+        let tree = PTBFormat::Spmrl.string_to_tree(input).unwrap();
+        assert_eq!(tree[tree.root()].label(), "VROOT");
+        assert_eq!(tree.n_terminals(), 4);
+
+        let der = tree[tree.terminal_at(0).unwrap()].terminal().unwrap();
+        assert_eq!(der.form(), "Der");
+        assert_eq!(der.label(), "ART");
+        assert_eq!(der.lemma(), Some("der"));
+        assert_eq!(
+            der.features(),
+            Some(&Features::from("case:nom|number:sg|gender:masc"))
+        );
+        let (np, edge) = tree.parent(tree.terminal_at(0).unwrap()).unwrap();
+        assert_eq!(tree[edge].label(), Some("NK"));
+        assert_eq!(tree[np].label(), "NP");
+        let (_, edge) = tree.parent(np).unwrap();
+        assert_eq!(tree[edge].label(), Some("SB"));
+        let period = tree[tree.terminal_at(3).unwrap()].terminal().unwrap();
+        assert_eq!(period.lemma(), Some("."));
+        assert!(period.features().is_none());
+
+        let output = PTBFormat::Spmrl.tree_to_string(&tree).unwrap();
+        assert_eq!(
+            output,
+            "(VROOT (S (NP-SB (ART-NK##case=nom|gender=masc|lem=der|number=sg## Der) \
+             (NN-NK##case=nom|gender=masc|lem=Hund|number=sg## Hund)) \
+             (VVFIN-HD##lem=bellen|number=sg|person=3|tense=pres## bellt) ($.##lem=.## .)))"
+        );
+        assert_eq!(PTBFormat::Spmrl.string_to_tree(&output).unwrap(), tree);
+    }
+
+    #[test]
+    fn bare_terminals() {
+        let input = "(S (NP the dog) (VP (VBZ barks)))";
+        assert!(PTBFormat::Simple.string_to_tree(input).is_err());
+
+        let formatter = PTBFormatter::new(PTBFormat::Simple).bare_terminals(true);
+        let tree = formatter.string_to_tree(input).unwrap();
+        assert_eq!(tree.n_terminals(), 3);
+        assert_eq!(
+            formatter.tree_to_string(&tree).unwrap(),
+            "(S (NP (_ the) (_ dog)) (VP (VBZ barks)))"
+        );
+
+        let formatter = formatter.bare_terminal_pos("UNK");
+        let tree = formatter.string_to_tree(input).unwrap();
+        assert_eq!(
+            formatter.tree_to_string(&tree).unwrap(),
+            "(S (NP (UNK the) (UNK dog)) (VP (VBZ barks)))"
+        );
+        assert_eq!(
+            tree.to_string_with_indices(),
+            "(S (NP (UNK the_0) (UNK dog_1)) (VP (VBZ barks_2)))"
+        );
+    }
+
+    #[test]
+    fn quoted_terminals() {
+        let input = "(S (NE \"New York\") (VBZ is) (\" \") (NN \"big\"))";
+        assert!(PTBFormat::Simple.string_to_tree(input).is_err());
+
+        let formatter = PTBFormatter::new(PTBFormat::Simple).quoted_terminals(true);
+        let tree = formatter.string_to_tree(input).unwrap();
+        assert_eq!(tree.n_terminals(), 4);
+        let forms = (0..tree.n_terminals())
+            .map(|idx| {
+                tree[tree.terminal_at(idx).unwrap()]
+                    .terminal()
+                    .unwrap()
+                    .form()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(forms, vec!["New York", "is", "\"", "big"]);
+        assert_eq!(
+            formatter.tree_to_string(&tree).unwrap(),
+            "(S (NE \"New York\") (VBZ is) (\" \") (NN big))"
+        );
+
+        // quotes are part of the form if quoting is disabled
+        let tree = PTBFormat::Simple
+            .string_to_tree("(S (\" \") (NN \"big\") (\" \"))")
+            .unwrap();
+        let forms = (0..tree.n_terminals())
+            .map(|idx| {
+                tree[tree.terminal_at(idx).unwrap()]
+                    .terminal()
+                    .unwrap()
+                    .form()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(forms, vec!["\"", "\"big\"", "\""]);
+    }
+
+    #[test]
+    fn root_features() {
+        let input = "(S##done:true (NP (# #) (CD 5)) (VP (VBZ barks)))";
+        let formatter = PTBFormatter::new(PTBFormat::PTB).root_features(true);
+        let tree = formatter.string_to_tree(input).unwrap();
+        let root = tree.root();
+        assert_eq!(tree[root].label(), "S");
+        assert_eq!(tree[root].features(), Some(&Features::from("done:true")));
+        let hash = tree.terminal_at(0).unwrap();
+        assert_eq!(tree[hash].label(), "#");
+        assert_eq!(tree[hash].features(), None);
+        assert_eq!(formatter.tree_to_string(&tree).unwrap(), input);
+        assert_eq!(
+            PTBFormat::PTB.tree_to_string(&tree).unwrap(),
+            "(S (NP (# #) (CD 5)) (VP (VBZ barks)))"
+        );
+    }
+
+    #[test]
+    fn read_features() {
+        let input = "(NX=sem:--#gender:masc(NN:HD#case:nom|num:sg dog)(ADJX:--(ADJA:HD old)))";
+        let formatter = PTBFormatter::new(PTBFormat::TueBa).features(true);
+        let tree = formatter.string_to_tree(input).unwrap();
+        let dog = tree.terminal_at(0).unwrap();
+        assert_eq!(tree[dog].label(), "NN");
+        assert_eq!(
+            tree[dog].features(),
+            Some(&Features::from("case:nom|num:sg"))
+        );
+        assert_eq!(tree[tree.parent(dog).unwrap().1].label(), Some("HD"));
+        let root = tree.root();
+        assert_eq!(tree[root].label(), "NX");
+        assert_eq!(
+            tree[root].features().unwrap().get_val("gender"),
+            Some("masc")
+        );
+        assert_eq!(
+            tree[root]
+                .features()
+                .unwrap()
+                .get_val(NODE_ANNOTATION_FEATURE_KEY),
+            Some("sem")
+        );
+        assert_eq!(
+            "(NX=sem:--##gender:masc(NN:HD##case:nom|num:sg dog)(ADJX:--(ADJA:HD old)))",
+            formatter.tree_to_string(&tree).unwrap()
+        );
+        let written = formatter.tree_to_string(&tree).unwrap();
+        assert_eq!(tree, formatter.string_to_tree(&written).unwrap());
+
+        // the PTB tag "#" is not a feature delimiter
+        let formatter = PTBFormatter::new(PTBFormat::PTB).features(true);
+        let tree = formatter
+            .string_to_tree("(NP (# #) (CD 10) (###case:nom #))")
+            .unwrap();
+        let hash = tree.terminal_at(0).unwrap();
+        assert_eq!(tree[hash].label(), "#");
+        assert_eq!(tree[hash].features(), None);
+        let hash = tree.terminal_at(2).unwrap();
+        assert_eq!(tree[hash].label(), "#");
+        assert_eq!(tree[hash].features(), Some(&Features::from("case:nom")));
+        let written = formatter.tree_to_string(&tree).unwrap();
+        assert_eq!(written, "(NP (# #) (CD 10) (###case:nom #))");
+        assert_eq!(tree, formatter.string_to_tree(&written).unwrap());
+
+        // without features, "#" is part of the label
+        let tree = PTBFormat::Simple
+            .string_to_tree("(NX#morph (NN x))")
+            .unwrap();
+        assert_eq!(tree[tree.root()].label(), "NX#morph");
+    }
+
     #[test]
     fn test_single_terminal() {
         let input = "(T t)";
@@ -482,6 +1634,51 @@ mod tests {
         PTBFormat::TueBa.string_to_tree(l).unwrap();
     }
 
+    #[test]
+    pub fn multibyte_label() {
+        let tree = PTBFormat::PTB
+            .string_to_tree("(NÄ-SBJÜ-Ö (ÄDJ-Ü ärger) (N n))")
+            .unwrap();
+        let root = &tree[tree.root()];
+        assert_eq!(root.label(), "NÄ");
+        assert_eq!(
+            root.features()
+                .and_then(|f| f.get_val(NODE_ANNOTATION_FEATURE_KEY)),
+            Some("-SBJÜ-Ö")
+        );
+        let adj = tree.terminals().next().unwrap();
+        assert_eq!(tree[adj].label(), "ÄDJ");
+        assert_eq!(
+            PTBFormat::PTB.tree_to_string(&tree).unwrap(),
+            "(NÄ-SBJÜ-Ö (ÄDJ ärger) (N n))"
+        );
+    }
+
+    #[test]
+    fn writer_flush() {
+        // counts flushes of the written content
+        struct Flushes<'a>(&'a mut Vec<usize>, usize);
+        impl<'a> Write for Flushes<'a> {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.1 += buf.len();
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                self.0.push(self.1);
+                Ok(())
+            }
+        }
+
+        let tree = PTBFormat::Simple.string_to_tree("(S (T t))").unwrap();
+        let mut flushes = Vec::new();
+        let mut writer = PTBWriter::new(Flushes(&mut flushes, 0), PTBFormat::Simple);
+        writer.write_tree(&tree).unwrap();
+        writer.flush().unwrap();
+        writer.write_tree(&tree).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(flushes, vec![10, 20]);
+    }
+
     #[test]
     pub fn write_test() {
         let tree = PTBFormat::TueBa
@@ -503,4 +1700,72 @@ mod tests {
             "(VROOT (NP (N n)) (VP (V v)))"
         )
     }
+
+    #[test]
+    pub fn write_indented() {
+        let input = "(S (NP (DT the) (NN dog)) (VBZ barks) (ADVP (RB very) (ADVP (RB loudly))))";
+        let tree = PTBFormat::Simple.string_to_tree(input).unwrap();
+        let formatter = PTBFormatter::new(PTBFormat::Simple).indent(Some(2));
+        let indented = formatter.tree_to_string(&tree).unwrap();
+        assert_eq!(
+            indented,
+            "(S\n  (NP (DT the) (NN dog))\n  (VBZ barks)\n  (ADVP (RB very)\n    (ADVP (RB loudly))))"
+        );
+        let four = formatter
+            .clone()
+            .indent(Some(4))
+            .tree_to_string(&tree)
+            .unwrap();
+        assert!(four.contains("\n        (ADVP (RB loudly))"));
+
+        let mut reader = PTBReader::with_formatter(
+            BufReader::new(indented.as_bytes()),
+            formatter,
+            PTBLineFormat::MultiLine,
+        );
+        assert_eq!(reader.next().unwrap().unwrap(), tree);
+        assert!(reader.next().is_none());
+
+        let tree = PTBFormat::TueBa
+            .string_to_tree("(VROOT:- (NP=NE:- (N:- n)) (VP:HD (V:HD v)))")
+            .unwrap();
+        assert_eq!(
+            PTBFormatter::new(PTBFormat::TueBa)
+                .indent(Some(1))
+                .tree_to_string(&tree)
+                .unwrap(),
+            "(VROOT:--\n (NP=NE:-(N:- n))\n (VP:HD(V:HD v)))"
+        );
+    }
+
+    #[test]
+    pub fn label_case() {
+        let input = "(s (NP:sb (dt the) (Nn dog)) (vp:HD (VBZ barks)))";
+        let formatter = PTBFormatter::new(PTBFormat::TueBa);
+        let tree = formatter.string_to_tree(input).unwrap();
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&tree).unwrap(),
+            "(s (NP (dt the) (Nn dog)) (vp (VBZ barks)))"
+        );
+
+        let tree = formatter
+            .clone()
+            .label_case(LabelCase::Upper)
+            .string_to_tree(input)
+            .unwrap();
+        assert_eq!(
+            formatter.tree_to_string(&tree).unwrap(),
+            "(S:--(NP:sb(dt:-- the)(Nn:-- dog))(VP:HD(VBZ:-- barks)))"
+        );
+
+        let tree = formatter
+            .clone()
+            .pos_case(LabelCase::Lower)
+            .string_to_tree(input)
+            .unwrap();
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&tree).unwrap(),
+            "(s (NP (dt the) (nn dog)) (vp (vbz barks)))"
+        );
+    }
 }
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use failure::Error;
+use itertools::Itertools;
+
+use crate::util::Climber;
+use crate::Tree;
+
+/// Statistics over a stream of trees.
+///
+/// `CorpusStats` is an accumulator, trees are added through `CorpusStats::observe`. The
+/// `Display` implementation prints a summary table.
+///
+/// The depth of a tree is the number of edges on the longest path from the root to a terminal.
+#[derive(Clone, Debug, Default)]
+pub struct CorpusStats {
+    n_sentences: usize,
+    n_terminals: usize,
+    n_nonprojective: usize,
+    max_depth: usize,
+    total_depth: usize,
+    label_counts: HashMap<String, usize>,
+    pos_counts: HashMap<String, usize>,
+}
+
+impl CorpusStats {
+    /// Construct empty `CorpusStats`.
+    pub fn new() -> Self {
+        CorpusStats::default()
+    }
+
+    /// Add a tree to the statistics.
+    pub fn observe(&mut self, tree: &Tree) {
+        self.n_sentences += 1;
+        self.n_terminals += tree.n_terminals();
+        if !tree.projective() {
+            self.n_nonprojective += 1;
+        }
+
+        let mut depth = 0;
+        for terminal in tree.terminals() {
+            *self
+                .pos_counts
+                .entry(tree[terminal].label().to_owned())
+                .or_insert(0) += 1;
+            let mut climber = Climber::new(terminal);
+            let mut terminal_depth = 0;
+            while climber.next(tree).is_some() {
+                terminal_depth += 1;
+            }
+            depth = depth.max(terminal_depth);
+        }
+        self.max_depth = self.max_depth.max(depth);
+        self.total_depth += depth;
+
+        for (label, count) in tree.label_counts() {
+            *self.label_counts.entry(label.to_owned()).or_insert(0) += count;
+        }
+    }
+
+    /// Add all trees from an iterator to the statistics.
+    ///
+    /// Returns the first `Error` returned by the iterator. Trees read before the error are
+    /// part of the statistics.
+    pub fn observe_all<I>(&mut self, trees: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Result<Tree, Error>>,
+    {
+        for tree in trees {
+            self.observe(&tree?);
+        }
+        Ok(())
+    }
+
+    /// Get the number of observed sentences.
+    pub fn n_sentences(&self) -> usize {
+        self.n_sentences
+    }
+
+    /// Get the number of observed terminals.
+    pub fn n_terminals(&self) -> usize {
+        self.n_terminals
+    }
+
+    /// Get the number of observed nonprojective trees.
+    pub fn n_nonprojective(&self) -> usize {
+        self.n_nonprojective
+    }
+
+    /// Get the maximum tree depth.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Get the mean tree depth.
+    ///
+    /// Returns `0` if no trees were observed.
+    pub fn mean_depth(&self) -> f64 {
+        if self.n_sentences == 0 {
+            0.
+        } else {
+            self.total_depth as f64 / self.n_sentences as f64
+        }
+    }
+
+    /// Get the frequencies of `NonTerminal` labels.
+    pub fn label_counts(&self) -> &HashMap<String, usize> {
+        &self.label_counts
+    }
+
+    /// Get the frequencies of part-of-speech tags.
+    pub fn pos_counts(&self) -> &HashMap<String, usize> {
+        &self.pos_counts
+    }
+}
+
+// write counts sorted by descending frequency, ties are broken alphabetically.
+fn fmt_counts(f: &mut fmt::Formatter, counts: &HashMap<String, usize>) -> fmt::Result {
+    for (label, count) in counts
+        .iter()
+        .sorted_by(|(l1, c1), (l2, c2)| c2.cmp(c1).then_with(|| l1.cmp(l2)))
+    {
+        writeln!(f, "{:<16}{}", label, count)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for CorpusStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:<16}{}", "sentences", self.n_sentences)?;
+        writeln!(f, "{:<16}{}", "terminals", self.n_terminals)?;
+        writeln!(f, "{:<16}{}", "nonprojective", self.n_nonprojective)?;
+        writeln!(f, "{:<16}{}", "max depth", self.max_depth)?;
+        writeln!(f, "{:<16}{:.2}", "mean depth", self.mean_depth())?;
+        writeln!(f)?;
+        writeln!(f, "nonterminal labels")?;
+        fmt_counts(f, &self.label_counts)?;
+        writeln!(f)?;
+        writeln!(f, "part-of-speech tags")?;
+        fmt_counts(f, &self.pos_counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::File;
+    use std::io::BufReader;
+
+    use super::CorpusStats;
+    use crate::io::PTBFormat;
+    use crate::NegraReader;
+
+    #[test]
+    fn stats() {
+        let mut stats = CorpusStats::new();
+        let tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (NN dog)) (VP (VBZ barks)))")
+            .unwrap();
+        stats.observe(&tree);
+        let tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (NN dogs)) (VP (VBP bark) (ADVP (RB loudly))))")
+            .unwrap();
+        stats.observe(&tree);
+        assert_eq!(stats.n_sentences(), 2);
+        assert_eq!(stats.n_terminals(), 6);
+        assert_eq!(stats.n_nonprojective(), 0);
+        assert_eq!(stats.max_depth(), 3);
+        assert!((stats.mean_depth() - 2.5).abs() < 1e-8);
+        assert_eq!(stats.label_counts()["S"], 2);
+        assert_eq!(stats.label_counts()["NP"], 2);
+        assert_eq!(stats.label_counts()["ADVP"], 1);
+        assert_eq!(stats.pos_counts()["NN"], 2);
+        assert_eq!(stats.pos_counts()["RB"], 1);
+        assert_eq!(
+            stats.to_string(),
+            "sentences       2\n\
+             terminals       6\n\
+             nonprojective   0\n\
+             max depth       3\n\
+             mean depth      2.50\n\
+             \n\
+             nonterminal labels\n\
+             NP              2\n\
+             S               2\n\
+             VP              2\n\
+             ADVP            1\n\
+             \n\
+             part-of-speech tags\n\
+             NN              2\n\
+             DT              1\n\
+             RB              1\n\
+             VBP             1\n\
+             VBZ             1\n"
+        );
+    }
+
+    #[test]
+    fn stats_from_reader() {
+        let input = File::open("testdata/10.negra").unwrap();
+        let mut stats = CorpusStats::new();
+        stats
+            .observe_all(NegraReader::new(BufReader::new(input)))
+            .unwrap();
+        assert_eq!(stats.n_sentences(), 10);
+        assert_eq!(stats.label_counts()["VROOT"], 10);
+    }
+}
@@ -5,20 +5,29 @@ extern crate failure;
 extern crate pest_derive;
 
 pub mod io;
-pub use io::{NegraReader, PTBReader, PTBWriter, WriteTree};
+pub use io::{ConlluReader, DotConfig, NegraReader, PTBReader, PTBWriter, WriteTree};
 
 mod tree;
-pub use tree::{Projectivity, Tree};
+pub use tree::{Nested, Projectivity, ShapeMetrics, Tree};
 
 mod edge;
 pub use edge::Edge;
 
+mod error;
+pub use error::{LumberjackError, StructuralError};
+
+mod head;
+pub use head::{HeadDirection, HeadRules};
+
 mod features;
 pub use features::Features;
 
 mod node;
 pub use node::{Node, NonTerminal, Terminal};
 
+mod parseval;
+pub use parseval::{best_overlaps, Parseval, ParsevalScore};
+
 mod span;
 pub use span::{ContinuousSpan, SkipSpan, Span};
 
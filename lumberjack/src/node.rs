@@ -83,6 +83,16 @@ impl Node {
         }
     }
 
+    /// Get a verbose description of the node.
+    ///
+    /// See `NonTerminal::describe` and `Terminal::describe`.
+    pub fn describe(&self) -> String {
+        match self {
+            Node::NonTerminal(nt) => nt.describe(),
+            Node::Terminal(t) => t.describe(),
+        }
+    }
+
     /// Get this `Node`'s `Features`.
     pub fn features(&self) -> Option<&Features> {
         match self {
@@ -119,7 +129,9 @@ impl Node {
         }
     }
 
-    /// Extend the upper bounds of a span.
+    /// Set the span of a node.
+    ///
+    /// Returns `Error` if a `Terminal` would cover anything but a single index.
     pub(crate) fn set_span(&mut self, span: impl Into<Span>) -> Result<Span, Error> {
         let span = span.into();
         match self {
@@ -134,17 +146,6 @@ impl Node {
             Node::NonTerminal(nt) => Ok(mem::replace(&mut nt.span, span)),
         }
     }
-
-    /// Extend the upper bounds of a span.
-    pub(crate) fn extend_span(&mut self) -> Result<(), Error> {
-        match self {
-            Node::Terminal(_) => Err(format_err!("Can't extend terminal's span.")),
-            Node::NonTerminal(nt) => {
-                nt.span.extend();
-                Ok(())
-            }
-        }
-    }
 }
 
 impl fmt::Display for Node {
@@ -231,6 +232,16 @@ impl NonTerminal {
     pub fn set_label(&mut self, label: impl Into<String>) -> String {
         mem::replace(&mut self.label, label.into())
     }
+
+    /// Get a verbose description of the `NonTerminal`.
+    ///
+    /// In contrast to the `Display` representation, the description includes the span and the
+    /// features, e.g. `NP span=0..2,3..4 features=case:nom`. Features are omitted if empty.
+    pub fn describe(&self) -> String {
+        let mut description = format!("{} span={}", self.label, describe_span(&self.span));
+        push_features(&mut description, self.features.as_ref());
+        description
+    }
 }
 
 impl fmt::Display for NonTerminal {
@@ -335,7 +346,7 @@ impl Terminal {
 
     /// Return lemma if present, else `None`.
     pub fn lemma(&self) -> Option<&str> {
-        self.lemma.as_ref().map(String::as_str)
+        self.lemma.as_deref()
     }
 
     /// Replace lemma with `new_lemma`. Return old value.
@@ -345,6 +356,26 @@ impl Terminal {
     {
         mem::replace(&mut self.lemma, new_lemma.map(Into::into))
     }
+
+    /// Get a verbose description of the `Terminal`.
+    ///
+    /// In contrast to the `Display` representation, the description includes the span, the
+    /// lemma and the features, e.g. `NN dog span=1..2 lemma=dog features=num:sg`. Lemma and
+    /// features are omitted if absent.
+    pub fn describe(&self) -> String {
+        let mut description = format!(
+            "{} {} span={}",
+            self.pos,
+            self.form,
+            describe_span(&self.span)
+        );
+        if let Some(lemma) = self.lemma.as_ref() {
+            description.push_str(" lemma=");
+            description.push_str(lemma);
+        }
+        push_features(&mut description, self.features.as_ref());
+        description
+    }
 }
 
 impl fmt::Display for Terminal {
@@ -353,6 +384,22 @@ impl fmt::Display for Terminal {
     }
 }
 
+// Describe span by its runs, e.g. `0..2,3..4`.
+fn describe_span(span: &Span) -> String {
+    span.runs()
+        .into_iter()
+        .map(|(lower, upper)| format!("{}..{}", lower, upper))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn push_features(description: &mut String, features: Option<&Features>) {
+    if let Some(features) = features.filter(|features| !features.inner().is_empty()) {
+        description.push_str(" features=");
+        description.push_str(&features.to_string());
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::{Node, NonTerminal, Span, Terminal};
@@ -363,7 +410,7 @@ mod test {
         assert!(terminal.is_terminal());
         assert!(terminal.terminal().is_some());
         assert!(terminal.nonterminal().is_none());
-        assert!(terminal.extend_span().is_err());
+        assert!(terminal.set_span(Span::new_continuous(0, 2)).is_err());
         assert_eq!(terminal.set_label("other_pos"), "pos");
         assert_eq!(terminal.label(), "other_pos");
         assert_eq!(
@@ -400,7 +447,7 @@ mod test {
         assert_eq!(nonterminal.label(), "other_label");
         assert_eq!(nonterminal.nonterminal_mut().unwrap().set_span(3), 0.into());
         assert_eq!(nonterminal.span(), &3.into());
-        nonterminal.extend_span().unwrap();
+        nonterminal.set_span(Span::new_continuous(3, 5)).unwrap();
         assert_eq!(nonterminal.span(), &Span::new_continuous(3, 5));
         assert_eq!(
             nonterminal
@@ -421,4 +468,30 @@ mod test {
         );
         assert_eq!(format!("{}", nonterminal), "other_label")
     }
+
+    #[test]
+    fn describe() {
+        let mut terminal = Terminal::new("dog", "NN", 1);
+        assert_eq!(terminal.describe(), "NN dog span=1..2");
+        terminal.set_lemma(Some("dog"));
+        terminal.features_mut();
+        assert_eq!(terminal.describe(), "NN dog span=1..2 lemma=dog");
+        terminal.features_mut().insert("num", Some("sg"));
+        terminal.features_mut().insert("case", Some("nom"));
+        assert_eq!(
+            terminal.describe(),
+            "NN dog span=1..2 lemma=dog features=case:nom|num:sg"
+        );
+        assert_eq!(terminal.to_string(), "NN dog");
+
+        let span = Span::from_vec(vec![0, 1, 3, 5, 6]).unwrap();
+        let mut nonterminal = Node::NonTerminal(NonTerminal::new("NP", span));
+        assert_eq!(nonterminal.describe(), "NP span=0..2,3..4,5..7");
+        nonterminal.features_mut().insert("head", None::<&str>);
+        assert_eq!(
+            nonterminal.describe(),
+            "NP span=0..2,3..4,5..7 features=head"
+        );
+        assert_eq!(nonterminal.to_string(), "NP");
+    }
 }
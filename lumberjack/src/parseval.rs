@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+
+use petgraph::prelude::NodeIndex;
+
+use crate::util::LabelSet;
+use crate::{Span, Tree};
+
+/// PARSEVAL scorer.
+///
+/// Compares labeled brackets of `NonTerminal`s between gold and predicted trees. A bracket
+/// consists of the label of a `NonTerminal` and the terminal indices it covers, part-of-speech
+/// tags are not scored.
+///
+/// By default all `NonTerminal`s are scored. `Parseval::evalb` constructs a scorer that
+/// ignores unary roots like EVALB's default parameters.
+#[derive(Clone)]
+pub struct Parseval {
+    root_labels: Option<LabelSet>,
+    drop_self_chains: bool,
+}
+
+impl Default for Parseval {
+    fn default() -> Self {
+        Parseval::new()
+    }
+}
+
+impl Parseval {
+    /// Construct a scorer that scores every `NonTerminal`.
+    pub fn new() -> Self {
+        Parseval {
+            root_labels: None,
+            drop_self_chains: false,
+        }
+    }
+
+    /// Construct a scorer ignoring unary roots labeled `ROOT`, `TOP` or `VROOT`.
+    pub fn evalb() -> Self {
+        let labels = vec!["ROOT", "TOP", "VROOT"]
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect();
+        Parseval::new().skip_unary_root(LabelSet::Positive(labels))
+    }
+
+    /// Skip the root if it has a single child and a label matched by `labels`.
+    pub fn skip_unary_root(mut self, labels: LabelSet) -> Self {
+        self.root_labels = Some(labels);
+        self
+    }
+
+    /// Set whether unary self-chains are dropped.
+    ///
+    /// If `true`, a `NonTerminal` with the same label and span as its parent is not scored,
+    /// e.g. `(NP (NP x))` results in a single `NP` bracket.
+    pub fn drop_self_chains(mut self, drop_self_chains: bool) -> Self {
+        self.drop_self_chains = drop_self_chains;
+        self
+    }
+
+    /// Get the brackets of a tree.
+    ///
+    /// Returns the label and covered terminal indices of each scored `NonTerminal`.
+    pub fn brackets(&self, tree: &Tree) -> Vec<(String, Vec<usize>)> {
+        let root = tree.root();
+        let skip_root = self.root_labels.as_ref().is_some_and(|labels| {
+            tree.children(root).count() == 1 && labels.matches(tree[root].label())
+        });
+
+        tree.nonterminals()
+            .filter(|&nt| !(skip_root && nt == root))
+            .filter(|&nt| {
+                !self.drop_self_chains
+                    || tree.parent(nt).is_none_or(|(parent, _)| {
+                        tree[parent].label() != tree[nt].label()
+                            || tree[parent].span() != tree[nt].span()
+                    })
+            })
+            .map(|nt| {
+                (
+                    tree[nt].label().to_owned(),
+                    tree[nt].span().into_iter().collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Score a predicted tree against a gold tree.
+    pub fn score(&self, gold: &Tree, predicted: &Tree) -> ParsevalScore {
+        let gold = self.brackets(gold);
+        let predicted = self.brackets(predicted);
+        let mut gold_counts = HashMap::new();
+        for bracket in gold.iter() {
+            *gold_counts.entry(bracket).or_insert(0) += 1;
+        }
+        let mut matched = 0;
+        for bracket in predicted.iter() {
+            if let Some(count) = gold_counts.get_mut(bracket) {
+                if *count > 0 {
+                    *count -= 1;
+                    matched += 1;
+                }
+            }
+        }
+
+        ParsevalScore {
+            matched,
+            gold: gold.len(),
+            predicted: predicted.len(),
+        }
+    }
+}
+
+/// Get the best-overlapping test constituent of each gold constituent.
+///
+/// For each `NonTerminal` of `gold` in pre-order, the `NonTerminal` of `test` with the highest
+/// Jaccard index of covered terminal indices is returned together with the score. Discontinuous
+/// spans are compared by the indices they cover, gaps don't count towards the overlap. On ties,
+/// a test constituent with the gold constituent's label is preferred, then the first in
+/// pre-order.
+///
+/// Labels are not required to match. Gold constituents that don't overlap with any test
+/// constituent are paired with `None` and a score of `0`.
+pub fn best_overlaps(gold: &Tree, test: &Tree) -> Vec<(NodeIndex, Option<NodeIndex>, f64)> {
+    let test_nts = test
+        .preorder()
+        .filter(|&node| !test[node].is_terminal())
+        .collect::<Vec<_>>();
+    gold.preorder()
+        .filter(|&node| !gold[node].is_terminal())
+        .map(|gold_nt| {
+            let mut best = None;
+            let mut best_score = 0.;
+            let mut best_label_match = false;
+            for &test_nt in test_nts.iter() {
+                let score = jaccard(gold[gold_nt].span(), test[test_nt].span());
+                let label_match = gold[gold_nt].label() == test[test_nt].label();
+                if score > best_score || (score == best_score && label_match && !best_label_match) {
+                    if score == 0. {
+                        continue;
+                    }
+                    best = Some(test_nt);
+                    best_score = score;
+                    best_label_match = label_match;
+                }
+            }
+            (gold_nt, best, best_score)
+        })
+        .collect()
+}
+
+// Jaccard index of the indices covered by two spans.
+fn jaccard(span1: &Span, span2: &Span) -> f64 {
+    let intersection = span1.into_iter().filter(|&idx| span2.contains(idx)).count();
+    let union = span1.n_indices() + span2.n_indices() - intersection;
+    intersection as f64 / union as f64
+}
+
+/// Bracket counts from PARSEVAL scoring.
+///
+/// Scores of multiple trees are accumulated through `ParsevalScore::add`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ParsevalScore {
+    matched: usize,
+    gold: usize,
+    predicted: usize,
+}
+
+impl ParsevalScore {
+    /// Add the counts of another score.
+    pub fn add(&mut self, other: ParsevalScore) {
+        self.matched += other.matched;
+        self.gold += other.gold;
+        self.predicted += other.predicted;
+    }
+
+    /// Get the number of matched brackets.
+    pub fn matched(&self) -> usize {
+        self.matched
+    }
+
+    /// Get the number of gold brackets.
+    pub fn gold(&self) -> usize {
+        self.gold
+    }
+
+    /// Get the number of predicted brackets.
+    pub fn predicted(&self) -> usize {
+        self.predicted
+    }
+
+    /// Get the labeled precision.
+    ///
+    /// Returns `0` if there are no predicted brackets.
+    pub fn precision(&self) -> f64 {
+        if self.predicted == 0 {
+            0.
+        } else {
+            self.matched as f64 / self.predicted as f64
+        }
+    }
+
+    /// Get the labeled recall.
+    ///
+    /// Returns `0` if there are no gold brackets.
+    pub fn recall(&self) -> f64 {
+        if self.gold == 0 {
+            0.
+        } else {
+            self.matched as f64 / self.gold as f64
+        }
+    }
+
+    /// Get the harmonic mean of precision and recall.
+    pub fn f1(&self) -> f64 {
+        let precision = self.precision();
+        let recall = self.recall();
+        if precision + recall == 0. {
+            0.
+        } else {
+            2. * precision * recall / (precision + recall)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{best_overlaps, Parseval, ParsevalScore};
+    use crate::io::{negra_to_tree, PTBFormat};
+
+    #[test]
+    fn overlaps() {
+        let gold = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (JJ big) (NN dog)) (VP (VBZ barks)))")
+            .unwrap();
+        let test = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (JJ big)) (X (NN dog) (VBZ barks)))")
+            .unwrap();
+        let overlaps = best_overlaps(&gold, &test)
+            .into_iter()
+            .map(|(gold_nt, test_nt, score)| {
+                (
+                    gold[gold_nt].label(),
+                    test_nt.map(|test_nt| test[test_nt].label()),
+                    score,
+                )
+            })
+            .collect::<Vec<_>>();
+        // S matches exactly, NP overlaps more with S (3 of 4 indices) than with NP (2 of 3),
+        // both S and X cover VP's index but X is smaller.
+        assert_eq!(
+            overlaps,
+            vec![
+                ("S", Some("S"), 1.),
+                ("NP", Some("S"), 0.75),
+                ("VP", Some("X"), 0.5),
+            ]
+        );
+
+        let input = std::fs::read_to_string("testdata/long_single.negra").unwrap();
+        let tree = negra_to_tree(&input).unwrap();
+        assert!(!tree.projective());
+        for (gold_nt, test_nt, score) in best_overlaps(&tree, &tree) {
+            assert_eq!(test_nt, Some(gold_nt));
+            assert!((score - 1.).abs() < 1e-8);
+        }
+    }
+
+    #[test]
+    fn score() {
+        let gold = PTBFormat::Simple
+            .string_to_tree("(ROOT (S (NP (DT the) (NN dog)) (VP (VBZ barks))))")
+            .unwrap();
+        let predicted = PTBFormat::Simple
+            .string_to_tree("(ROOT (S (NP (DT the)) (VP (NN dog) (VBZ barks))))")
+            .unwrap();
+        let score = Parseval::new().score(&gold, &predicted);
+        assert_eq!(score.matched(), 2);
+        assert_eq!(score.gold(), 4);
+        assert_eq!(score.predicted(), 4);
+
+        let score = Parseval::evalb().score(&gold, &predicted);
+        assert_eq!(score.matched(), 1);
+        assert_eq!(score.gold(), 3);
+        assert!((score.precision() - 1. / 3.).abs() < 1e-8);
+        assert!((score.f1() - 1. / 3.).abs() < 1e-8);
+
+        let score = Parseval::evalb().score(&gold, &gold);
+        assert!((score.f1() - 1.).abs() < 1e-8);
+    }
+
+    #[test]
+    fn branching_root_is_scored() {
+        let tree = PTBFormat::Simple
+            .string_to_tree("(ROOT (NP (NN dog)) (VP (VBZ barks)))")
+            .unwrap();
+        assert_eq!(Parseval::evalb().brackets(&tree).len(), 3);
+        let tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (NN dog)) (VP (VBZ barks)))")
+            .unwrap();
+        assert_eq!(Parseval::evalb().brackets(&tree).len(), 3);
+    }
+
+    #[test]
+    fn self_chains() {
+        let gold = PTBFormat::Simple
+            .string_to_tree("(TOP (NP (NP (DT the) (NN dog))))")
+            .unwrap();
+        let predicted = PTBFormat::Simple
+            .string_to_tree("(TOP (NP (DT the) (NN dog)))")
+            .unwrap();
+        let scorer = Parseval::evalb();
+        assert_eq!(scorer.brackets(&gold).len(), 2);
+        let score = scorer.score(&gold, &predicted);
+        assert_eq!(score.matched(), 1);
+
+        let scorer = scorer.drop_self_chains(true);
+        assert_eq!(scorer.brackets(&gold), vec![("NP".to_owned(), vec![0, 1])]);
+        let mut score = scorer.score(&gold, &predicted);
+        assert!((score.f1() - 1.).abs() < 1e-8);
+        score.add(ParsevalScore::default());
+        assert_eq!(score.matched(), 1);
+    }
+}
@@ -68,6 +68,57 @@ impl Span {
         }
     }
 
+    /// Get the maximal runs of consecutive covered indices.
+    ///
+    /// Each run is given by its lower and upper bounds, the upper bound is exclusive. A
+    /// continuous span consists of a single run.
+    pub fn runs(&self) -> Vec<(usize, usize)> {
+        match self {
+            Span::Continuous(span) => vec![span.bounds()],
+            Span::Discontinuous(span) => span.runs(),
+        }
+    }
+
+    /// Get the gaps between runs of covered indices.
+    ///
+    /// Each gap is given by its lower and upper bounds, the upper bound is exclusive. A
+    /// continuous span has no gaps.
+    pub fn gaps(&self) -> Vec<(usize, usize)> {
+        match self {
+            Span::Continuous(_) => Vec::new(),
+            Span::Discontinuous(span) => span.gaps(),
+        }
+    }
+
+    /// Get a copy of this span with all indices shifted by `by`.
+    ///
+    /// Panics if an index would become negative.
+    pub fn shift(&self, by: isize) -> Span {
+        self.shift_above(0, by)
+    }
+
+    /// Get a copy of this span with all indices `>= threshold` shifted by `by`.
+    ///
+    /// Indices below `threshold` are kept as they are. Skipped indices are shifted along with the
+    /// covered indices, gaps that are closed by a shift disappear and covered indices that
+    /// coincide after shifting are merged.
+    ///
+    /// Panics if an index would become negative.
+    pub fn shift_above(&self, threshold: usize, by: isize) -> Span {
+        let coverage = self
+            .into_iter()
+            .map(|idx| {
+                if idx < threshold {
+                    return idx;
+                }
+                let shifted = idx as isize + by;
+                assert!(shifted >= 0, "Can't shift index {} by {}.", idx, by);
+                shifted as usize
+            })
+            .collect::<Vec<_>>();
+        Span::from_vec(coverage).expect("Spans are never empty.")
+    }
+
     pub(crate) fn discontinuous(&self) -> Option<&SkipSpan> {
         if let Span::Discontinuous(span) = self {
             Some(span)
@@ -104,14 +155,6 @@ impl Span {
         }
     }
 
-    // Method used internally to increment the upper bounds of a span.
-    pub(crate) fn extend(&mut self) {
-        match self {
-            Span::Continuous(span) => span.upper += 1,
-            Span::Discontinuous(span) => span.upper += 1,
-        }
-    }
-
     // Internally used constructor for convenience.
     pub(crate) fn new_continuous(lower: usize, upper: usize) -> Self {
         Span::Continuous(ContinuousSpan::new(lower, upper))
@@ -207,6 +250,50 @@ impl SkipSpan {
     pub fn n_indices(&self) -> usize {
         self.upper - self.lower - self.skip.len()
     }
+
+    /// Number of gaps.
+    ///
+    /// A gap is a maximal run of consecutive skipped indices.
+    pub fn n_gaps(&self) -> usize {
+        self.skip
+            .iter()
+            .filter(|&&idx| idx == self.lower || !self.skip.contains(&(idx - 1)))
+            .count()
+    }
+
+    /// Get the maximal runs of consecutive covered indices.
+    ///
+    /// Each run is given by its lower and upper bounds, the upper bound is exclusive.
+    pub fn runs(&self) -> Vec<(usize, usize)> {
+        let mut runs = Vec::new();
+        let mut start = self.lower;
+        for (lower, upper) in self.gaps() {
+            if start < lower {
+                runs.push((start, lower));
+            }
+            start = upper;
+        }
+        if start < self.upper {
+            runs.push((start, self.upper));
+        }
+        runs
+    }
+
+    /// Get the gaps, the maximal runs of consecutive skipped indices.
+    ///
+    /// Each gap is given by its lower and upper bounds, the upper bound is exclusive.
+    pub fn gaps(&self) -> Vec<(usize, usize)> {
+        let mut skips = self.skip.iter().cloned().collect::<Vec<_>>();
+        skips.sort();
+        let mut gaps: Vec<(usize, usize)> = Vec::new();
+        for idx in skips {
+            match gaps.last_mut() {
+                Some(gap) if gap.1 == idx => gap.1 += 1,
+                _ => gaps.push((idx, idx + 1)),
+            }
+        }
+        gaps
+    }
 }
 
 impl Ord for Span {
@@ -273,7 +360,7 @@ impl<'a> Iterator for SpanIter<'a> {
     type Item = usize;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(next) = self.range.next() {
+        for next in self.range.by_ref() {
             if let Some(skip) = self.skip {
                 if skip.contains(&next) {
                     continue;
@@ -291,6 +378,65 @@ mod tests {
 
     use crate::{ContinuousSpan, SkipSpan, Span};
 
+    #[test]
+    fn shift() {
+        let span = Span::new_continuous(2, 4);
+        assert_eq!(span.shift(3), Span::new_continuous(5, 7));
+        assert_eq!(span.shift(-2), Span::new_continuous(0, 2));
+
+        let skip = vec![3, 5].into_iter().collect::<HashSet<_>>();
+        let span = Span::Discontinuous(SkipSpan::new(1, 7, skip));
+        let skip = vec![5, 7].into_iter().collect::<HashSet<_>>();
+        assert_eq!(
+            span.shift(2),
+            Span::Discontinuous(SkipSpan::new(3, 9, skip))
+        );
+    }
+
+    #[test]
+    fn shift_above() {
+        // 1 2 _ 4 _ 6
+        let skip = vec![3, 5].into_iter().collect::<HashSet<_>>();
+        let span = Span::Discontinuous(SkipSpan::new(1, 7, skip));
+
+        // threshold inside the first gap: only 4 and 6 move
+        let skip = vec![3, 4, 5, 7].into_iter().collect::<HashSet<_>>();
+        assert_eq!(
+            span.shift_above(3, 2),
+            Span::Discontinuous(SkipSpan::new(1, 9, skip))
+        );
+
+        // closing the first gap
+        let skip = vec![4].into_iter().collect::<HashSet<_>>();
+        assert_eq!(
+            span.shift_above(4, -1),
+            Span::Discontinuous(SkipSpan::new(1, 6, skip))
+        );
+
+        // 4 merges with 2
+        let skip = vec![3].into_iter().collect::<HashSet<_>>();
+        assert_eq!(
+            span.shift_above(4, -2),
+            Span::Discontinuous(SkipSpan::new(1, 5, skip))
+        );
+
+        // indices above the upper bound don't exist
+        assert_eq!(span.shift_above(7, -5), span);
+
+        // closing the only gap yields a continuous span
+        assert_eq!(
+            Span::Discontinuous(SkipSpan::new(0, 3, vec![1].into_iter().collect()))
+                .shift_above(2, -1),
+            Span::new_continuous(0, 2)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn shift_negative() {
+        Span::new_continuous(2, 4).shift(-3);
+    }
+
     #[test]
     #[should_panic]
     fn invalid_cont_span_2_1() {
@@ -338,6 +484,34 @@ mod tests {
         assert!(!span.contains(10));
     }
 
+    #[test]
+    fn n_gaps() {
+        let skip = vec![3, 5].into_iter().collect::<HashSet<usize>>();
+        assert_eq!(SkipSpan::new(0, 10, skip).n_gaps(), 2);
+        let skip = vec![3, 4, 5, 8].into_iter().collect::<HashSet<usize>>();
+        assert_eq!(SkipSpan::new(0, 10, skip).n_gaps(), 2);
+        let skip = vec![1].into_iter().collect::<HashSet<usize>>();
+        assert_eq!(SkipSpan::new(0, 3, skip).n_gaps(), 1);
+    }
+
+    #[test]
+    fn runs_and_gaps() {
+        let skip = vec![3, 4, 5, 8].into_iter().collect::<HashSet<usize>>();
+        let span = Span::Discontinuous(SkipSpan::new(0, 10, skip));
+        assert_eq!(span.runs(), vec![(0, 3), (6, 8), (9, 10)]);
+        assert_eq!(span.gaps(), vec![(3, 6), (8, 9)]);
+        assert_eq!(span.gaps().len(), span.discontinuous().unwrap().n_gaps());
+
+        let skip = vec![1].into_iter().collect::<HashSet<usize>>();
+        let span = SkipSpan::new(0, 3, skip);
+        assert_eq!(span.runs(), vec![(0, 1), (2, 3)]);
+        assert_eq!(span.gaps(), vec![(1, 2)]);
+
+        let span = Span::new_continuous(2, 5);
+        assert_eq!(span.runs(), vec![(2, 5)]);
+        assert!(span.gaps().is_empty());
+    }
+
     #[test]
     fn contains_contspan() {
         let span = ContinuousSpan::new(0, 10);
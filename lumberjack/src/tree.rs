@@ -1,30 +1,66 @@
-use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
 use std::ops::{Index, IndexMut};
 
 use failure::Error;
 use petgraph::prelude::{Bfs, DfsPostOrder, Direction, EdgeIndex, EdgeRef, NodeIndex, StableGraph};
 
-use crate::util::LabelSet;
-use crate::{Edge, Node, Span};
+use crate::io::{DotConfig, PTBFormat, PTBFormatter};
+use crate::util::{Climber, LabelSet};
+use crate::{
+    Edge, HeadRules, LumberjackError, Node, NonTerminal, SkipSpan, Span, StructuralError, Terminal,
+};
 
 /// Enum describing whether a tree is projective.
+///
+/// Readers set `Projective` or `Nonprojective` if the projectivity is known after reading a tree
+/// and `Unknown` otherwise. An `Unknown` projectivity is resolved on demand:
+///
+/// * `Tree::projective` and `Tree::validate` compute the projectivity from the tree's spans
+///   without storing the result.
+/// * `Projectivize::projectivize` always leaves the tree `Projective`.
+/// * Transformations that can change the projectivity of a tree, e.g.
+///   `TreeOps::filter_nonterminals`, reset a `Nonprojective` tree to `Unknown`.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Projectivity {
     Projective,
     Nonprojective,
+    Unknown,
 }
 
+const SENTENCE_ID_KEY: &str = "sent_id";
+const REMOVED_TERMINALS_KEY: &str = "removed_terminals";
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
 /// `Tree`
 ///
 /// `Tree`s represent constituency trees and consist of `Node`s. The nodes are either
 /// `Terminal`s or `NonTerminal`s. Relations between nodes are expressed as `Edge`s.
-#[derive(Debug, Clone)]
+///
+/// Trees can carry metadata such as sentence identifiers from the source corpus. Metadata is
+/// not considered when comparing trees.
+///
+/// Trees contain at least one terminal. Readers skip empty inputs such as blank lines or empty
+/// brackets and transformations that would remove all terminals return `Error`.
+///
+/// `Tree` is `Send` and `Sync`. Since the readers in `lumberjack::io` iterate over
+/// `Result<Tree, Error>`, trees can be processed in parallel, e.g. with `rayon`'s `par_bridge`.
+///
+/// The `Debug` representation shows the bracketing with the description of each node, see
+/// `Node::describe`, and the edge labels. `{:?}` writes it on a single line, `{:#?}` puts each
+/// node on its own line, indented by depth. Nodes that are not attached to the root are listed
+/// at the end.
+#[derive(Clone)]
 pub struct Tree {
     graph: StableGraph<Node, Edge>,
     n_terminals: usize,
     root: NodeIndex,
     projectivity: Projectivity,
+    metadata: HashMap<String, String>,
+    // terminal node indices in surface order
+    terminal_indices: Vec<NodeIndex>,
 }
 
 impl Tree {
@@ -34,12 +70,132 @@ impl Tree {
         root: NodeIndex,
         projectivity: Projectivity,
     ) -> Self {
-        Tree {
+        let mut tree = Tree {
             graph,
             n_terminals,
             root,
             projectivity,
+            metadata: HashMap::new(),
+            terminal_indices: Vec::new(),
+        };
+        tree.index_terminals();
+        tree
+    }
+
+    /// Construct a tree from a `Nested` description.
+    ///
+    /// Terminals are numbered from left to right, spans of `NonTerminal`s are computed from
+    /// their children, the resulting tree is projective. Nodes are added to the graph in
+    /// pre-order, e.g. the root is always at index `0`.
+    ///
+    /// ```
+    /// use lumberjack::{Nested, Tree};
+    ///
+    /// let tree = Tree::from_nested((
+    ///     "S",
+    ///     vec![
+    ///         ("NP", vec![Nested::leaf("the", "DT"), Nested::leaf("dog", "NN")]).into(),
+    ///         Nested::leaf("barks", "VBZ"),
+    ///     ],
+    /// ))
+    /// .unwrap();
+    /// assert_eq!(tree.n_terminals(), 3);
+    /// ```
+    ///
+    /// Returns `Error` if the root is a leaf or if an inner node has no children.
+    pub fn from_nested(nested: impl Into<Nested>) -> Result<Tree, Error> {
+        let nested = nested.into();
+        if let Nested::Leaf { .. } = nested {
+            return Err(format_err!("Root of a tree can't be a leaf."));
+        }
+        let mut graph = StableGraph::new();
+        let mut n_terminals = 0;
+        let root = Self::add_nested(&mut graph, nested, &mut n_terminals)?;
+        let mut tree = Tree::new(graph, n_terminals, root, Projectivity::Projective);
+        tree.reset_nt_spans();
+        Ok(tree)
+    }
+
+    fn add_nested(
+        graph: &mut StableGraph<Node, Edge>,
+        nested: Nested,
+        n_terminals: &mut usize,
+    ) -> Result<NodeIndex, Error> {
+        match nested {
+            Nested::Leaf { form, pos } => {
+                let node = graph.add_node(Node::Terminal(Terminal::new(form, pos, *n_terminals)));
+                *n_terminals += 1;
+                Ok(node)
+            }
+            Nested::Inner { label, children } => {
+                if children.is_empty() {
+                    return Err(format_err!("Inner node without children: {}", label));
+                }
+                // placeholder span, reset once all terminals were added
+                let node = graph.add_node(Node::NonTerminal(NonTerminal::new(label, 0)));
+                for child in children {
+                    let child = Self::add_nested(graph, child, n_terminals)?;
+                    graph.add_edge(node, child, Edge::default());
+                }
+                Ok(node)
+            }
+        }
+    }
+
+    /// Join trees under a new root.
+    ///
+    /// Constructs a tree with a root labeled `root_label` that dominates the roots of `trees`.
+    /// Terminal indices are offset such that the terminals of each tree follow the terminals of
+    /// the preceding trees, e.g. joining two trees with two terminals each results in a tree
+    /// whose first tree covers `0..2` and whose second tree covers `2..4`.
+    ///
+    /// The result is nonprojective if any of the trees is nonprojective. Metadata of the trees
+    /// is not carried over.
+    ///
+    /// Returns `Error` if `trees` is empty.
+    pub fn join(trees: Vec<Tree>, root_label: &str) -> Result<Tree, Error> {
+        if trees.is_empty() {
+            return Err(format_err!("Can't join empty sequence of trees."));
+        }
+
+        let mut graph = StableGraph::new();
+        let root = graph.add_node(Node::NonTerminal(NonTerminal::new(root_label, 0)));
+        let mut projectivity = Projectivity::Projective;
+        let mut offset = 0;
+        for tree in trees {
+            match tree.projectivity {
+                Projectivity::Nonprojective => projectivity = Projectivity::Nonprojective,
+                Projectivity::Unknown if projectivity == Projectivity::Projective => {
+                    projectivity = Projectivity::Unknown
+                }
+                _ => (),
+            }
+
+            let mut mapping = HashMap::new();
+            for node in tree.graph.node_indices() {
+                let mut weight = tree[node].clone();
+                if weight.is_terminal() {
+                    let span = weight.span().shift(offset as isize);
+                    weight.set_span(span)?;
+                }
+                mapping.insert(node, graph.add_node(weight));
+            }
+            for node in tree.graph.node_indices() {
+                for edge in tree.graph.edges_directed(node, Direction::Outgoing) {
+                    graph.add_edge(
+                        mapping[&node],
+                        mapping[&edge.target()],
+                        edge.weight().clone(),
+                    );
+                }
+            }
+            graph.add_edge(root, mapping[&tree.root], Edge::default());
+            offset += tree.n_terminals;
         }
+
+        let mut joined = Tree::new(graph, offset, root, projectivity);
+        joined.reset_nt_spans();
+        Ok(joined)
     }
 
     /// Get the number of terminals in the tree.
@@ -47,6 +203,96 @@ impl Tree {
         self.n_terminals
     }
 
+    /// Get the number of nonterminals in the tree.
+    pub fn n_nonterminals(&self) -> usize {
+        self.nonterminals().count()
+    }
+
+    /// Get the number of nodes in the tree.
+    pub fn n_nodes(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    /// Get the frequencies of `NonTerminal` labels in the tree.
+    pub fn label_counts(&self) -> HashMap<&str, usize> {
+        let mut counts = HashMap::new();
+        for nonterminal in self.nonterminals() {
+            *counts.entry(self[nonterminal].label()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Get the frequencies of the values of the feature `key`.
+    ///
+    /// Counts the values of `key` over all nodes, `Terminal`s and `NonTerminal`s. Valueless
+    /// features are counted under `None`, nodes without the feature are not counted. Values
+    /// added through `Features::push` are counted individually.
+    pub fn feature_value_counts(&self, key: &str) -> HashMap<Option<String>, usize> {
+        let mut counts = HashMap::new();
+        for node in self.graph.node_indices() {
+            let values = self[node]
+                .features()
+                .map(|features| features.get_all(key))
+                .unwrap_or_default();
+            for value in values {
+                *counts.entry(value.map(ToOwned::to_owned)).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    /// Get the features of all `Terminal`s as a table.
+    ///
+    /// Returns the sorted union of feature keys as header and one row per terminal in surface
+    /// order. A cell is `None` if the terminal lacks the key or the feature has no value, keys
+    /// with multiple values contribute their first value.
+    pub fn feature_table(&self) -> (Vec<String>, Vec<Vec<Option<String>>>) {
+        self.feature_table_for(self.terminal_indices.iter().cloned())
+    }
+
+    /// Get the features of all nodes as a table.
+    ///
+    /// Like `Tree::feature_table`, but the terminal rows are followed by one row per
+    /// `NonTerminal` in the order returned by `Tree::nonterminals`.
+    pub fn feature_table_with_nonterminals(&self) -> (Vec<String>, Vec<Vec<Option<String>>>) {
+        self.feature_table_for(
+            self.terminal_indices
+                .iter()
+                .cloned()
+                .chain(self.nonterminals()),
+        )
+    }
+
+    fn feature_table_for(
+        &self,
+        nodes: impl Iterator<Item = NodeIndex>,
+    ) -> (Vec<String>, Vec<Vec<Option<String>>>) {
+        let nodes = nodes.collect::<Vec<_>>();
+        let header = nodes
+            .iter()
+            .filter_map(|&node| self[node].features())
+            .flat_map(|features| features.inner().keys())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .cloned()
+            .collect::<Vec<_>>();
+        let rows = nodes
+            .into_iter()
+            .map(|node| {
+                header
+                    .iter()
+                    .map(|key| {
+                        self[node]
+                            .features()
+                            .and_then(|features| features.get_val(key))
+                            .map(ToOwned::to_owned)
+                    })
+                    .collect()
+            })
+            .collect();
+        (header, rows)
+    }
+
     /// Get the index of the root of the tree.
     pub fn root(&self) -> NodeIndex {
         self.root
@@ -59,6 +305,97 @@ impl Tree {
             .filter(move |idx| self.graph[*idx].is_terminal())
     }
 
+    /// Get the tree's metadata.
+    pub fn metadata(&self) -> &HashMap<String, String> {
+        &self.metadata
+    }
+
+    /// Get the tree's metadata mutably.
+    pub fn metadata_mut(&mut self) -> &mut HashMap<String, String> {
+        &mut self.metadata
+    }
+
+    /// Get the sentence identifier.
+    ///
+    /// The identifier is stored in the metadata under the key `"sent_id"`. It is set by
+    /// `NegraReader` from `#BOS <id>` and by `ConlluReader` from `# sent_id = <id>` comments,
+    /// trees from other readers have no identifier.
+    ///
+    /// Identifiers are not written: the bracketed formats have no place for them and the CONLLX
+    /// reader and writer of the `conllx` crate don't support comment lines.
+    pub fn sentence_id(&self) -> Option<&str> {
+        self.metadata.get(SENTENCE_ID_KEY).map(String::as_str)
+    }
+
+    /// Set the sentence identifier.
+    ///
+    /// Returns the replaced value.
+    pub fn set_sentence_id<S>(&mut self, sentence_id: Option<S>) -> Option<String>
+    where
+        S: Into<String>,
+    {
+        match sentence_id {
+            Some(sentence_id) => self
+                .metadata
+                .insert(SENTENCE_ID_KEY.to_owned(), sentence_id.into()),
+            None => self.metadata.remove(SENTENCE_ID_KEY),
+        }
+    }
+
+    /// Get the terminal at surface position `index`.
+    ///
+    /// Returns `None` if `index` is out of bounds.
+    pub fn terminal_at(&self, index: usize) -> Option<NodeIndex> {
+        self.terminal_indices.get(index).cloned()
+    }
+
+    /// Consume the tree and get an iterator over its `Terminal`s in surface order.
+    pub fn into_terminals(self) -> impl Iterator<Item = Terminal> {
+        let Tree {
+            mut graph,
+            terminal_indices,
+            ..
+        } = self;
+        terminal_indices
+            .into_iter()
+            .filter_map(move |terminal| match graph.remove_node(terminal) {
+                Some(Node::Terminal(terminal)) => Some(terminal),
+                _ => None,
+            })
+    }
+
+    /// Get the `Terminal` at surface position `index`.
+    ///
+    /// Returns `None` if `index` is out of bounds.
+    pub fn nth_terminal(&self, index: usize) -> Option<&Terminal> {
+        let terminal = self.terminal_at(index)?;
+        self[terminal].terminal()
+    }
+
+    /// Get the `Terminal` at surface position `index` mutably.
+    ///
+    /// Returns `None` if `index` is out of bounds.
+    pub fn nth_terminal_mut(&mut self, index: usize) -> Option<&mut Terminal> {
+        let terminal = self.terminal_at(index)?;
+        self[terminal].terminal_mut()
+    }
+
+    /// Apply `f` to all `Terminal`s in surface order.
+    ///
+    /// This allows mutating terminals without collecting their indices first, e.g. to lowercase
+    /// all forms.
+    pub fn for_each_terminal_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Terminal),
+    {
+        for idx in 0..self.terminal_indices.len() {
+            let terminal = self.terminal_indices[idx];
+            if let Some(terminal) = self[terminal].terminal_mut() {
+                f(terminal);
+            }
+        }
+    }
+
     /// Get an iterator over the terminal indices in the constituency tree.
     pub fn nonterminals<'a>(&'a self) -> impl Iterator<Item = NodeIndex> + 'a {
         self.graph
@@ -84,8 +421,76 @@ impl Tree {
             .map(|edge_ref| edge_ref.target())
     }
 
+    /// Get the span of `node`.
+    ///
+    /// Panics if `node` is not part of the tree.
+    pub fn span(&self, node: NodeIndex) -> &Span {
+        self[node].span()
+    }
+
+    /// Get the range of terminals covered by `node`.
+    ///
+    /// Returns the indices of the first and last terminal covered by `node`, both bounds are
+    /// inclusive. Gaps of discontinuous spans are ignored, e.g. a node covering `0`, `2` and `3`
+    /// has the range `(0, 3)`.
+    ///
+    /// Panics if `node` is not part of the tree.
+    pub fn yield_range(&self, node: NodeIndex) -> (usize, usize) {
+        let (lower, upper) = self.span(node).bounds();
+        (lower, upper - 1)
+    }
+
+    /// Get the leftmost terminal dominated by `node`.
+    ///
+    /// The leftmost terminal is the terminal at the lower bound of `node`'s span, a `Terminal`
+    /// is its own leftmost terminal.
+    ///
+    /// Panics if `node` is not part of the tree.
+    pub fn leftmost_terminal(&self, mut node: NodeIndex) -> NodeIndex {
+        while let Some(child) = self
+            .children(node)
+            .min_by_key(|&child| self[child].span().lower())
+        {
+            node = child;
+        }
+        node
+    }
+
+    /// Get the rightmost terminal dominated by `node`.
+    ///
+    /// The rightmost terminal is the last terminal covered by `node`'s span, gaps of
+    /// discontinuous spans are ignored. A `Terminal` is its own rightmost terminal.
+    ///
+    /// Panics if `node` is not part of the tree.
+    pub fn rightmost_terminal(&self, mut node: NodeIndex) -> NodeIndex {
+        while let Some(child) = self
+            .children(node)
+            .max_by_key(|&child| self[child].span().upper())
+        {
+            node = child;
+        }
+        node
+    }
+
+    /// Get an iterator over all nodes in pre-order.
+    ///
+    /// Nodes are visited depth-first starting at the root, the children of a node are visited in
+    /// surface order, i.e. ordered by the lowest index covered by their spans. This corresponds
+    /// to the order of nodes in the bracketed representation. For discontinuous trees, a node is
+    /// visited at the position of its lowest covered index.
+    pub fn preorder<'a>(&'a self) -> impl Iterator<Item = NodeIndex> + 'a {
+        let mut stack = vec![self.root];
+        std::iter::from_fn(move || {
+            let node = stack.pop()?;
+            let mut children = self.children(node).collect::<Vec<_>>();
+            self.sort_indices(&mut children);
+            stack.extend(children.into_iter().rev());
+            Some(node)
+        })
+    }
+
     /// Get an iterator over `node`'s siblings.
-    pub fn siblings<'a>(&'a self, node: NodeIndex) -> Box<Iterator<Item = NodeIndex> + 'a> {
+    pub fn siblings<'a>(&'a self, node: NodeIndex) -> Box<dyn Iterator<Item = NodeIndex> + 'a> {
         if let Some((parent, _)) = self.parent(node) {
             Box::new(self.children(parent).filter(move |&target| target != node))
         } else {
@@ -93,11 +498,31 @@ impl Tree {
         }
     }
 
+    /// Find the `NonTerminal`s whose labels match `set`.
+    ///
+    /// The nodes are returned in pre-order, see `Tree::preorder`.
+    pub fn find(&self, set: &LabelSet) -> Vec<NodeIndex> {
+        self.preorder()
+            .filter(|&node| !self[node].is_terminal() && set.matches(self[node].label()))
+            .collect()
+    }
+
+    /// Find the `Terminal`s whose part-of-speech tags match `set`.
+    ///
+    /// The terminals are returned in surface order.
+    pub fn find_terminals(&self, set: &LabelSet) -> Vec<NodeIndex> {
+        self.terminal_indices
+            .iter()
+            .cloned()
+            .filter(|&terminal| set.matches(self[terminal].label()))
+            .collect()
+    }
+
     /// Get an iterator over `node`'s descendents.
     pub fn descendent_terminals<'a>(
         &'a self,
         node: NodeIndex,
-    ) -> Box<Iterator<Item = NodeIndex> + 'a> {
+    ) -> Box<dyn Iterator<Item = NodeIndex> + 'a> {
         if let Node::NonTerminal(nt) = &self[node] {
             let terminals = self.terminals().collect::<Vec<_>>();
             Box::new(nt.span().into_iter().map(move |idx| terminals[idx]))
@@ -116,150 +541,1146 @@ impl Tree {
         }
     }
 
-    /// Set root of the tree.
+    /// Get the smallest constituent covering a range of terminals.
     ///
-    /// Panics if the new root index is invalid.
-    pub(crate) fn set_root(&mut self, new_root: NodeIndex) {
-        assert!(
-            self.graph.contains_node(new_root),
-            "New root node has to be present in the  graph."
-        );
-        self.root = new_root;
-    }
-
-    /// Get an immutable reference to the underlying `StableGraph`.
-    pub fn graph(&self) -> &StableGraph<Node, Edge> {
-        &self.graph
+    /// Returns the `NodeIndex` of the lowest `NonTerminal` whose span contains every index in
+    /// the half-open range `[lower, upper)`, i.e. like the bounds of a `Span`, `upper` is
+    /// exclusive. Discontinuous constituents only match if none of their skipped indices fall
+    /// inside the range.
+    ///
+    /// Since the root covers the complete sentence, `None` is only returned if the range is empty
+    /// or exceeds the number of terminals.
+    pub fn smallest_constituent(&self, lower: usize, upper: usize) -> Option<NodeIndex> {
+        if lower >= upper || upper > self.n_terminals {
+            return None;
+        }
+        // every node covering the range dominates the terminal at lower, the first ancestor
+        // covering the full range is thus the smallest constituent.
+        let terminal = self
+            .terminals()
+            .find(|&terminal| self[terminal].span().lower() == lower)?;
+        self.smallest_constituent_above(terminal, lower, upper)
     }
 
-    /// Get a mutable reference to the underlying `StableGraph`.
-    pub(crate) fn graph_mut(&mut self) -> &mut StableGraph<Node, Edge> {
-        &mut self.graph
-    }
+    /// Get the smallest constituents covering multiple ranges of terminals.
+    ///
+    /// Batch version of `Tree::smallest_constituent`, the returned `Vec` holds the result for
+    /// each `(lower, upper)` range in `spans`. Terminals are looked up through an index built
+    /// once for all ranges and repeated ranges are only resolved once.
+    ///
+    /// Unlike `Tree::smallest_constituent`, a constituent is only returned if its yield is
+    /// exactly the range. `None` is returned for ranges crossing brackets and for ranges that
+    /// are empty or exceed the number of terminals.
+    pub fn constituents_for_spans(&self, spans: &[(usize, usize)]) -> Vec<Option<NodeIndex>> {
+        let mut terminals = vec![None; self.n_terminals];
+        for terminal in self.terminals() {
+            if let Some(slot) = terminals.get_mut(self[terminal].span().lower()) {
+                *slot = Some(terminal);
+            }
+        }
 
-    /// Returns whether the tree is projective.
-    pub fn projective(&self) -> bool {
-        self.projectivity == Projectivity::Projective
+        let mut cache = HashMap::new();
+        spans
+            .iter()
+            .map(|&(lower, upper)| {
+                if lower >= upper || upper > self.n_terminals {
+                    return None;
+                }
+                *cache.entry((lower, upper)).or_insert_with(|| {
+                    terminals[lower]
+                        .and_then(|terminal| {
+                            self.smallest_constituent_above(terminal, lower, upper)
+                        })
+                        // the smallest covering constituent of a crossing range covers more.
+                        .filter(|&node| {
+                            let span = self[node].span();
+                            span.bounds() == (lower, upper) && span.discontinuous().is_none()
+                        })
+                })
+            })
+            .collect()
     }
 
-    /// Set the tree's projectivity.
-    pub(crate) fn set_projectivity(&mut self, projectivity: Projectivity) {
-        self.projectivity = projectivity
+    // Get the first ancestor of terminal that covers lower..upper.
+    fn smallest_constituent_above(
+        &self,
+        terminal: NodeIndex,
+        lower: usize,
+        upper: usize,
+    ) -> Option<NodeIndex> {
+        let mut climber = Climber::new(terminal);
+        while let Some(node) = climber.next(self) {
+            let span = self[node].span();
+            if span.lower() > lower || span.upper() < upper {
+                continue;
+            }
+            let skips_range = span
+                .discontinuous()
+                .is_some_and(|span| span.skips().iter().any(|&idx| idx >= lower && idx < upper));
+            if !skips_range {
+                return Some(node);
+            }
+        }
+        None
     }
 
-    /// Project indices of `NonTerminal`s onto `Terminal`s.
+    /// Get the nearest ancestor of `node` whose label matches `set`.
     ///
-    /// This method projects the `NodeIndex` of `NonTerminal`s with a label in
-    /// `tag_set` onto the terminal nodes. The hierarchically closest node's `NodeIndex`
-    /// will be assigned to each terminal.
+    /// `node` itself is not considered, the search starts at its parent and ends at the root,
+    /// e.g. to find the enclosing clause of a terminal.
     ///
-    /// Returns a `Vec<NodeIndex>`.
-    pub fn project_nt_indices(&self, tag_set: &LabelSet) -> Vec<NodeIndex> {
-        let mut bfs = Bfs::new(&self.graph, self.root);
-        let mut indices = vec![self.root; self.n_terminals];
-        while let Some(node) = bfs.next(&self.graph) {
-            if let Some(inner) = self.graph[node].nonterminal() {
-                if tag_set.matches(inner.label()) {
-                    for id in self.graph[node].span() {
-                        indices[id] = node;
-                    }
-                }
+    /// Returns `None` if no ancestor matches.
+    pub fn nearest_ancestor(&self, node: NodeIndex, set: &LabelSet) -> Option<NodeIndex> {
+        let mut climber = Climber::new(node);
+        while let Some(ancestor) = climber.next(self) {
+            if set.matches(self[ancestor].label()) {
+                return Some(ancestor);
             }
         }
-        indices
+        None
     }
 
-    /// Project labels of `NonTerminal`s onto `Terminal`s.
+    /// Get the lowest common ancestor of two nodes.
     ///
-    /// This method projects the label for each node in `tag_set` onto the terminal nodes.
-    /// The hierarchically closest node's label will be assigned to each terminal.
-    pub fn project_tag_set(&self, tag_set: &LabelSet) -> Vec<&str> {
-        let indices = self.project_nt_indices(tag_set);
-        indices
-            .into_iter()
-            .map(|nt_idx| {
-                // safe to unwrap, we get the IDs from project_ids()
-                // which checks if nt_idx is Node::Inner
-                self.graph[nt_idx].nonterminal().unwrap().label()
-            })
-            .collect::<Vec<_>>()
-    }
+    /// A node is considered to be its own ancestor, the lowest common ancestor of a node and one
+    /// of its descendents is thus the node itself.
+    ///
+    /// Returns `None` if either index is invalid or if the nodes are not part of the same tree.
+    pub fn lca(&self, a: NodeIndex, b: NodeIndex) -> Option<NodeIndex> {
+        if !self.graph.contains_node(a) || !self.graph.contains_node(b) {
+            return None;
+        }
+        let mut ancestors = vec![a];
+        let mut climber = Climber::new(a);
+        while let Some(node) = climber.next(self) {
+            ancestors.push(node);
+        }
 
-    /// Project unique IDs for nodes with label in `tag_set` onto terminals.
-    pub fn project_ids(&self, tag_set: &LabelSet) -> Vec<usize> {
-        let indices = self.project_nt_indices(tag_set);
-        let mut ids = vec![0; self.n_terminals];
-        let mut mapping = HashMap::new();
-        for (term_id, idx) in indices.into_iter().enumerate() {
-            let n = mapping.len();
-            let id = *mapping.entry(idx).or_insert(n);
-            ids[term_id] = id;
+        if ancestors.contains(&b) {
+            return Some(b);
         }
-        ids
+        let mut climber = Climber::new(b);
+        while let Some(node) = climber.next(self) {
+            if ancestors.contains(&node) {
+                return Some(node);
+            }
+        }
+        None
     }
 
-    // helper method to sort a vec of node indices
-    // order is determined by:
-    // 1. lower bound of span (starting point of span)
-    // 2. upper bound of span (end point of span)
-    // 3. number of covered indices by span
-    // 4. Inner nodes before terminal nodes
-    // 5. alphabetical order
-    fn sort_indices(&self, indices: &mut Vec<NodeIndex>) {
-        indices.sort_by(
-            |node1, node2| match self[*node1].span().cmp(&self[*node2].span()) {
-                Ordering::Equal => match (&self[*node1], &self[*node2]) {
-                    (Node::NonTerminal(_), Node::Terminal(_)) => Ordering::Greater,
-                    (Node::Terminal(_), Node::NonTerminal(_)) => Ordering::Less,
-                    (Node::NonTerminal(nt1), Node::NonTerminal(nt2)) => {
-                        let order = self
-                            .children(*node1)
-                            .count()
-                            .cmp(&self.children(*node2).count());
-                        if order != Ordering::Equal {
-                            order
-                        } else {
-                            nt1.label().cmp(nt2.label())
-                        }
-                    }
-                    (Node::Terminal(t1), Node::Terminal(t2)) => t1.form().cmp(t2.form()),
-                },
-                ordering => ordering,
-            },
-        );
+    /// Get the path between two nodes.
+    ///
+    /// The path starts at `a`, goes up to the lowest common ancestor of `a` and `b` and then down
+    /// to `b`. Both `a` and `b` are part of the returned path.
+    ///
+    /// Returns `None` if either index is invalid or if the nodes are not part of the same tree.
+    pub fn path_between(&self, a: NodeIndex, b: NodeIndex) -> Option<Vec<NodeIndex>> {
+        let lca = self.lca(a, b)?;
+        let mut path = vec![a];
+        let mut climber = Climber::new(a);
+        if a != lca {
+            while let Some(node) = climber.next(self) {
+                path.push(node);
+                if node == lca {
+                    break;
+                }
+            }
+        }
+
+        let mut down = Vec::new();
+        let mut cur = b;
+        let mut climber = Climber::new(b);
+        while cur != lca {
+            down.push(cur);
+            cur = climber.next(self)?;
+        }
+        path.extend(down.into_iter().rev());
+        Some(path)
     }
 
-    /// Reset terminal spans to increasing by 1 at each step.
+    /// Get the labels on the path between two nodes.
     ///
-    /// If a span was skipped because of e.g. removed terminals, restore the correct order.
-    /// If two terminals with same span are present, alphabetical order of forms is used as the
-    /// tie-breaker.
-    #[allow(dead_code)]
-    pub(crate) fn compact_terminal_spans(&mut self) -> Result<(), Error> {
-        let mut terminals = self.terminals().collect::<Vec<_>>();
-        self.sort_indices(&mut terminals);
-        for (idx, term) in terminals.into_iter().enumerate() {
-            self[term].set_span(idx)?;
+    /// The labels of the nodes returned by `Tree::path_between` are joined by `"↑"` while going
+    /// up towards the lowest common ancestor and by `"↓"` while going down, e.g.
+    /// `NN↑NP↑S↓VP↓VB`.
+    ///
+    /// Returns `None` if there is no path between the nodes.
+    pub fn dependency_path_labels(&self, a: NodeIndex, b: NodeIndex) -> Option<String> {
+        let path = self.path_between(a, b)?;
+        let lca = self.lca(a, b)?;
+        let mut labels = String::new();
+        let mut up = true;
+        for (idx, &node) in path.iter().enumerate() {
+            if idx != 0 {
+                labels.push(if up { '↑' } else { '↓' });
+            }
+            labels.push_str(self[node].label());
+            if node == lca {
+                up = false;
+            }
         }
-        Ok(())
+        Some(labels)
     }
 
-    /// Resets nonterminal spans based on terminal spans.
-    #[allow(dead_code)]
-    pub(crate) fn reset_nt_spans(&mut self) {
+    /// Convert the tree to dependencies.
+    ///
+    /// Returns a `(dependent, head, relation)` triple for each terminal in surface order. The
+    /// index of a terminal is the lower bound of its span plus one, the head of the root
+    /// terminal is `0`. Indices are thus not contiguous if the terminal indices have gaps. The
+    /// head of a terminal is the lexical head of the parent of its maximal projection, heads are
+    /// selected through `rules`. The relation is the edge label above the maximal projection,
+    /// `"_"` if the edge is unlabeled and `"ROOT"` for the root terminal.
+    ///
+    /// Returns `Error` if the tree contains `NonTerminal`s without children.
+    pub fn to_dependencies(&self, rules: &HeadRules) -> Result<Vec<(usize, usize, String)>, Error> {
+        let mut heads = HashMap::new();
         let mut dfs = DfsPostOrder::new(&self.graph, self.root);
         while let Some(node) = dfs.next(&self.graph) {
-            if !self[node].is_terminal() {
-                let coverage = self
-                    .children(node)
-                    .flat_map(|child| self[child].span().into_iter())
-                    .collect::<Vec<_>>();
-                let span = Span::from_vec(coverage).unwrap();
-                self[node].nonterminal_mut().unwrap().set_span(span);
+            let head = if self[node].is_terminal() {
+                node
+            } else {
+                let child = rules
+                    .head_child(self, node)
+                    .ok_or_else(|| format_err!("NonTerminal without children: {}", self[node]))?;
+                heads[&child]
+            };
+            heads.insert(node, head);
+        }
+
+        let mut dependencies = Vec::with_capacity(self.n_terminals);
+        for &terminal in self.terminal_indices.iter() {
+            let dependent = self[terminal].span().lower() + 1;
+            // climb to the maximal projection of the terminal
+            let mut projection = terminal;
+            let mut attachment = None;
+            while let Some((parent, edge)) = self.parent(projection) {
+                if heads[&parent] != terminal {
+                    attachment = Some((parent, edge));
+                    break;
+                }
+                projection = parent;
             }
+            let dependency = match attachment {
+                Some((parent, edge)) => {
+                    let head = self[heads[&parent]].span().lower() + 1;
+                    let relation = self[edge].label().unwrap_or("_").to_owned();
+                    (dependent, head, relation)
+                }
+                None => (dependent, 0, "ROOT".to_owned()),
+            };
+            dependencies.push(dependency);
         }
+        Ok(dependencies)
     }
-}
+
+    /// Normalize the lemmas of all `Terminal`s.
+    ///
+    /// Each lemma is replaced by the result of calling `f` on it, `Terminal`s without lemma are
+    /// left untouched.
+    pub fn normalize_lemmas<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str) -> String,
+    {
+        self.for_each_terminal_mut(|terminal| {
+            if let Some(lemma) = terminal.lemma().map(&mut f) {
+                terminal.set_lemma(Some(lemma));
+            }
+        });
+    }
+
+    /// Replace the forms of all `Terminal`s.
+    ///
+    /// Forms are assigned to the terminals in surface order, all other fields of the terminals
+    /// are preserved.
+    ///
+    /// Returns `Error` and leaves the tree unchanged if the number of forms doesn't match the
+    /// number of terminals.
+    pub fn set_forms<S>(&mut self, forms: impl IntoIterator<Item = S>) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let forms = forms.into_iter().collect::<Vec<_>>();
+        if forms.len() != self.terminal_indices.len() {
+            return Err(LumberjackError::FormCountMismatch {
+                n_forms: forms.len(),
+                n_terminals: self.terminal_indices.len(),
+            }
+            .into());
+        }
+        for (idx, form) in forms.into_iter().enumerate() {
+            let terminal = self.terminal_indices[idx];
+            self[terminal].terminal_mut().unwrap().set_form(form);
+        }
+        Ok(())
+    }
+
+    /// Replace the part-of-speech tags of all `Terminal`s.
+    ///
+    /// Tags are assigned to the terminals in surface order. If `keep_original_as` is given, the
+    /// replaced tag of each terminal whose tag changes is stored as a feature under that key,
+    /// e.g. to compare the tags of an external tagger with the gold standard. Terminals keeping
+    /// their tag are not annotated.
+    ///
+    /// Returns `Error` and leaves the tree unchanged if the number of tags doesn't match the
+    /// number of terminals.
+    pub fn retag<S>(
+        &mut self,
+        tags: impl IntoIterator<Item = S>,
+        keep_original_as: Option<&str>,
+    ) -> Result<(), Error>
+    where
+        S: Into<String>,
+    {
+        let tags = tags.into_iter().map(Into::into).collect::<Vec<String>>();
+        if tags.len() != self.terminal_indices.len() {
+            return Err(LumberjackError::PosCountMismatch {
+                n_tags: tags.len(),
+                n_terminals: self.terminal_indices.len(),
+            }
+            .into());
+        }
+        let mut tags = tags.into_iter();
+        self.for_each_terminal_mut(|terminal| {
+            let tag = tags.next().unwrap();
+            if terminal.label() == tag {
+                return;
+            }
+            let original = terminal.set_label(tag);
+            if let Some(key) = keep_original_as {
+                terminal.features_mut().insert(key, Some(original));
+            }
+        });
+        Ok(())
+    }
+
+    /// Retain only features with keys matched by `keys`.
+    ///
+    /// Applies to the features of all nodes, `Terminal`s and `NonTerminal`s. Note that
+    /// annotations read from bracketed node labels, e.g. `NX=PER`, are stored as features and
+    /// are removed unless their key is matched.
+    pub fn retain_features(&mut self, keys: &LabelSet) {
+        let nodes = self.graph.node_indices().collect::<Vec<_>>();
+        for node in nodes {
+            if self[node].features().is_some() {
+                self[node].features_mut().retain(|key, _| keys.matches(key));
+            }
+        }
+    }
+
+    /// Relabel nodes.
+    ///
+    /// `f` is applied to every node, `Terminal`s and `NonTerminal`s alike. If `f` returns
+    /// `Some(label)`, the node is relabeled, the label of a `Terminal` is its part-of-speech tag.
+    /// Returning `None` leaves the label unchanged.
+    pub fn map_labels<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Node) -> Option<String>,
+    {
+        let nodes = self.graph.node_indices().collect::<Vec<_>>();
+        for node in nodes {
+            if let Some(label) = f(&self[node]) {
+                self[node].set_label(label);
+            }
+        }
+    }
+
+    /// Set the lemma of all `Terminal`s to their form.
+    ///
+    /// Existing lemmas are overwritten.
+    pub fn copy_form_to_lemma(&mut self) {
+        self.for_each_terminal_mut(|terminal| {
+            let form = terminal.form().to_owned();
+            terminal.set_lemma(Some(form));
+        });
+    }
+
+    /// Swap the form and the part-of-speech tag of all `Terminal`s.
+    ///
+    /// Meant for inputs with swapped form and tag columns. Lemmas and features are left
+    /// untouched, swapping twice restores the original tree.
+    pub fn swap_form_label(&mut self) {
+        self.for_each_terminal_mut(|terminal| {
+            let form = terminal.set_form("");
+            let label = terminal.set_label(form);
+            terminal.set_form(label);
+        });
+    }
+
+    /// Merge adjacent sibling terminals.
+    ///
+    /// Two terminals are merged if they are adjacent in surface order, attached to the same
+    /// parent and `predicate` returns `true` for the pair. The merged terminal keeps the part of
+    /// speech of the left terminal, its form is the concatenation of both forms delimited by
+    /// `separator`. Lemmas are concatenated the same way if both terminals have a lemma,
+    /// otherwise the lemma is removed. Features of the right terminal are added to the features
+    /// of the left terminal, values of the left terminal take precedence.
+    ///
+    /// Runs of terminals are merged from left to right. Afterwards, terminal spans are
+    /// reindexed and nonterminal spans are recomputed.
+    pub fn merge_terminals<F>(&mut self, separator: &str, predicate: F) -> Result<(), Error>
+    where
+        F: Fn(&Terminal, &Terminal) -> bool,
+    {
+        let terminals = self.terminal_indices.clone();
+        let mut terminals = terminals.into_iter();
+        let mut left = match terminals.next() {
+            Some(left) => left,
+            None => return Ok(()),
+        };
+        let mut merged = false;
+        for right in terminals {
+            let siblings = match (self.parent(left), self.parent(right)) {
+                (Some((parent_1, _)), Some((parent_2, _))) => parent_1 == parent_2,
+                _ => false,
+            };
+            if !siblings
+                || !predicate(
+                    self[left].terminal().unwrap(),
+                    self[right].terminal().unwrap(),
+                )
+            {
+                left = right;
+                continue;
+            }
+
+            let right = match self.graph.remove_node(right) {
+                Some(Node::Terminal(right)) => right,
+                _ => unreachable!(),
+            };
+            let left = self[left].terminal_mut().unwrap();
+            let form = format!("{}{}{}", left.form(), separator, right.form());
+            left.set_form(form);
+            let lemma = match (left.lemma(), right.lemma()) {
+                (Some(l1), Some(l2)) => Some(format!("{}{}{}", l1, separator, l2)),
+                _ => None,
+            };
+            left.set_lemma(lemma);
+            if let Some(features) = right.features() {
+                let left_features = left.features_mut();
+                for key in features.inner().keys() {
+                    if !left_features.inner().contains_key(key) {
+                        for val in features.get_all(key) {
+                            left_features.push(key.as_str(), val);
+                        }
+                    }
+                }
+            }
+            merged = true;
+        }
+
+        if merged {
+            self.reindex_terminals()?;
+        }
+        Ok(())
+    }
+
+    /// Insert a new `NonTerminal` above a group of siblings.
+    ///
+    /// The new node is labeled `label` and attached to the common parent of `children`, the
+    /// children are moved below the new node, keeping their `Edge`s. The span of the new node is
+    /// computed from the spans of its children. If the span is discontinuous, the tree becomes
+    /// `Projectivity::Nonprojective`.
+    ///
+    /// Returns the `NodeIndex` of the new node, or `Error` if `children` is empty, contains the
+    /// root or if the children are not attached to the same parent.
+    pub fn add_nonterminal_over(
+        &mut self,
+        label: &str,
+        children: &[NodeIndex],
+    ) -> Result<NodeIndex, Error> {
+        let mut parent = None;
+        for &child in children {
+            let (child_parent, _) = self
+                .parent(child)
+                .ok_or_else(|| format_err!("Node is not attached to a parent: {:?}", child))?;
+            if parent.is_some_and(|parent| parent != child_parent) {
+                return Err(format_err!("Children don't share a parent."));
+            }
+            parent = Some(child_parent);
+        }
+        let parent =
+            parent.ok_or_else(|| format_err!("Can't add nonterminal without children."))?;
+
+        let coverage = children
+            .iter()
+            .flat_map(|&child| self[child].span().into_iter())
+            .collect::<Vec<_>>();
+        let span = Span::from_vec(coverage)?;
+        if span.discontinuous().is_some() {
+            self.projectivity = Projectivity::Nonprojective;
+        }
+
+        let node = self
+            .graph
+            .add_node(Node::NonTerminal(NonTerminal::new(label, span)));
+        self.graph.add_edge(parent, node, Edge::default());
+        for &child in children {
+            let (_, id) = self.parent(child).unwrap();
+            let weight = self.graph.remove_edge(id).unwrap();
+            self.graph.add_edge(node, child, weight);
+        }
+        Ok(node)
+    }
+
+    /// Relabel the root of the tree.
+    ///
+    /// Only the label of the root changes, features and structure are left untouched.
+    ///
+    /// Returns `Error` if the root is a `Terminal`.
+    pub fn relabel_root(&mut self, label: &str) -> Result<(), Error> {
+        let root = self.root;
+        if self[root].is_terminal() {
+            return Err(format_err!("Root is a terminal: {}", self[root]));
+        }
+        self[root].set_label(label);
+        Ok(())
+    }
+
+    /// Set root of the tree.
+    ///
+    /// Returns `Error` if the new root index is invalid or if the new root does not dominate all
+    /// terminals in the graph. The latter would leave orphaned subtrees.
+    pub(crate) fn set_root(&mut self, new_root: NodeIndex) -> Result<(), Error> {
+        if !self.graph.contains_node(new_root) {
+            return Err(format_err!("New root node has to be present in the graph."));
+        }
+        let mut dominated = 0;
+        let mut bfs = Bfs::new(&self.graph, new_root);
+        while let Some(node) = bfs.next(&self.graph) {
+            if self.graph[node].is_terminal() {
+                dominated += 1;
+            }
+        }
+        if dominated != self.terminals().count() {
+            return Err(format_err!(
+                "New root does not dominate all terminals: {}",
+                self.graph[new_root]
+            ));
+        }
+        self.root = new_root;
+        Ok(())
+    }
+
+    /// Get an immutable reference to the underlying `StableGraph`.
+    pub fn graph(&self) -> &StableGraph<Node, Edge> {
+        &self.graph
+    }
+
+    /// Get a mutable reference to the underlying `StableGraph`.
+    pub(crate) fn graph_mut(&mut self) -> &mut StableGraph<Node, Edge> {
+        &mut self.graph
+    }
+
+    /// Returns whether the tree is projective.
+    ///
+    /// If the stored projectivity is `Projectivity::Unknown`, it is computed from the spans of
+    /// the tree.
+    pub fn projective(&self) -> bool {
+        match self.projectivity {
+            Projectivity::Projective => true,
+            Projectivity::Nonprojective => false,
+            Projectivity::Unknown => self.compute_projectivity() == Projectivity::Projective,
+        }
+    }
+
+    /// Get the stored projectivity.
+    ///
+    /// Unlike `Tree::projective`, this method does not resolve `Projectivity::Unknown`.
+    pub fn projectivity(&self) -> Projectivity {
+        self.projectivity
+    }
+
+    /// Compute the projectivity of the tree.
+    ///
+    /// The tree is projective if no `NonTerminal` covers a discontinuous span. The stored
+    /// projectivity is ignored, this method never returns `Projectivity::Unknown`.
+    pub fn compute_projectivity(&self) -> Projectivity {
+        let nonprojective = self
+            .nonterminals()
+            .any(|nt| self[nt].span().discontinuous().is_some());
+        if nonprojective {
+            Projectivity::Nonprojective
+        } else {
+            Projectivity::Projective
+        }
+    }
+
+    /// Check whether the tree has crossing brackets.
+    ///
+    /// A tree has crossing brackets if it can't be written as a bracketed string, i.e. if some
+    /// `NonTerminal` covers a discontinuous span. Unlike `Tree::projective`, this is always
+    /// derived from the spans of the tree, the stored projectivity is ignored.
+    pub fn has_crossing_brackets(&self) -> bool {
+        self.compute_projectivity() == Projectivity::Nonprojective
+    }
+
+    /// Check whether all `Terminal`s carry a part-of-speech tag.
+    ///
+    /// Empty tags and the placeholder `"_"`, e.g. assigned to bare terminals by
+    /// `PTBFormatter::bare_terminals`, don't count as tags.
+    pub fn is_fully_tagged(&self) -> bool {
+        self.terminals()
+            .all(|terminal| !is_placeholder_pos(self[terminal].label()))
+    }
+
+    /// Get the indices of all nonprojective `NonTerminal`s.
+    ///
+    /// A `NonTerminal` is nonprojective if it covers a discontinuous span. The stored
+    /// projectivity is ignored.
+    pub fn nonprojective_nodes(&self) -> Vec<NodeIndex> {
+        self.nonterminals()
+            .filter(|&nt| self[nt].span().discontinuous().is_some())
+            .collect()
+    }
+
+    /// Check whether `node` is part of a unary chain.
+    ///
+    /// A node is part of a unary chain if its parent or one of its children covers the same span,
+    /// see `Tree::unary_chains`.
+    pub fn is_unary_chain_node(&self, node: NodeIndex) -> bool {
+        let span = self[node].span();
+        self.parent(node)
+            .is_some_and(|(parent, _)| self[parent].span() == span)
+            || self.children(node).any(|child| self[child].span() == span)
+    }
+
+    /// Get all unary chains.
+    ///
+    /// A unary chain is a maximal sequence of nodes covering the same span in which each node
+    /// dominates the next one, e.g. `(ROOT (UNARY (T t)))` consists of the chain
+    /// `[ROOT, UNARY, T]`. Chains are listed top-down and in pre-order of their topmost nodes,
+    /// each chain contains at least two nodes. These are the chains collapsed by
+    /// `TreeOps::collapse_unary_chains`, the tree is not modified.
+    pub fn unary_chains(&self) -> Vec<Vec<NodeIndex>> {
+        let mut chains = Vec::new();
+        for node in self.preorder() {
+            let span = self[node].span();
+            if self
+                .parent(node)
+                .is_some_and(|(parent, _)| self[parent].span() == span)
+            {
+                continue;
+            }
+            let mut chain = vec![node];
+            let mut cur = node;
+            while let Some(child) = self.children(cur).find(|&child| self[child].span() == span) {
+                chain.push(child);
+                cur = child;
+            }
+            if chain.len() > 1 {
+                chains.push(chain);
+            }
+        }
+        chains
+    }
+
+    /// Get the gap degree of the tree.
+    ///
+    /// The gap degree is the maximum number of gaps in the span of a single `NonTerminal`.
+    /// Projective trees have gap degree `0`. The stored projectivity is ignored.
+    pub fn gap_degree(&self) -> usize {
+        self.nonterminals()
+            .filter_map(|nt| self[nt].span().discontinuous().map(SkipSpan::n_gaps))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Get a projective core of the tree.
+    ///
+    /// Instead of re-attaching nonprojective material like `Projectivize::projectivize`, the
+    /// terminals filling the gaps of discontinuous `NonTerminal`s are removed along with
+    /// `NonTerminal`s that no longer dominate any terminal. Removing the fillers of one node can
+    /// close the gaps of others, so fillers are removed greedily, starting with the
+    /// discontinuous node that covers the most terminals. The removed set is not guaranteed to
+    /// be the smallest one that makes the tree projective. Terminal spans are reindexed
+    /// afterwards, the returned tree is `Projectivity::Projective`.
+    ///
+    /// The original indices of the removed terminals are recorded as a comma-separated list
+    /// under the metadata key `removed_terminals`. Projective trees are returned unchanged.
+    pub fn projective_core(&self) -> Tree {
+        let coverages = self
+            .nonterminals()
+            .filter_map(|nt| self[nt].span().discontinuous())
+            .map(|span| {
+                (span.lower()..span.upper())
+                    .filter(|&idx| span.contains(idx))
+                    .collect::<BTreeSet<_>>()
+            })
+            .collect::<Vec<_>>();
+        let mut core = self.clone();
+        if coverages.is_empty() {
+            return core;
+        }
+        let terminals = self
+            .terminal_indices
+            .iter()
+            .map(|&terminal| self[terminal].span().lower())
+            .collect::<BTreeSet<_>>();
+        let removed = gap_fillers(&coverages, &terminals);
+
+        for &terminal in self.terminal_indices.iter() {
+            if removed.contains(&self[terminal].span().lower()) {
+                core.graph.remove_node(terminal);
+            }
+        }
+        // remove nonterminals that are left without children, bottom-up.
+        let mut dfs = DfsPostOrder::new(&self.graph, self.root);
+        while let Some(node) = dfs.next(&self.graph) {
+            if core.graph.contains_node(node)
+                && !core[node].is_terminal()
+                && core.children(node).next().is_none()
+            {
+                core.graph.remove_node(node);
+            }
+        }
+
+        // reindexing only fails for nonterminals, all nodes passed are terminals.
+        core.reindex_terminals().unwrap();
+        core.projectivity = Projectivity::Projective;
+        core.metadata.insert(
+            REMOVED_TERMINALS_KEY.to_owned(),
+            removed
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        core
+    }
+
+    /// Get a bracketed representation with terminal indices.
+    ///
+    /// Each form is suffixed with `"_"` and the lower bound of the terminal's span, e.g.
+    /// `(S (NP (DT the_0) (NN dog_1)))`. This representation is meant for debugging, unlike
+    /// `PTBFormatter::tree_to_string` it does not fail for nonprojective trees.
+    pub fn to_string_with_indices(&self) -> String {
+        PTBFormatter::new(PTBFormat::Simple)
+            .terminal_indices(true)
+            .format_sub_tree(self, self.root, None)
+    }
+
+    /// Get the PTB representation including removed empty elements.
+    ///
+    /// The empty elements removed by `TreeOps::remove_traces` are reinserted at their original
+    /// positions, writing a tree read in `PTBFormat::PTB` after removing its traces reproduces
+    /// the original tree.
+    ///
+    /// Returns `Error` if the tree is nonprojective.
+    pub fn to_penn_treebank_with_empties(&self) -> Result<String, Error> {
+        PTBFormatter::new(PTBFormat::PTB)
+            .empty_elements(true)
+            .tree_to_string(self)
+    }
+
+    /// Get a single-line PTB representation with function labels.
+    ///
+    /// The label of each `NonTerminal` is suffixed with `separator` and its edge label, e.g.
+    /// `(S (NP-SB (NN dog)) (VP-HD (VBZ barks)))`. The tree can be read back with
+    /// `PTBFormatter::function_labels` to restore the edge labels.
+    ///
+    /// Returns `Error` if the tree is nonprojective.
+    pub fn to_ptb_with_function_labels(&self, separator: &str) -> Result<String, Error> {
+        PTBFormatter::new(PTBFormat::Simple)
+            .function_labels(Some(separator))
+            .tree_to_string(self)
+    }
+
+    /// Get a hash of the tree's structure.
+    ///
+    /// The hash covers the tree's labels, forms and bracketing, i.e. it is computed from
+    /// `Tree::to_string_with_indices`. Features, lemmas, edge labels and metadata are ignored.
+    /// The hash is computed with 64-bit FNV-1a and is stable across runs and platforms.
+    pub fn structural_hash(&self) -> u64 {
+        self.to_string_with_indices()
+            .bytes()
+            .fold(FNV_OFFSET_BASIS, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// Get the Graphviz DOT representation of the tree.
+    ///
+    /// Nodes are colored according to `config`, see `DotConfig::tree_to_dot` for details.
+    pub fn to_dot(&self, config: &DotConfig) -> String {
+        config.tree_to_dot(self)
+    }
+
+    /// Set the tree's projectivity.
+    pub(crate) fn set_projectivity(&mut self, projectivity: Projectivity) {
+        self.projectivity = projectivity
+    }
+
+    /// Get shape metrics of the tree.
+    ///
+    /// The metrics are computed in a single traversal from the root.
+    pub fn shape_metrics(&self) -> ShapeMetrics {
+        let mut metrics = ShapeMetrics::default();
+        let mut n_children = 0;
+        let mut stack = vec![(self.root, 0)];
+        while let Some((node, depth)) = stack.pop() {
+            if self[node].is_terminal() {
+                metrics.max_depth = metrics.max_depth.max(depth);
+                continue;
+            }
+            metrics.n_nonterminals += 1;
+            let len = stack.len();
+            stack.extend(self.children(node).map(|child| (child, depth + 1)));
+            let node_children = stack.len() - len;
+            n_children += node_children;
+            if node_children == 1 {
+                metrics.n_unary += 1;
+            }
+        }
+        if metrics.n_nonterminals != 0 {
+            metrics.branching_factor = n_children as f64 / metrics.n_nonterminals as f64;
+        }
+        metrics
+    }
+
+    /// Returns whether the terminals cover exactly the indices `0..n`.
+    ///
+    /// Returns `false` if there are gaps, duplicate or out of range terminal indices.
+    /// Projectivity doesn't affect the result.
+    pub fn covers_contiguous(&self, n: usize) -> bool {
+        let mut covered = vec![false; n];
+        for terminal in self.terminals() {
+            match covered.get_mut(self[terminal].span().lower()) {
+                Some(covered) if !*covered => *covered = true,
+                _ => return false,
+            }
+        }
+        covered.into_iter().all(|covered| covered)
+    }
+
+    /// Returns whether the span of `node` matches its yield.
+    ///
+    /// The yield of a node are the terminals it dominates. The span matches the yield if it
+    /// covers exactly the indices of the dominated terminals, for discontinuous spans this
+    /// includes the skipped indices.
+    ///
+    /// Panics if `node` is not part of the tree.
+    pub fn span_matches_yield(&self, node: NodeIndex) -> bool {
+        let mut yield_indices = Vec::new();
+        let mut bfs = Bfs::new(&self.graph, node);
+        while let Some(descendant) = bfs.next(&self.graph) {
+            if let Node::Terminal(terminal) = &self[descendant] {
+                yield_indices.push(terminal.span().lower());
+            }
+        }
+        yield_indices.sort();
+        self.span(node).into_iter().eq(yield_indices)
+    }
+
+    /// Find structural errors in the tree's graph.
+    ///
+    /// Detects self-loops, nodes with multiple parents, a root with a parent and nodes that are
+    /// not reachable from the root. Errors are reported per node in the order of the node
+    /// indices, unreachable nodes are reported together.
+    pub fn find_structural_errors(&self) -> Vec<StructuralError> {
+        let mut errors = Vec::new();
+        let mut nodes = self.graph.node_indices().collect::<Vec<_>>();
+        nodes.sort();
+        for &node in nodes.iter() {
+            let mut parents = self
+                .graph
+                .neighbors_directed(node, Direction::Incoming)
+                .collect::<Vec<_>>();
+            parents.sort();
+            if parents.contains(&node) {
+                errors.push(StructuralError::SelfLoop { node });
+                parents.retain(|&parent| parent != node);
+            }
+            if node == self.root {
+                if let Some(&parent) = parents.first() {
+                    errors.push(StructuralError::RootWithParent { parent });
+                }
+            } else if parents.len() > 1 {
+                errors.push(StructuralError::MultipleParents { node, parents });
+            }
+        }
+
+        let mut reachable = HashSet::new();
+        if self.graph.contains_node(self.root) {
+            let mut bfs = Bfs::new(&self.graph, self.root);
+            while let Some(node) = bfs.next(&self.graph) {
+                reachable.insert(node);
+            }
+        }
+        let disconnected = nodes
+            .into_iter()
+            .filter(|node| !reachable.contains(node))
+            .collect::<Vec<_>>();
+        if !disconnected.is_empty() {
+            errors.push(StructuralError::Disconnected {
+                nodes: disconnected,
+            });
+        }
+        errors
+    }
+
+    /// Validate the tree.
+    ///
+    /// Returns `Error` if the tree has structural errors, see `Tree::find_structural_errors`,
+    /// if the terminals don't cover exactly `0..n_terminals`, if the span of a `NonTerminal`
+    /// doesn't match its yield or if the stored projectivity contradicts the spans of the tree.
+    /// An `Unknown` projectivity is computed and always considered valid.
+    ///
+    /// For structural errors, the first `StructuralError` is returned.
+    pub fn validate(&self) -> Result<(), Error> {
+        if let Some(error) = self.find_structural_errors().into_iter().next() {
+            return Err(error.into());
+        }
+        if !self.covers_contiguous(self.n_terminals) {
+            return Err(format_err!(
+                "Terminals don't cover the indices 0..{}.",
+                self.n_terminals
+            ));
+        }
+        if let Some(node) = self
+            .nonterminals()
+            .find(|&node| !self.span_matches_yield(node))
+        {
+            return Err(format_err!("Span doesn't match yield: {}", self[node]));
+        }
+        let computed = self.compute_projectivity();
+        match self.projectivity {
+            Projectivity::Unknown => Ok(()),
+            projectivity if projectivity == computed => Ok(()),
+            projectivity => Err(format_err!(
+                "Tree is marked {:?} but is {:?}.",
+                projectivity,
+                computed
+            )),
+        }
+    }
+
+    /// Bring the tree into canonical form after building or editing it.
+    ///
+    /// * Terminals are renumbered to cover `0..n_terminals` in their current order.
+    /// * The spans of all `NonTerminal`s are recomputed from their yields.
+    /// * The projectivity is recomputed from the spans.
+    /// * Empty `Features` are removed. Features are always stored sorted by key, so their order
+    ///   is canonical already.
+    ///
+    /// A normalized tree passes `Tree::validate`, normalizing it again doesn't change it.
+    ///
+    /// Returns `Error` if a `NonTerminal` has no children, the tree is not modified in that case.
+    pub fn normalize(&mut self) -> Result<(), Error> {
+        if let Some(node) = self
+            .nonterminals()
+            .find(|&node| self.children(node).next().is_none())
+        {
+            return Err(format_err!("NonTerminal without children: {}", self[node]));
+        }
+        self.reindex_terminals()?;
+        self.projectivity = self.compute_projectivity();
+        let nodes = self.graph.node_indices().collect::<Vec<_>>();
+        for node in nodes {
+            if self[node]
+                .features()
+                .is_some_and(|features| features.inner().is_empty())
+            {
+                self[node].set_features(None);
+            }
+        }
+        Ok(())
+    }
+
+    /// Project indices of `NonTerminal`s onto `Terminal`s.
+    ///
+    /// This method projects the `NodeIndex` of `NonTerminal`s with a label in
+    /// `tag_set` onto the terminal nodes. The hierarchically closest node's `NodeIndex`
+    /// will be assigned to each terminal.
+    ///
+    /// Returns a `Vec<NodeIndex>`.
+    pub fn project_nt_indices(&self, tag_set: &LabelSet) -> Vec<NodeIndex> {
+        let mut bfs = Bfs::new(&self.graph, self.root);
+        let mut indices = vec![self.root; self.n_terminals];
+        while let Some(node) = bfs.next(&self.graph) {
+            if let Some(inner) = self.graph[node].nonterminal() {
+                if tag_set.matches(inner.label()) {
+                    for id in self.graph[node].span() {
+                        indices[id] = node;
+                    }
+                }
+            }
+        }
+        indices
+    }
+
+    /// Project labels of `NonTerminal`s onto `Terminal`s.
+    ///
+    /// This method projects the label for each node in `tag_set` onto the terminal nodes.
+    /// The hierarchically closest node's label will be assigned to each terminal.
+    pub fn project_tag_set(&self, tag_set: &LabelSet) -> Vec<&str> {
+        let indices = self.project_nt_indices(tag_set);
+        indices
+            .into_iter()
+            .map(|nt_idx| {
+                // safe to unwrap, we get the IDs from project_ids()
+                // which checks if nt_idx is Node::Inner
+                self.graph[nt_idx].nonterminal().unwrap().label()
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// Project unique IDs for nodes with label in `tag_set` onto terminals.
+    pub fn project_ids(&self, tag_set: &LabelSet) -> Vec<usize> {
+        let indices = self.project_nt_indices(tag_set);
+        let mut ids = vec![0; self.n_terminals];
+        let mut mapping = HashMap::new();
+        for (term_id, idx) in indices.into_iter().enumerate() {
+            let n = mapping.len();
+            let id = *mapping.entry(idx).or_insert(n);
+            ids[term_id] = id;
+        }
+        ids
+    }
+
+    /// Project labels of `NonTerminal`s in `tag_set` onto terminals in BIO encoding.
+    ///
+    /// Terminals are projected onto nodes as in `Tree::project_nt_indices`. A terminal is tagged
+    /// `B-` followed by the label of its node if the node differs from the node of the preceding
+    /// terminal, `I-` followed by the label otherwise, e.g. `B-NP I-NP B-VP`. Terminals that are
+    /// not covered by a node in `tag_set` are tagged `O`. Each run of a discontinuous node begins
+    /// with `B-`.
+    pub fn project_bio(&self, tag_set: &LabelSet) -> Vec<String> {
+        let indices = self.project_nt_indices(tag_set);
+        let mut prev = None;
+        indices
+            .into_iter()
+            .map(|nt_idx| {
+                let label = self[nt_idx].label();
+                let tag = if nt_idx == self.root && !tag_set.matches(label) {
+                    "O".to_owned()
+                } else if prev == Some(nt_idx) {
+                    format!("I-{}", label)
+                } else {
+                    format!("B-{}", label)
+                };
+                prev = Some(nt_idx);
+                tag
+            })
+            .collect()
+    }
+
+    // helper method to sort a vec of node indices
+    // order is determined by:
+    // 1. lower bound of span (starting point of span)
+    // 2. upper bound of span (end point of span)
+    // 3. number of covered indices by span
+    // 4. Inner nodes before terminal nodes
+    // 5. alphabetical order
+    fn sort_indices(&self, indices: &mut [NodeIndex]) {
+        indices.sort_by(
+            |node1, node2| match self[*node1].span().cmp(self[*node2].span()) {
+                Ordering::Equal => match (&self[*node1], &self[*node2]) {
+                    (Node::NonTerminal(_), Node::Terminal(_)) => Ordering::Greater,
+                    (Node::Terminal(_), Node::NonTerminal(_)) => Ordering::Less,
+                    (Node::NonTerminal(nt1), Node::NonTerminal(nt2)) => {
+                        let order = self
+                            .children(*node1)
+                            .count()
+                            .cmp(&self.children(*node2).count());
+                        if order != Ordering::Equal {
+                            order
+                        } else {
+                            nt1.label().cmp(nt2.label())
+                        }
+                    }
+                    (Node::Terminal(t1), Node::Terminal(t2)) => t1.form().cmp(t2.form()),
+                },
+                ordering => ordering,
+            },
+        );
+    }
+
+    /// Reset terminal spans to increasing by 1 at each step.
+    ///
+    /// If a span was skipped because of e.g. removed terminals, restore the correct order.
+    /// If two terminals with same span are present, alphabetical order of forms is used as the
+    /// tie-breaker.
+    #[allow(dead_code)]
+    pub(crate) fn compact_terminal_spans(&mut self) -> Result<(), Error> {
+        let mut terminals = self.terminals().collect::<Vec<_>>();
+        self.sort_indices(&mut terminals);
+        for (idx, &term) in terminals.iter().enumerate() {
+            self[term].set_span(idx)?;
+        }
+        self.terminal_indices = terminals;
+        Ok(())
+    }
+
+    /// Reindex the tree after terminals were removed or merged.
+    ///
+    /// Compacts the terminal spans, updates the number of terminals and recomputes the spans of
+    /// all `NonTerminal`s. Every `NonTerminal` is required to dominate at least one terminal.
+    pub(crate) fn reindex_terminals(&mut self) -> Result<(), Error> {
+        self.compact_terminal_spans()?;
+        self.n_terminals = self.terminal_indices.len();
+        self.reset_nt_spans();
+        Ok(())
+    }
+
+    // (re)build the mapping from surface positions to terminal nodes.
+    fn index_terminals(&mut self) {
+        let mut terminals = self.terminals().collect::<Vec<_>>();
+        self.sort_indices(&mut terminals);
+        self.terminal_indices = terminals;
+    }
+
+    /// Resets nonterminal spans based on terminal spans.
+    #[allow(dead_code)]
+    pub(crate) fn reset_nt_spans(&mut self) {
+        let mut dfs = DfsPostOrder::new(&self.graph, self.root);
+        while let Some(node) = dfs.next(&self.graph) {
+            if !self[node].is_terminal() {
+                let coverage = self
+                    .children(node)
+                    .flat_map(|child| self[child].span().into_iter())
+                    .collect::<Vec<_>>();
+                let span = Span::from_vec(coverage).unwrap();
+                self[node].nonterminal_mut().unwrap().set_span(span);
+            }
+        }
+    }
+}
+
+/// Shape metrics of a single tree.
+///
+/// The depth of a tree is the number of edges on the longest path from the root to a terminal.
+/// Unary productions are `NonTerminal`s with exactly one child.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ShapeMetrics {
+    branching_factor: f64,
+    max_depth: usize,
+    n_unary: usize,
+    n_nonterminals: usize,
+}
+
+impl ShapeMetrics {
+    /// Get the mean number of children per `NonTerminal`.
+    ///
+    /// Returns `0` if the tree has no `NonTerminal`s.
+    pub fn branching_factor(&self) -> f64 {
+        self.branching_factor
+    }
+
+    /// Get the depth of the tree.
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Get the number of unary productions.
+    pub fn n_unary(&self) -> usize {
+        self.n_unary
+    }
+
+    /// Get the number of `NonTerminal`s.
+    pub fn n_nonterminals(&self) -> usize {
+        self.n_nonterminals
+    }
+}
 
 // recursively check if at each level the trees are identical.
 fn eq_helper(tree_1_idx: NodeIndex, tree_1: &Tree, tree_2_idx: NodeIndex, tree_2: &Tree) -> bool {
@@ -268,169 +1689,1464 @@ fn eq_helper(tree_1_idx: NodeIndex, tree_1: &Tree, tree_2_idx: NodeIndex, tree_2
     let mut nodes2 = tree_2.children(tree_2_idx).collect::<Vec<_>>();
     tree_2.sort_indices(&mut nodes2);
 
-    for (node1, node2) in nodes1.into_iter().zip(nodes2) {
-        if tree_1[node1] != tree_2[node2] {
-            return false;
-        }
-        let p1 = tree_1
-            .parent(node1)
-            .map(|(parent_id, edge_id)| (&tree_1[parent_id], &tree_1[edge_id]));
-        let p2 = tree_2
-            .parent(node2)
-            .map(|(parent_id, edge_id)| (&tree_2[parent_id], &tree_2[edge_id]));
-        if p1 != p2 {
-            return false;
-        }
+    for (node1, node2) in nodes1.into_iter().zip(nodes2) {
+        if tree_1[node1] != tree_2[node2] {
+            return false;
+        }
+        let p1 = tree_1
+            .parent(node1)
+            .map(|(parent_id, edge_id)| (&tree_1[parent_id], &tree_1[edge_id]));
+        let p2 = tree_2
+            .parent(node2)
+            .map(|(parent_id, edge_id)| (&tree_2[parent_id], &tree_2[edge_id]));
+        if p1 != p2 {
+            return false;
+        }
+
+        if !eq_helper(node1, tree_1, node2, tree_2) {
+            return false;
+        }
+    }
+    true
+}
+
+// greedily find a set of terminals whose removal closes all gaps. the fillers of the
+// discontinuous node that still covers the most terminals are removed first, ties are broken by
+// the number of fillers. every step removes at least one terminal, so there are at most as many
+// steps as terminals.
+fn gap_fillers(coverages: &[BTreeSet<usize>], terminals: &BTreeSet<usize>) -> BTreeSet<usize> {
+    let mut removed = BTreeSet::new();
+    loop {
+        let next = coverages
+            .iter()
+            .filter_map(|coverage| {
+                let remaining = coverage.difference(&removed).collect::<Vec<_>>();
+                let (&&lower, &&upper) = (remaining.first()?, remaining.last()?);
+                let fillers = terminals
+                    .range(lower..=upper)
+                    .filter(|idx| !removed.contains(idx) && !coverage.contains(idx))
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if fillers.is_empty() {
+                    None
+                } else {
+                    Some((remaining.len(), fillers))
+                }
+            })
+            .max_by_key(|(covered, fillers)| (*covered, Reverse(fillers.len())));
+        match next {
+            Some((_, fillers)) => removed.extend(fillers),
+            None => return removed,
+        }
+    }
+}
+
+pub(crate) fn is_placeholder_pos(pos: &str) -> bool {
+    pos.is_empty() || pos == "_"
+}
+
+/// Nested description of a tree.
+///
+/// Used to construct trees through `Tree::from_nested`. Inner nodes can also be constructed
+/// from `(label, children)` tuples.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Nested {
+    /// A terminal with form and part-of-speech.
+    Leaf { form: String, pos: String },
+    /// A nonterminal with label and children.
+    Inner {
+        label: String,
+        children: Vec<Nested>,
+    },
+}
+
+impl Nested {
+    /// Construct a leaf.
+    pub fn leaf(form: impl Into<String>, pos: impl Into<String>) -> Self {
+        Nested::Leaf {
+            form: form.into(),
+            pos: pos.into(),
+        }
+    }
+
+    /// Construct an inner node.
+    pub fn inner(label: impl Into<String>, children: Vec<Nested>) -> Self {
+        Nested::Inner {
+            label: label.into(),
+            children,
+        }
+    }
+}
+
+impl<S> From<(S, Vec<Nested>)> for Nested
+where
+    S: Into<String>,
+{
+    fn from((label, children): (S, Vec<Nested>)) -> Self {
+        Nested::inner(label, children)
+    }
+}
+
+impl PartialEq for Tree {
+    fn eq(&self, other: &Tree) -> bool {
+        // cheap checks first, node count and number of terminals
+        if self.n_terminals != other.n_terminals {
+            return false;
+        };
+        if self.graph.node_count() != other.graph.node_count() {
+            return false;
+        };
+
+        eq_helper(self.root(), self, other.root(), other)
+    }
+}
+
+impl fmt::Debug for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Tree({:?}", self.projectivity)?;
+        let mut visited = HashSet::new();
+        if self.graph.contains_node(self.root) {
+            self.fmt_debug_node(f, self.root, None, 0, &mut visited)?;
+        }
+        let mut unattached = self
+            .graph
+            .node_indices()
+            .filter(|node| !visited.contains(node))
+            .collect::<Vec<_>>();
+        if !unattached.is_empty() {
+            self.sort_indices(&mut unattached);
+            f.write_str(if f.alternate() {
+                "\nunattached:"
+            } else {
+                " unattached:"
+            })?;
+            for node in unattached {
+                self.fmt_debug_node(f, node, None, 1, &mut visited)?;
+            }
+        }
+        if f.alternate() {
+            f.write_str("\n")?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl Tree {
+    // Write the Debug representation of the subtree rooted at `node`.
+    fn fmt_debug_node(
+        &self,
+        f: &mut fmt::Formatter,
+        node: NodeIndex,
+        edge: Option<&str>,
+        depth: usize,
+        visited: &mut HashSet<NodeIndex>,
+    ) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "\n{}", "  ".repeat(depth + 1))?;
+        } else {
+            f.write_str(" ")?;
+        }
+        if !visited.insert(node) {
+            return write!(f, "(cycle at {})", self[node].label());
+        }
+        write!(f, "({}", self[node].describe())?;
+        if let Some(edge) = edge {
+            write!(f, " edge={}", edge)?;
+        }
+        let mut children = self.children(node).collect::<Vec<_>>();
+        self.sort_indices(&mut children);
+        for child in children {
+            let (_, edge) = self.parent(child).unwrap();
+            self.fmt_debug_node(f, child, self[edge].label(), depth + 1, visited)?;
+        }
+        f.write_str(")")
+    }
+}
+
+impl Index<NodeIndex> for Tree {
+    type Output = Node;
+
+    fn index(&self, index: NodeIndex) -> &<Self as Index<NodeIndex>>::Output {
+        &self.graph[index]
+    }
+}
+
+impl Index<EdgeIndex> for Tree {
+    type Output = Edge;
+
+    fn index(&self, index: EdgeIndex) -> &<Self as Index<EdgeIndex>>::Output {
+        &self.graph[index]
+    }
+}
+
+impl IndexMut<NodeIndex> for Tree {
+    fn index_mut(&mut self, index: NodeIndex) -> &mut Node {
+        &mut self.graph[index]
+    }
+}
+
+impl IndexMut<EdgeIndex> for Tree {
+    fn index_mut(&mut self, index: EdgeIndex) -> &mut Edge {
+        &mut self.graph[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use petgraph::prelude::{NodeIndex, StableGraph};
+
+    use crate::io::{negra_to_tree, PTBFormat};
+    use crate::tree_modification::TreeOps;
+    use crate::util::LabelSet;
+    use crate::{
+        Edge, Features, HeadDirection, HeadRules, LumberjackError, Nested, Node, NonTerminal,
+        Projectivity, ShapeMetrics, Span, StructuralError, Terminal, Tree,
+    };
+
+    fn leaf(form: &str, pos: &str) -> Nested {
+        Nested::leaf(form, pos)
+    }
+
+    #[test]
+    fn retag() {
+        let mut tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (NN dog)) (VP (VBZ barks)))")
+            .unwrap();
+        let original = tree.clone();
+        assert!(tree.retag(vec!["DT", "NN"], Some("gold")).is_err());
+        assert!(tree
+            .retag(vec!["DT", "NN", "VBZ", "X"], Some("gold"))
+            .is_err());
+        assert_eq!(tree, original);
+
+        tree.retag(vec!["DT", "JJ", "NNS"], Some("gold")).unwrap();
+        let tags = (0..3)
+            .map(|idx| {
+                let terminal = tree.nth_terminal(idx).unwrap();
+                (
+                    terminal.label(),
+                    terminal
+                        .features()
+                        .and_then(|features| features.get_val("gold")),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tags,
+            vec![("DT", None), ("JJ", Some("NN")), ("NNS", Some("VBZ"))]
+        );
+
+        let mut tree = original.clone();
+        tree.retag(vec!["X", "Y", "Z"], None).unwrap();
+        assert!(tree
+            .terminals()
+            .all(|terminal| tree[terminal].features().is_none()));
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&tree).unwrap(),
+            "(S (NP (X the) (Y dog)) (VP (Z barks)))"
+        );
+    }
+
+    #[test]
+    fn leftmost_rightmost_terminal() {
+        let tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (NN dog)) (VP (VBZ barks) (ADVP (RB loudly))))")
+            .unwrap();
+        let form = |node: NodeIndex| tree[node].terminal().unwrap().form();
+        let root = tree.root();
+        assert_eq!(form(tree.leftmost_terminal(root)), "the");
+        assert_eq!(form(tree.rightmost_terminal(root)), "loudly");
+        let vp = tree.parent(tree.terminal_at(2).unwrap()).unwrap().0;
+        assert_eq!(form(tree.leftmost_terminal(vp)), "barks");
+        assert_eq!(form(tree.rightmost_terminal(vp)), "loudly");
+        let dog = tree.terminal_at(1).unwrap();
+        assert_eq!(tree.leftmost_terminal(dog), dog);
+        assert_eq!(tree.rightmost_terminal(dog), dog);
+
+        // spans changed through IndexMut are picked up without reindexing.
+        let mut swapped = tree.clone();
+        let the = swapped.terminal_at(0).unwrap();
+        swapped[the].set_span(1).unwrap();
+        swapped[dog].set_span(0).unwrap();
+        let np = swapped.parent(dog).unwrap().0;
+        assert_eq!(swapped.leftmost_terminal(np), dog);
+        assert_eq!(swapped.rightmost_terminal(np), the);
+
+        let tree = gapped_tree();
+        let form = |node: NodeIndex| tree[node].terminal().unwrap().form();
+        let vp = tree.parent(tree.terminal_at(2).unwrap()).unwrap().0;
+        assert_eq!(form(tree.leftmost_terminal(vp)), "barks");
+        assert_eq!(form(tree.rightmost_terminal(tree.root())), "barks");
+
+        let input = std::fs::read_to_string("testdata/long_single.negra").unwrap();
+        let tree = negra_to_tree(&input).unwrap();
+        for nt in tree.nonprojective_nodes() {
+            let covered = tree[nt].span().into_iter().collect::<Vec<_>>();
+            let leftmost = tree.leftmost_terminal(nt);
+            let rightmost = tree.rightmost_terminal(nt);
+            assert_eq!(tree[leftmost].span().lower(), covered[0]);
+            assert_eq!(tree[rightmost].span().lower(), covered[covered.len() - 1]);
+        }
+    }
+
+    #[test]
+    fn swap_form_label() {
+        let tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (the DT) (dog NN)) (VP (barks VBZ)))")
+            .unwrap();
+        let mut swapped = tree.clone();
+        let terminal = swapped.terminal_at(1).unwrap();
+        swapped[terminal].features_mut().insert("num", Some("sg"));
+        let features = swapped[terminal].features().cloned();
+        swapped.swap_form_label();
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&swapped).unwrap(),
+            "(S (NP (DT the) (NN dog)) (VP (VBZ barks)))"
+        );
+        assert_eq!(swapped[terminal].features(), features.as_ref());
+        swapped.swap_form_label();
+        swapped[terminal].set_features(None);
+        assert_eq!(swapped, tree);
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&swapped).unwrap(),
+            "(S (NP (the DT) (dog NN)) (VP (barks VBZ)))"
+        );
+    }
+
+    #[test]
+    fn for_each_terminal_mut() {
+        let mut tree = PTBFormat::Simple
+            .string_to_tree("(S (VP (VBZ Barks)) (NP (DT The) (NN Dog)))")
+            .unwrap();
+        let mut forms = Vec::new();
+        tree.for_each_terminal_mut(|terminal| {
+            forms.push(terminal.form().to_owned());
+            let form = terminal.form().to_lowercase();
+            terminal.set_form(form);
+        });
+        assert_eq!(forms, vec!["Barks", "The", "Dog"]);
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&tree).unwrap(),
+            "(S (VP (VBZ barks)) (NP (DT the) (NN dog)))"
+        );
+    }
+
+    #[test]
+    fn unary_chains() {
+        let tree = PTBFormat::Simple
+            .string_to_tree("(ROOT (UNARY (T t)))")
+            .unwrap();
+        let chains = tree.unary_chains();
+        assert_eq!(chains.len(), 1);
+        let labels = chains[0]
+            .iter()
+            .map(|&node| tree[node].label())
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec!["ROOT", "UNARY", "T"]);
+        assert!(tree.preorder().all(|node| tree.is_unary_chain_node(node)));
+
+        let tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (NN dog)) (VP (VBZ barks)) (ADVP (ADVP (RB now))))")
+            .unwrap();
+        let labels = tree
+            .unary_chains()
+            .into_iter()
+            .map(|chain| {
+                chain
+                    .into_iter()
+                    .map(|node| tree[node].label())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(labels, vec![vec!["VP", "VBZ"], vec!["ADVP", "ADVP", "RB"]]);
+        let dt = tree.terminal_at(0).unwrap();
+        assert!(!tree.is_unary_chain_node(dt));
+        assert!(!tree.is_unary_chain_node(tree.root()));
+    }
+
+    #[test]
+    fn reset_spans() {
+        let mut tree = some_tree();
+        let nt_indices = tree.nonterminals().collect::<Vec<_>>();
+        for nt in nt_indices {
+            tree[nt]
+                .nonterminal_mut()
+                .unwrap()
+                .set_span(Span::from_vec(vec![0, 2]).unwrap());
+        }
+        tree.reset_nt_spans();
+        assert_eq!(tree, some_tree());
+
+        let mut tree = some_tree();
+        let term_indices = tree.terminals().collect::<Vec<_>>();
+        for (idx, term) in term_indices.into_iter().enumerate() {
+            tree[term].set_span(idx + 1).unwrap();
+        }
+        tree.compact_terminal_spans().unwrap();
+        assert_eq!(tree, some_tree());
+    }
+
+    #[test]
+    fn debug() {
+        let mut tree = PTBFormat::TueBa
+            .string_to_tree("(S(NP:SB(NN:HD dog))(VBZ:HD barks))")
+            .unwrap();
+        assert_eq!(
+            format!("{:?}", tree),
+            "Tree(Projective (S span=0..2 (NP span=0..1 edge=SB (NN dog span=0..1 edge=HD)) \
+             (VBZ barks span=1..2 edge=HD)))"
+        );
+        assert_eq!(
+            format!("{:#?}", tree),
+            "Tree(Projective
+  (S span=0..2
+    (NP span=0..1 edge=SB
+      (NN dog span=0..1 edge=HD))
+    (VBZ barks span=1..2 edge=HD))
+)"
+        );
+
+        tree.graph_mut()
+            .add_node(Node::NonTerminal(NonTerminal::new("X", 0)));
+        assert!(format!("{:?}", tree).ends_with(" unattached: (X span=0..1))"));
+    }
+
+    #[test]
+    fn normalize() {
+        // stale spans, terminal indices with gaps and a wrong projectivity.
+        let mut tree = nonprojective_some_tree();
+        let first = NodeIndex::new(1);
+        let root = tree.root();
+        tree.set_projectivity(Projectivity::Projective);
+        let terminals = tree.terminals().collect::<Vec<_>>();
+        for terminal in terminals {
+            let idx = tree[terminal].span().lower();
+            tree[terminal].set_span(idx * 2).unwrap();
+        }
+        tree[root].features_mut();
+        assert!(tree.validate().is_err());
+
+        tree.normalize().unwrap();
+        tree.validate().unwrap();
+        assert_eq!(tree.projectivity(), Projectivity::Nonprojective);
+        assert_eq!(tree[first].span(), &Span::from_vec(vec![0, 2]).unwrap());
+        assert_eq!(tree[root].span(), &Span::new_continuous(0, 5));
+        assert_eq!(tree[root].features(), None);
+
+        let normalized = tree.clone();
+        tree.normalize().unwrap();
+        assert_eq!(tree, normalized);
+        assert_eq!(
+            tree.to_string_with_indices(),
+            normalized.to_string_with_indices()
+        );
+
+        let mut tree = some_tree();
+        tree.graph_mut()
+            .add_node(Node::NonTerminal(NonTerminal::new("EMPTY", 0)));
+        assert!(tree.normalize().is_err());
+    }
+
+    #[test]
+    fn structural_errors() {
+        let tree = some_tree();
+        assert!(tree.find_structural_errors().is_empty());
+        let root = tree.root();
+        let first = NodeIndex::new(1);
+        let t1 = NodeIndex::new(2);
+        let second = NodeIndex::new(5);
+        let t4 = NodeIndex::new(6);
+
+        let mut corrupted = tree.clone();
+        corrupted
+            .graph_mut()
+            .add_edge(first, first, Edge::default());
+        assert_eq!(
+            corrupted.find_structural_errors(),
+            vec![StructuralError::SelfLoop { node: first }]
+        );
+        let err = corrupted.validate().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<StructuralError>(),
+            Some(&StructuralError::SelfLoop { node: first })
+        );
+
+        let mut corrupted = tree.clone();
+        corrupted.graph_mut().add_edge(second, t1, Edge::default());
+        corrupted.graph_mut().add_edge(t4, root, Edge::default());
+        assert_eq!(
+            corrupted.find_structural_errors(),
+            vec![
+                StructuralError::RootWithParent { parent: t4 },
+                StructuralError::MultipleParents {
+                    node: t1,
+                    parents: vec![first, second]
+                },
+            ]
+        );
+        assert!(corrupted.validate().is_err());
+
+        let mut corrupted = tree.clone();
+        let (_, edge) = corrupted.parent(second).unwrap();
+        corrupted.graph_mut().remove_edge(edge);
+        assert_eq!(
+            corrupted.find_structural_errors(),
+            vec![StructuralError::Disconnected {
+                nodes: vec![second, t4]
+            }]
+        );
+        assert!(corrupted.validate().is_err());
+    }
+
+    #[test]
+    fn unknown_projectivity() {
+        let mut tree = some_tree();
+        tree.set_projectivity(Projectivity::Unknown);
+        assert!(tree.projective());
+        assert_eq!(tree.projectivity(), Projectivity::Unknown);
+        assert!(tree.validate().is_ok());
+        tree.set_projectivity(Projectivity::Nonprojective);
+        assert!(tree.validate().is_err());
+
+        let mut tree = nonprojective_some_tree();
+        assert_eq!(tree.compute_projectivity(), Projectivity::Nonprojective);
+        assert!(tree.validate().is_ok());
+        tree.set_projectivity(Projectivity::Unknown);
+        assert!(!tree.projective());
+        tree.set_projectivity(Projectivity::Projective);
+        assert!(tree.validate().is_err());
+    }
+
+    #[test]
+    fn project_node_labels() {
+        let mut g = StableGraph::new();
+        let root = NonTerminal::new("ROOT", Span::new_continuous(0, 6));
+        let first = NonTerminal::new("FIRST", Span::from_vec(vec![0, 2]).unwrap());
+        let term1 = Terminal::new("t1", "TERM1", 0);
+        let term2 = Terminal::new("t2", "TERM1", 1);
+        let term3 = Terminal::new("t3", "TERM3", 2);
+        let second = NonTerminal::new("SECOND", 3);
+        let term4 = Terminal::new("t4", "TERM4", 4);
+        let term5 = Terminal::new("t5", "TERM5", 5);
+        let root_idx = g.add_node(Node::NonTerminal(root));
+        let first_idx = g.add_node(Node::NonTerminal(first));
+        let term1_idx = g.add_node(Node::Terminal(term1));
+        let term2_idx = g.add_node(Node::Terminal(term2));
+        g.add_edge(root_idx, first_idx, Edge::default());
+        g.add_edge(first_idx, term1_idx, Edge::default());
+        g.add_edge(root_idx, term2_idx, Edge::default());
+        let term3_idx = g.add_node(Node::Terminal(term3));
+        g.add_edge(first_idx, term3_idx, Edge::default());
+        let second_idx = g.add_node(Node::NonTerminal(second));
+        g.add_edge(root_idx, second_idx, Edge::default());
+        let term4_idx = g.add_node(Node::Terminal(term4));
+        g.add_edge(second_idx, term4_idx, Edge::default());
+        let term5_idx = g.add_node(Node::Terminal(term5));
+        g.add_edge(root_idx, term5_idx, Edge::default());
+
+        let tree = Tree::new(g, 5, root_idx, Projectivity::Nonprojective);
+        let mut tags = HashSet::new();
+        tags.insert("FIRST".into());
+        let indices = tree.project_tag_set(&LabelSet::Positive(tags));
+        let target = vec!["FIRST", "ROOT", "FIRST", "ROOT", "ROOT"];
+        assert_eq!(indices, target)
+    }
+
+    #[test]
+    fn project_bio() {
+        let tree = PTBFormat::Simple
+            .string_to_tree(
+                "(S (NP (DT the) (NN dog)) (NP (NNS cats)) (VP (VBP chase) (PP (IN for) \
+                 (NP (NN fun)))) (. .))",
+            )
+            .unwrap();
+        let chunks =
+            LabelSet::Positive(vec!["NP".to_owned(), "VP".to_owned()].into_iter().collect());
+        assert_eq!(
+            tree.project_bio(&chunks),
+            vec!["B-NP", "I-NP", "B-NP", "B-VP", "I-VP", "B-NP", "O"]
+        );
+
+        let clauses = LabelSet::Positive(vec!["S".to_owned()].into_iter().collect());
+        assert_eq!(tree.project_bio(&clauses)[..2], ["B-S", "I-S"]);
+
+        let tree = nonprojective_some_tree();
+        let first = LabelSet::Positive(vec!["FIRST".to_owned()].into_iter().collect());
+        assert_eq!(
+            tree.project_bio(&first),
+            vec!["B-FIRST", "O", "B-FIRST", "O", "O"]
+        );
+    }
+
+    #[test]
+    fn project_node_ids() {
+        let mut g = StableGraph::new();
+        let root = NonTerminal::new("ROOT", Span::new_continuous(0, 5));
+        let first = NonTerminal::new("L", Span::from_vec(vec![0, 2]).unwrap());
+        let term1 = Terminal::new("t1", "TERM1", 0);
+        let term2 = Terminal::new("t2", "TERM1", 1);
+        let term3 = Terminal::new("t3", "TERM3", 2);
+        let second = NonTerminal::new("L", Span::new_continuous(3, 4));
+        let term4 = Terminal::new("t4", "TERM4", 3);
+        let term5 = Terminal::new("t5", "TERM5", 4);
+        let root_idx = g.add_node(Node::NonTerminal(root));
+        let first_idx = g.add_node(Node::NonTerminal(first));
+        let term1_idx = g.add_node(Node::Terminal(term1));
+        let term2_idx = g.add_node(Node::Terminal(term2));
+        g.add_edge(root_idx, first_idx, Edge::default());
+        g.add_edge(first_idx, term1_idx, Edge::default());
+        g.add_edge(root_idx, term2_idx, Edge::default());
+        let term3_idx = g.add_node(Node::Terminal(term3));
+        g.add_edge(first_idx, term3_idx, Edge::default());
+        let second_idx = g.add_node(Node::NonTerminal(second));
+        g.add_edge(root_idx, second_idx, Edge::default());
+        let term4_idx = g.add_node(Node::Terminal(term4));
+        g.add_edge(second_idx, term4_idx, Edge::default());
+        let term5_idx = g.add_node(Node::Terminal(term5));
+        g.add_edge(root_idx, term5_idx, Edge::default());
+
+        let tree = Tree::new(g, 5, root_idx, Projectivity::Nonprojective);
+        let mut tags = HashSet::new();
+        tags.insert("L".into());
+        let indices = tree.project_ids(&LabelSet::Positive(tags));
+        // 0 is first "L", 1 is ROOT and 2 is second "L"
+        let target = vec![0, 1, 0, 2, 1];
+        assert_eq!(indices, target)
+    }
+
+    #[test]
+    fn smallest_constituent() {
+        let tree = some_tree();
+        let first = tree.smallest_constituent(0, 2).unwrap();
+        assert_eq!(tree[first].label(), "FIRST");
+        let first = tree.smallest_constituent(1, 2).unwrap();
+        assert_eq!(tree[first].label(), "FIRST");
+        let second = tree.smallest_constituent(3, 4).unwrap();
+        assert_eq!(tree[second].label(), "SECOND");
+        let root = tree.smallest_constituent(1, 4).unwrap();
+        assert_eq!(root, tree.root());
+        let root = tree.smallest_constituent(2, 3).unwrap();
+        assert_eq!(root, tree.root());
+        assert!(tree.smallest_constituent(2, 2).is_none());
+        assert!(tree.smallest_constituent(3, 6).is_none());
+
+        assert_eq!(
+            tree.constituents_for_spans(&[(0, 2), (3, 4), (1, 4), (2, 2), (3, 6), (0, 2), (0, 5)]),
+            vec![
+                Some(first),
+                Some(second),
+                None,
+                None,
+                None,
+                Some(first),
+                Some(root)
+            ]
+        );
+    }
+
+    #[test]
+    fn constituents_for_crossing_spans() {
+        let tree = some_tree();
+        // FIRST covers 0..2, so 1..3 crosses its bracket although ROOT covers it.
+        assert_eq!(tree.smallest_constituent(1, 3), Some(tree.root()));
+        assert_eq!(
+            tree.constituents_for_spans(&[(1, 3), (0, 3)]),
+            vec![None, None]
+        );
+    }
+
+    #[test]
+    fn smallest_constituent_nonprojective() {
+        let mut g = StableGraph::new();
+        let root = NonTerminal::new("ROOT", Span::new_continuous(0, 4));
+        let first = NonTerminal::new("FIRST", Span::from_vec(vec![0, 2, 3]).unwrap());
+        let term1 = Terminal::new("t1", "TERM1", 0);
+        let term2 = Terminal::new("t2", "TERM2", 1);
+        let term3 = Terminal::new("t3", "TERM3", 2);
+        let term4 = Terminal::new("t4", "TERM4", 3);
+        let root_idx = g.add_node(Node::NonTerminal(root));
+        let first_idx = g.add_node(Node::NonTerminal(first));
+        g.add_edge(root_idx, first_idx, Edge::default());
+        let term1_idx = g.add_node(Node::Terminal(term1));
+        g.add_edge(first_idx, term1_idx, Edge::default());
+        let term2_idx = g.add_node(Node::Terminal(term2));
+        g.add_edge(root_idx, term2_idx, Edge::default());
+        let term3_idx = g.add_node(Node::Terminal(term3));
+        g.add_edge(first_idx, term3_idx, Edge::default());
+        let term4_idx = g.add_node(Node::Terminal(term4));
+        g.add_edge(first_idx, term4_idx, Edge::default());
+        let tree = Tree::new(g, 4, root_idx, Projectivity::Nonprojective);
+
+        assert_eq!(tree.smallest_constituent(2, 4), Some(first_idx));
+        assert_eq!(tree.smallest_constituent(0, 1), Some(first_idx));
+        // FIRST skips index 1
+        assert_eq!(tree.smallest_constituent(0, 2), Some(root_idx));
+        assert_eq!(tree.smallest_constituent(1, 2), Some(root_idx));
+        assert_eq!(
+            tree.span(first_idx),
+            &Span::from_vec(vec![0, 2, 3]).unwrap()
+        );
+        assert_eq!(tree.yield_range(first_idx), (0, 3));
+        assert_eq!(tree.yield_range(term2_idx), (1, 1));
+        assert_eq!(tree.yield_range(root_idx), (0, 3));
+        assert_eq!(
+            tree.constituents_for_spans(&[(2, 4), (0, 2), (0, 1), (0, 4)]),
+            vec![None, None, None, Some(root_idx)]
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        let mut tree = some_tree();
+        assert_eq!(tree.sentence_id(), None);
+        assert_eq!(tree.set_sentence_id(Some("s1")), None);
+        assert_eq!(tree.sentence_id(), Some("s1"));
+        assert_eq!(tree.metadata()["sent_id"], "s1");
+        tree.metadata_mut()
+            .insert("source".to_owned(), "corpus".to_owned());
+        assert_eq!(tree.metadata().len(), 2);
+        // metadata is ignored in comparisons
+        assert_eq!(tree, some_tree());
+        assert_eq!(tree.set_sentence_id::<String>(None), Some("s1".to_owned()));
+        assert_eq!(tree.sentence_id(), None);
+    }
+
+    #[test]
+    fn terminal_at() {
+        let mut tree = some_tree();
+        assert_eq!(tree.terminal_at(0), Some(NodeIndex::new(2)));
+        assert_eq!(tree.terminal_at(2), Some(NodeIndex::new(4)));
+        assert_eq!(tree.terminal_at(3), Some(NodeIndex::new(6)));
+        assert_eq!(tree.terminal_at(5), None);
+        for idx in 0..tree.n_terminals() {
+            let terminal = tree.terminal_at(idx).unwrap();
+            assert_eq!(tree[terminal].span().lower(), idx);
+        }
+
+        let t3 = NodeIndex::new(4);
+        tree.graph_mut().remove_node(t3);
+        tree.compact_terminal_spans().unwrap();
+        assert_eq!(tree.terminal_at(2), Some(NodeIndex::new(6)));
+        assert_eq!(tree.terminal_at(4), None);
+    }
+
+    #[test]
+    fn counts() {
+        let mut tree = some_tree();
+        assert_eq!(tree.n_nonterminals(), 3);
+        assert_eq!(tree.n_nodes(), 8);
+        let counts = tree.label_counts();
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts["FIRST"], 1);
+
+        let labels = vec!["FIRST".to_owned()].into_iter().collect();
+        tree.filter_nonterminals(&LabelSet::Negative(labels))
+            .unwrap();
+        assert_eq!(tree.n_nonterminals(), 2);
+        assert_eq!(tree.n_nodes(), 7);
+        let counts = tree.label_counts();
+        assert_eq!(counts.len(), 2);
+        assert!(!counts.contains_key("FIRST"));
+        assert_eq!(counts["SECOND"], 1);
+    }
+
+    #[test]
+    fn feature_value_counts() {
+        let mut tree = some_tree();
+        assert!(tree.feature_value_counts("parent").is_empty());
+        tree.annotate_parent_tag("parent").unwrap();
+        let counts = tree.feature_value_counts("parent");
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts[&Some("FIRST".to_owned())], 2);
+        assert_eq!(counts[&Some("ROOT".to_owned())], 2);
+        assert_eq!(counts[&Some("SECOND".to_owned())], 1);
+
+        let root = tree.root();
+        tree[root]
+            .features_mut()
+            .insert::<_, String>("parent", None);
+        tree[root].features_mut().push("parent", Some("ROOT"));
+        let counts = tree.feature_value_counts("parent");
+        assert_eq!(counts[&None], 1);
+        assert_eq!(counts[&Some("ROOT".to_owned())], 3);
+    }
+
+    #[test]
+    fn feature_table() {
+        let mut tree = some_tree();
+        let (header, rows) = tree.feature_table();
+        assert!(header.is_empty());
+        assert_eq!(rows, vec![Vec::<Option<String>>::new(); 5]);
+
+        let t3 = tree.terminal_at(2).unwrap();
+        tree[t3].set_features(Some(Features::from("morph:sg|case:nom")));
+        let t1 = tree.terminal_at(0).unwrap();
+        tree[t1].set_features(Some(Features::from("case:acc|extra")));
+        let root = tree.root();
+        tree[root].set_features(Some(Features::from("top:yes")));
+
+        let (header, rows) = tree.feature_table();
+        assert_eq!(header, vec!["case", "extra", "morph"]);
+        let s = |s: &str| Some(s.to_owned());
+        assert_eq!(
+            rows,
+            vec![
+                vec![s("acc"), None, None],
+                vec![None, None, None],
+                vec![s("nom"), None, s("sg")],
+                vec![None, None, None],
+                vec![None, None, None],
+            ]
+        );
+
+        let (header, rows) = tree.feature_table_with_nonterminals();
+        assert_eq!(header, vec!["case", "extra", "morph", "top"]);
+        assert_eq!(rows.len(), 8);
+        assert_eq!(rows[2], vec![s("nom"), None, s("sg"), None]);
+        assert!(rows[5..].contains(&vec![None, None, None, s("yes")]));
+    }
+
+    #[test]
+    fn from_nested() {
+        let tree = Tree::from_nested((
+            "S",
+            vec![
+                ("NP", vec![leaf("the", "DT"), leaf("dog", "NN")]).into(),
+                ("VP", vec![leaf("barks", "VBZ")]).into(),
+            ],
+        ))
+        .unwrap();
+        let expected = PTBFormat::TueBa
+            .string_to_tree("(S (NP (DT the) (NN dog)) (VP (VBZ barks)))")
+            .unwrap();
+        assert_eq!(tree, expected);
+        assert_eq!(tree.projectivity(), Projectivity::Projective);
+        assert_eq!(tree.n_terminals(), 3);
+        let np = tree
+            .children(tree.root())
+            .find(|&c| tree[c].label() == "NP");
+        assert_eq!(tree[np.unwrap()].span(), &Span::new_continuous(0, 2));
+        assert_eq!(tree.terminal_at(2).map(|t| tree[t].label()), Some("VBZ"));
+
+        assert!(Tree::from_nested(leaf("barks", "VBZ")).is_err());
+        assert!(Tree::from_nested(("S", vec![])).is_err());
+        assert!(Tree::from_nested(("S", vec![("NP", vec![]).into()])).is_err());
+    }
+
+    #[test]
+    fn is_fully_tagged() {
+        let mut tree = some_tree();
+        assert!(tree.is_fully_tagged());
+        let t3 = tree.terminal_at(2).unwrap();
+        tree[t3].set_label("_");
+        assert!(!tree.is_fully_tagged());
+        tree[t3].set_label("");
+        assert!(!tree.is_fully_tagged());
+    }
+
+    #[test]
+    fn into_terminals() {
+        let tree = some_tree();
+        let forms = tree
+            .into_terminals()
+            .map(|terminal| {
+                assert_eq!(terminal.features(), None);
+                terminal.form().to_owned()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(forms, vec!["t1", "t2", "t3", "t4", "t5"]);
+    }
+
+    #[test]
+    fn nth_terminal() {
+        let mut tree = some_tree();
+        assert_eq!(tree.nth_terminal(0).unwrap().form(), "t1");
+        assert_eq!(tree.nth_terminal(4).unwrap().form(), "t5");
+        assert!(tree.nth_terminal(5).is_none());
+        tree.nth_terminal_mut(2).unwrap().set_form("t");
+        assert_eq!(tree[NodeIndex::new(4)].terminal().unwrap().form(), "t");
+        assert!(tree.nth_terminal_mut(5).is_none());
+    }
+
+    #[test]
+    fn covers_contiguous() {
+        let mut tree = some_tree();
+        assert!(tree.covers_contiguous(5));
+        assert!(!tree.covers_contiguous(4));
+        assert!(!tree.covers_contiguous(6));
+        assert!(tree.validate().is_ok());
+
+        // duplicate index 0
+        let t2 = NodeIndex::new(3);
+        tree[t2].set_span(0).unwrap();
+        assert!(!tree.covers_contiguous(5));
+        assert!(tree.validate().is_err());
+
+        // gap at index 1
+        tree[t2].set_span(5).unwrap();
+        assert!(!tree.covers_contiguous(5));
+        assert!(!tree.covers_contiguous(6));
+        tree.compact_terminal_spans().unwrap();
+        assert!(tree.covers_contiguous(5));
+    }
+
+    #[test]
+    fn set_forms() {
+        let mut tree = some_tree();
+        tree[NodeIndex::new(2)]
+            .terminal_mut()
+            .unwrap()
+            .set_lemma(Some("l1"));
+        tree.set_forms(vec!["a", "b", "c", "d", "e"]).unwrap();
+        let forms = (0..5)
+            .map(|idx| {
+                let terminal = tree.terminal_at(idx).unwrap();
+                tree[terminal].terminal().unwrap().form().to_owned()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(forms, vec!["a", "b", "c", "d", "e"]);
+        let t1 = tree[NodeIndex::new(2)].terminal().unwrap();
+        assert_eq!(t1.lemma(), Some("l1"));
+        assert_eq!(t1.label(), "TERM1");
+
+        let err = tree.set_forms(vec!["a"]).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LumberjackError>(),
+            Some(&LumberjackError::FormCountMismatch {
+                n_forms: 1,
+                n_terminals: 5
+            })
+        );
+        assert_eq!(
+            tree[tree.terminal_at(0).unwrap()]
+                .terminal()
+                .unwrap()
+                .form(),
+            "a"
+        );
+    }
+
+    #[test]
+    fn add_nonterminal_over() {
+        let mut tree = some_tree();
+        let root = tree.root();
+        let t1 = NodeIndex::new(2);
+        let t3 = NodeIndex::new(4);
+        let second = NodeIndex::new(5);
+        let t5 = NodeIndex::new(7);
+
+        assert!(tree.add_nonterminal_over("NEW", &[]).is_err());
+        assert!(tree.add_nonterminal_over("NEW", &[root]).is_err());
+        assert!(tree.add_nonterminal_over("NEW", &[t1, t3]).is_err());
+
+        let new = tree.add_nonterminal_over("NEW", &[t3, second]).unwrap();
+        assert_eq!(tree[new].span(), &Span::new_continuous(2, 4));
+        assert_eq!(tree.parent(new).unwrap().0, root);
+        assert_eq!(tree.parent(t3).unwrap().0, new);
+        assert_eq!(tree.parent(second).unwrap().0, new);
+        assert!(tree.projective());
+        tree.validate().unwrap();
+
+        let upper = tree.add_nonterminal_over("UPPER", &[new, t5]).unwrap();
+        assert_eq!(tree[upper].span(), &Span::new_continuous(2, 5));
+        assert_eq!(tree.parent(new).unwrap().0, upper);
+
+        let mut tree = some_tree();
+        let first = NodeIndex::new(1);
+        let gap = tree.add_nonterminal_over("GAP", &[first, t5]).unwrap();
+        assert_eq!(tree[gap].span(), &Span::from_vec(vec![0, 1, 4]).unwrap());
+        assert_eq!(tree.projectivity(), Projectivity::Nonprojective);
+        tree.validate().unwrap();
+    }
+
+    #[test]
+    fn relabel_and_set_root() {
+        let mut tree = some_tree();
+        let root = tree.root();
+        tree.relabel_root("TOP").unwrap();
+        assert_eq!(tree[root].label(), "TOP");
+        assert_eq!(tree.root(), root);
+        assert_eq!(tree.children(root).count(), 4);
 
-        if !eq_helper(node1, tree_1, node2, tree_2) {
-            return false;
-        }
+        let first = NodeIndex::new(1);
+        assert!(tree.set_root(first).is_err());
+        assert!(tree.set_root(NodeIndex::new(100)).is_err());
+        assert_eq!(tree.root(), root);
+        tree.set_root(root).unwrap();
+
+        let mut tree = PTBFormat::Simple.string_to_tree("(NN dog)").unwrap();
+        assert!(tree.relabel_root("TOP").is_err());
+        let root = tree.root();
+        assert_eq!(tree[root].label(), "NN");
     }
-    true
-}
 
-impl PartialEq for Tree {
-    fn eq(&self, other: &Tree) -> bool {
-        // cheap checks first, node count and number of terminals
-        if self.n_terminals != other.n_terminals {
-            return false;
-        };
-        if self.graph.node_count() != other.graph.node_count() {
-            return false;
-        };
+    #[test]
+    fn merge_terminals() {
+        let input = "(ROOT (NP (NN foo) (NN bar) (NN baz)) (VP (NN qux) (VBZ runs)))";
+        let mut tree = PTBFormat::Simple.string_to_tree(input).unwrap();
+        let foo = tree.terminal_at(0).unwrap();
+        tree[foo].features_mut().insert("case", Some("nom"));
+        let bar = tree.terminal_at(1).unwrap();
+        tree[bar].features_mut().insert("case", Some("acc"));
+        tree[bar].features_mut().insert("num", Some("sg"));
 
-        eq_helper(self.root(), self, other.root(), other)
+        tree.merge_terminals("", |t1, t2| {
+            t1.label() == t2.label() && t1.form() != "foobar"
+        })
+        .unwrap();
+        assert_eq!(tree.n_terminals(), 4);
+        let forms = (0..tree.n_terminals())
+            .map(|idx| {
+                let terminal = tree.terminal_at(idx).unwrap();
+                tree[terminal].terminal().unwrap().form()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(forms, vec!["foobar", "baz", "qux", "runs"]);
+        assert_eq!(
+            tree[foo].features(),
+            Some(&Features::from("case:nom|num:sg"))
+        );
+        assert!(tree.covers_contiguous(4));
+        tree.validate().unwrap();
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&tree).unwrap(),
+            "(ROOT (NP (NN foobar) (NN baz)) (VP (NN qux) (VBZ runs)))"
+        );
+
+        // siblings only
+        let mut tree = PTBFormat::Simple.string_to_tree(input).unwrap();
+        tree.merge_terminals("_", |t1, t2| t1.label() == t2.label())
+            .unwrap();
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&tree).unwrap(),
+            "(ROOT (NP (NN foo_bar_baz)) (VP (NN qux) (VBZ runs)))"
+        );
+        assert_eq!(tree[tree.root()].span(), &Span::new_continuous(0, 3));
     }
-}
 
-impl Index<NodeIndex> for Tree {
-    type Output = Node;
+    #[test]
+    fn shape_metrics() {
+        let metrics = some_tree().shape_metrics();
+        assert_eq!(metrics.n_nonterminals(), 3);
+        assert_eq!(metrics.n_unary(), 1);
+        assert_eq!(metrics.max_depth(), 2);
+        assert!((metrics.branching_factor() - 7. / 3.).abs() < 1e-8);
 
-    fn index(&self, index: NodeIndex) -> &<Self as Index<NodeIndex>>::Output {
-        &self.graph[index]
+        let tree = PTBFormat::Simple.string_to_tree("(NN dog)").unwrap();
+        assert_eq!(tree.shape_metrics(), ShapeMetrics::default());
     }
-}
 
-impl Index<EdgeIndex> for Tree {
-    type Output = Edge;
+    #[test]
+    fn to_dependencies() {
+        let input = "(S:--(NX:ON(ART:-- the)(NN:HD dog))(VXFIN:HD(VVFIN:HD barks))(ADVX:MOD(ADV:HD loudly)))";
+        let tree = PTBFormat::TueBa.string_to_tree(input).unwrap();
+        let rules = HeadRules::new(HeadDirection::LeftToRight)
+            .rule("S", HeadDirection::LeftToRight, vec!["VXFIN"])
+            .rule("NX", HeadDirection::RightToLeft, vec!["NN"]);
+        let dependencies = tree.to_dependencies(&rules).unwrap();
+        assert_eq!(
+            dependencies,
+            vec![
+                (1, 2, "_".to_owned()),
+                (2, 3, "ON".to_owned()),
+                (3, 0, "ROOT".to_owned()),
+                (4, 3, "MOD".to_owned()),
+            ]
+        );
 
-    fn index(&self, index: EdgeIndex) -> &<Self as Index<EdgeIndex>>::Output {
-        &self.graph[index]
+        let tree = PTBFormat::Simple.string_to_tree("(NN dog)").unwrap();
+        assert_eq!(
+            tree.to_dependencies(&rules).unwrap(),
+            vec![(1, 0, "ROOT".to_owned())]
+        );
+
+        // (S (NP (DT the_0) (NN dog_1)) (VP (VBZ barks_3)))
+        let tree = gapped_tree();
+        let rules = HeadRules::new(HeadDirection::LeftToRight)
+            .rule("S", HeadDirection::LeftToRight, vec!["VP"])
+            .rule("NP", HeadDirection::RightToLeft, vec!["NN"]);
+        assert_eq!(
+            tree.to_dependencies(&rules).unwrap(),
+            vec![
+                (1, 2, "_".to_owned()),
+                (2, 4, "_".to_owned()),
+                (4, 0, "ROOT".to_owned()),
+            ]
+        );
     }
-}
 
-impl IndexMut<NodeIndex> for Tree {
-    fn index_mut(&mut self, index: NodeIndex) -> &mut Node {
-        &mut self.graph[index]
+    #[test]
+    fn find() {
+        let input = "(ROOT (NP (NN a) (NP (NN b))) (VP (VB c)) (NP (NN d) (NE e)))";
+        let tree = PTBFormat::Simple.string_to_tree(input).unwrap();
+        let lowers = |nodes: Vec<NodeIndex>| {
+            nodes
+                .into_iter()
+                .map(|node| tree[node].span().lower())
+                .collect::<Vec<_>>()
+        };
+        let np = LabelSet::Positive(vec!["NP".to_owned()].into_iter().collect());
+        assert_eq!(lowers(tree.find(&np)), vec![0, 1, 3]);
+        let not_np = LabelSet::Negative(vec!["NP".to_owned()].into_iter().collect());
+        let found = tree.find(&not_np);
+        assert_eq!(
+            found
+                .iter()
+                .map(|&node| tree[node].label())
+                .collect::<Vec<_>>(),
+            vec!["ROOT", "VP"]
+        );
+
+        let nn = LabelSet::Positive(vec!["NN".to_owned()].into_iter().collect());
+        assert_eq!(lowers(tree.find_terminals(&nn)), vec![0, 1, 3]);
+        assert_eq!(lowers(tree.find_terminals(&np)), Vec::<usize>::new());
+        let not_nn = LabelSet::Negative(vec!["NN".to_owned()].into_iter().collect());
+        assert_eq!(lowers(tree.find_terminals(&not_nn)), vec![2, 4]);
     }
-}
 
-impl IndexMut<EdgeIndex> for Tree {
-    fn index_mut(&mut self, index: EdgeIndex) -> &mut Edge {
-        &mut self.graph[index]
+    #[test]
+    fn preorder() {
+        let input =
+            "(ROOT (FIRST (TERM1 t1) (TERM2 t2)) (TERM3 t3) (SECOND (TERM4 t4)) (TERM5 t5))";
+        let tree = PTBFormat::Simple.string_to_tree(input).unwrap();
+        let labels = tree
+            .preorder()
+            .map(|node| tree[node].label())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            vec!["ROOT", "FIRST", "TERM1", "TERM2", "TERM3", "SECOND", "TERM4", "TERM5"]
+        );
+
+        // (ROOT (FIRST t0 t2 t4) (SECOND t1 t3))
+        let mut g = StableGraph::new();
+        let root = g.add_node(Node::NonTerminal(NonTerminal::new(
+            "ROOT",
+            Span::new_continuous(0, 5),
+        )));
+        let second = NonTerminal::new("SECOND", Span::from_vec(vec![1, 3]).unwrap());
+        let second = g.add_node(Node::NonTerminal(second));
+        let first = NonTerminal::new("FIRST", Span::from_vec(vec![0, 2, 4]).unwrap());
+        let first = g.add_node(Node::NonTerminal(first));
+        g.add_edge(root, second, Edge::default());
+        g.add_edge(root, first, Edge::default());
+        let mut terminals = Vec::new();
+        for idx in (0..5).rev() {
+            let term = g.add_node(Node::Terminal(Terminal::new(format!("t{}", idx), "T", idx)));
+            g.add_edge(
+                if idx % 2 == 0 { first } else { second },
+                term,
+                Edge::default(),
+            );
+            terminals.push(term);
+        }
+        terminals.reverse();
+        let tree = Tree::new(g, 5, root, Projectivity::Nonprojective);
+        assert_eq!(
+            tree.preorder().collect::<Vec<_>>(),
+            vec![
+                root,
+                first,
+                terminals[0],
+                terminals[2],
+                terminals[4],
+                second,
+                terminals[1],
+                terminals[3]
+            ]
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashSet;
+    #[test]
+    fn join() {
+        let first = PTBFormat::Simple
+            .string_to_tree("(S (NP (NN dogs)) (VP (VBP bark)))")
+            .unwrap();
+        let second = PTBFormat::Simple
+            .string_to_tree("(S (NP (PRP they)) (VP (VBP do) (ADVP (RB loudly))))")
+            .unwrap();
+        let joined = Tree::join(vec![first.clone(), second.clone()], "DOC").unwrap();
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&joined).unwrap(),
+            "(DOC (S (NP (NN dogs)) (VP (VBP bark))) (S (NP (PRP they)) (VP (VBP do) (ADVP (RB \
+             loudly)))))"
+        );
+        assert_eq!(joined.n_terminals(), 5);
+        assert_eq!(joined.projectivity(), Projectivity::Projective);
+        assert_eq!(joined.nth_terminal(2).unwrap().form(), "they");
+        let sentences = joined.children(joined.root()).collect::<Vec<_>>();
+        let mut spans = sentences
+            .iter()
+            .map(|&s| joined[s].span().clone())
+            .collect::<Vec<_>>();
+        spans.sort();
+        assert_eq!(
+            spans,
+            vec![Span::new_continuous(0, 2), Span::new_continuous(2, 5)]
+        );
+        joined.validate().unwrap();
 
-    use petgraph::prelude::{NodeIndex, StableGraph};
+        let nonprojective = negra_to_tree(
+            "#BOS 1
+A\ta\tNN\t--\tHD\t501
+B\tb\tVVFIN\t--\tHD\t500
+C\tc\tNN\t--\t--\t501
+#500\t--\tS\t--\t--\t0
+#501\t--\tNP\t--\tSB\t500
+#EOS 1
+",
+        )
+        .unwrap();
+        let joined = Tree::join(vec![first, nonprojective], "DOC").unwrap();
+        assert_eq!(joined.projectivity(), Projectivity::Nonprojective);
+        assert_eq!(joined.n_terminals(), 5);
+        let np = joined
+            .nonterminals()
+            .find(|&nt| joined[nt].span().discontinuous().is_some())
+            .unwrap();
+        assert_eq!(joined[np].label(), "NP");
+        assert_eq!(
+            joined[np].span().into_iter().collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        joined.validate().unwrap();
 
-    use crate::util::LabelSet;
-    use crate::{Edge, Node, NonTerminal, Projectivity, Span, Terminal, Tree};
+        assert!(Tree::join(Vec::new(), "DOC").is_err());
+    }
 
     #[test]
-    fn reset_spans() {
-        let mut tree = some_tree();
-        let nt_indices = tree.nonterminals().collect::<Vec<_>>();
-        for nt in nt_indices {
-            tree[nt]
-                .nonterminal_mut()
-                .unwrap()
-                .set_span(Span::from_vec(vec![0, 2]).unwrap());
-        }
-        tree.reset_nt_spans();
-        assert_eq!(tree, some_tree());
+    fn map_labels() {
+        let mut tree = PTBFormat::Simple
+            .string_to_tree("(s (np (dt the) (NN dog)) (VP (vbz barks)))")
+            .unwrap();
+        tree.map_labels(|node| {
+            if node.is_terminal() {
+                Some(node.label().to_uppercase())
+            } else if node.label() == "s" {
+                Some("ROOT".to_owned())
+            } else {
+                None
+            }
+        });
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&tree).unwrap(),
+            "(ROOT (np (DT the) (NN dog)) (VP (VBZ barks)))"
+        );
+    }
 
+    #[test]
+    fn retain_features() {
         let mut tree = some_tree();
-        let term_indices = tree.terminals().collect::<Vec<_>>();
-        for (idx, term) in term_indices.into_iter().enumerate() {
-            tree[term].set_span(idx + 1).unwrap();
-        }
-        tree.compact_terminal_spans().unwrap();
-        assert_eq!(tree, some_tree());
+        let t1 = NodeIndex::new(2);
+        tree[t1].set_features(Some(Features::from("case:nom|number:sg|gender:masc")));
+        let first = NodeIndex::new(1);
+        tree[first].set_features(Some(Features::from("case:nom|node_annotation:x")));
+        let keys = LabelSet::Positive(
+            vec!["case".to_owned(), "number".to_owned()]
+                .into_iter()
+                .collect(),
+        );
+        tree.retain_features(&keys);
+        assert_eq!(
+            tree[t1].features(),
+            Some(&Features::from("case:nom|number:sg"))
+        );
+        assert_eq!(tree[first].features(), Some(&Features::from("case:nom")));
+        assert_eq!(tree[tree.root()].features(), None);
+
+        let keys = LabelSet::Negative(vec!["case".to_owned()].into_iter().collect());
+        tree.retain_features(&keys);
+        assert_eq!(tree[t1].features(), Some(&Features::from("number:sg")));
+        assert_eq!(tree[first].features(), Some(&Features::new()));
     }
 
     #[test]
-    fn project_node_labels() {
+    fn span_matches_yield() {
+        let mut tree = some_tree();
+        assert!(tree.nonterminals().all(|nt| tree.span_matches_yield(nt)));
+        tree.validate().unwrap();
+
+        // attach t3 to FIRST without updating spans
+        let first = NodeIndex::new(1);
+        let t3 = NodeIndex::new(4);
+        let (_, edge) = tree.parent(t3).unwrap();
+        tree.graph_mut().remove_edge(edge);
+        tree.graph_mut().add_edge(first, t3, Edge::default());
+        assert!(!tree.span_matches_yield(first));
+        assert!(tree.span_matches_yield(tree.root()));
+        assert!(tree.span_matches_yield(t3));
+        assert!(tree.validate().is_err());
+
+        tree[first]
+            .nonterminal_mut()
+            .unwrap()
+            .set_span(Span::new_continuous(0, 3));
+        assert!(tree.span_matches_yield(first));
+        tree.validate().unwrap();
+
+        // discontinuous spans are compared including skipped indices
         let mut g = StableGraph::new();
-        let root = NonTerminal::new("ROOT", Span::new_continuous(0, 6));
+        let root = NonTerminal::new("ROOT", Span::new_continuous(0, 3));
         let first = NonTerminal::new("FIRST", Span::from_vec(vec![0, 2]).unwrap());
-        let term1 = Terminal::new("t1", "TERM1", 0);
-        let term2 = Terminal::new("t2", "TERM1", 1);
-        let term3 = Terminal::new("t3", "TERM3", 2);
-        let second = NonTerminal::new("SECOND", 3);
-        let term4 = Terminal::new("t4", "TERM4", 4);
-        let term5 = Terminal::new("t5", "TERM5", 5);
         let root_idx = g.add_node(Node::NonTerminal(root));
         let first_idx = g.add_node(Node::NonTerminal(first));
-        let term1_idx = g.add_node(Node::Terminal(term1));
-        let term2_idx = g.add_node(Node::Terminal(term2));
         g.add_edge(root_idx, first_idx, Edge::default());
-        g.add_edge(first_idx, term1_idx, Edge::default());
-        g.add_edge(root_idx, term2_idx, Edge::default());
-        let term3_idx = g.add_node(Node::Terminal(term3));
-        g.add_edge(first_idx, term3_idx, Edge::default());
-        let second_idx = g.add_node(Node::NonTerminal(second));
-        g.add_edge(root_idx, second_idx, Edge::default());
-        let term4_idx = g.add_node(Node::Terminal(term4));
-        g.add_edge(second_idx, term4_idx, Edge::default());
-        let term5_idx = g.add_node(Node::Terminal(term5));
-        g.add_edge(root_idx, term5_idx, Edge::default());
+        for idx in 0..3 {
+            let term = g.add_node(Node::Terminal(Terminal::new("t", "T", idx)));
+            let parent = if idx == 1 { root_idx } else { first_idx };
+            g.add_edge(parent, term, Edge::default());
+        }
+        let mut tree = Tree::new(g, 3, root_idx, Projectivity::Nonprojective);
+        assert!(tree.span_matches_yield(first_idx));
+        tree.validate().unwrap();
+        tree[first_idx]
+            .nonterminal_mut()
+            .unwrap()
+            .set_span(Span::new_continuous(0, 3));
+        assert!(!tree.span_matches_yield(first_idx));
+    }
 
-        let tree = Tree::new(g, 5, root_idx, Projectivity::Nonprojective);
-        let mut tags = HashSet::new();
-        tags.insert("FIRST".into());
-        let indices = tree.project_tag_set(&LabelSet::Positive(tags));
-        let target = vec!["FIRST", "ROOT", "FIRST", "ROOT", "ROOT"];
-        assert_eq!(indices, target)
+    #[test]
+    fn lemmas() {
+        let mut tree = some_tree();
+        let t1 = NodeIndex::new(2);
+        let t2 = NodeIndex::new(3);
+        tree[t1].terminal_mut().unwrap().set_lemma(Some("Lemma"));
+        tree.normalize_lemmas(str::to_lowercase);
+        assert_eq!(tree[t1].terminal().unwrap().lemma(), Some("lemma"));
+        assert_eq!(tree[t2].terminal().unwrap().lemma(), None);
+
+        tree.copy_form_to_lemma();
+        for terminal in tree.terminals() {
+            let terminal = tree[terminal].terminal().unwrap();
+            assert_eq!(terminal.lemma(), Some(terminal.form()));
+        }
     }
 
     #[test]
-    fn project_node_ids() {
+    fn gap_degree() {
+        let tree = some_tree();
+        assert!(tree.nonprojective_nodes().is_empty());
+        assert_eq!(tree.gap_degree(), 0);
+
         let mut g = StableGraph::new();
         let root = NonTerminal::new("ROOT", Span::new_continuous(0, 5));
-        let first = NonTerminal::new("L", Span::from_vec(vec![0, 2]).unwrap());
-        let term1 = Terminal::new("t1", "TERM1", 0);
-        let term2 = Terminal::new("t2", "TERM1", 1);
-        let term3 = Terminal::new("t3", "TERM3", 2);
-        let second = NonTerminal::new("L", Span::new_continuous(3, 4));
-        let term4 = Terminal::new("t4", "TERM4", 3);
-        let term5 = Terminal::new("t5", "TERM5", 4);
+        let first = NonTerminal::new("FIRST", Span::from_vec(vec![0, 2, 4]).unwrap());
+        let second = NonTerminal::new("SECOND", Span::from_vec(vec![1, 3]).unwrap());
         let root_idx = g.add_node(Node::NonTerminal(root));
         let first_idx = g.add_node(Node::NonTerminal(first));
-        let term1_idx = g.add_node(Node::Terminal(term1));
-        let term2_idx = g.add_node(Node::Terminal(term2));
-        g.add_edge(root_idx, first_idx, Edge::default());
-        g.add_edge(first_idx, term1_idx, Edge::default());
-        g.add_edge(root_idx, term2_idx, Edge::default());
-        let term3_idx = g.add_node(Node::Terminal(term3));
-        g.add_edge(first_idx, term3_idx, Edge::default());
         let second_idx = g.add_node(Node::NonTerminal(second));
+        g.add_edge(root_idx, first_idx, Edge::default());
         g.add_edge(root_idx, second_idx, Edge::default());
-        let term4_idx = g.add_node(Node::Terminal(term4));
-        g.add_edge(second_idx, term4_idx, Edge::default());
-        let term5_idx = g.add_node(Node::Terminal(term5));
-        g.add_edge(root_idx, term5_idx, Edge::default());
-
+        for idx in 0..5 {
+            let term = Terminal::new(format!("t{}", idx), "TERM", idx);
+            let term_idx = g.add_node(Node::Terminal(term));
+            let parent = if idx % 2 == 0 { first_idx } else { second_idx };
+            g.add_edge(parent, term_idx, Edge::default());
+        }
         let tree = Tree::new(g, 5, root_idx, Projectivity::Nonprojective);
-        let mut tags = HashSet::new();
-        tags.insert("L".into());
-        let indices = tree.project_ids(&LabelSet::Positive(tags));
-        // 0 is first "L", 1 is ROOT and 2 is second "L"
-        let target = vec![0, 1, 0, 2, 1];
-        assert_eq!(indices, target)
+
+        let mut nonprojective = tree.nonprojective_nodes();
+        nonprojective.sort();
+        assert_eq!(nonprojective, vec![first_idx, second_idx]);
+        assert_eq!(tree.gap_degree(), 2);
+
+        let core = tree.projective_core();
+        assert_eq!(core.projectivity(), Projectivity::Projective);
+        assert_eq!(core.compute_projectivity(), Projectivity::Projective);
+        assert_eq!(core.metadata()["removed_terminals"], "1,3");
+        assert_eq!(core.n_terminals(), 3);
+        assert!(core.covers_contiguous(3));
+        core.validate().unwrap();
+        assert_eq!(
+            core.to_string_with_indices(),
+            "(ROOT (FIRST (TERM t0_0) (TERM t2_1) (TERM t4_2)))"
+        );
+
+        let input = std::fs::read_to_string("testdata/long_single.negra").unwrap();
+        let long = negra_to_tree(&input).unwrap();
+        let core = long.projective_core();
+        assert_eq!(core.compute_projectivity(), Projectivity::Projective);
+        let n_removed = core.metadata()["removed_terminals"].split(',').count();
+        assert_eq!(core.n_terminals() + n_removed, long.n_terminals());
+        assert!(core.covers_contiguous(core.n_terminals()));
+        core.validate().unwrap();
+
+        let projective = some_tree();
+        assert_eq!(projective.projective_core(), projective);
+        assert!(projective.projective_core().metadata().is_empty());
+        assert_eq!(
+            tree.to_string_with_indices(),
+            "(ROOT (FIRST (TERM t0_0) (TERM t2_2) (TERM t4_4)) (SECOND (TERM t1_1) (TERM t3_3)))"
+        );
+    }
+
+    #[test]
+    fn nearest_ancestor() {
+        let tree = PTBFormat::Simple
+            .string_to_tree(
+                "(S (NP (PRP I)) (VP (VBD said) (SBAR (S (NP (PRP it)) (VP (VBD rained))))))",
+            )
+            .unwrap();
+        let clause = LabelSet::Positive(vec!["S".to_owned()].into_iter().collect());
+        let i = tree.terminal_at(0).unwrap();
+        assert_eq!(tree.nearest_ancestor(i, &clause), Some(tree.root()));
+        let it = tree.terminal_at(2).unwrap();
+        let embedded = tree.nearest_ancestor(it, &clause).unwrap();
+        assert_ne!(embedded, tree.root());
+        assert_eq!(tree[embedded].span(), &Span::new_continuous(2, 4));
+        assert_eq!(tree.nearest_ancestor(embedded, &clause), Some(tree.root()));
+
+        let frag = LabelSet::Positive(vec!["FRAG".to_owned()].into_iter().collect());
+        assert_eq!(tree.nearest_ancestor(it, &frag), None);
+        assert_eq!(tree.nearest_ancestor(tree.root(), &clause), None);
+    }
+
+    #[test]
+    fn lca_and_path() {
+        let tree = some_tree();
+        let root = tree.root();
+        let first = NodeIndex::new(1);
+        let t1 = NodeIndex::new(2);
+        let t2 = NodeIndex::new(3);
+        let t3 = NodeIndex::new(4);
+        let second = NodeIndex::new(5);
+        let t4 = NodeIndex::new(6);
+        assert_eq!(tree.lca(t1, t2), Some(first));
+        assert_eq!(tree.lca(t1, t4), Some(root));
+        assert_eq!(tree.lca(t1, first), Some(first));
+        assert_eq!(tree.lca(t3, t3), Some(t3));
+        assert_eq!(tree.lca(t1, NodeIndex::new(100)), None);
+
+        assert_eq!(tree.path_between(t1, t2), Some(vec![t1, first, t2]));
+        assert_eq!(
+            tree.path_between(t2, t4),
+            Some(vec![t2, first, root, second, t4])
+        );
+        assert_eq!(tree.path_between(t4, root), Some(vec![t4, second, root]));
+        assert_eq!(tree.path_between(root, t3), Some(vec![root, t3]));
+        assert_eq!(tree.path_between(t3, t3), Some(vec![t3]));
+        assert_eq!(tree.path_between(NodeIndex::new(100), t3), None);
+
+        assert_eq!(
+            tree.dependency_path_labels(t2, t4),
+            Some("TERM1↑FIRST↑ROOT↓SECOND↓TERM4".to_owned())
+        );
+        assert_eq!(
+            tree.dependency_path_labels(t4, root),
+            Some("TERM4↑SECOND↑ROOT".to_owned())
+        );
+        assert_eq!(
+            tree.dependency_path_labels(root, t3),
+            Some("ROOT↓TERM3".to_owned())
+        );
+        assert_eq!(
+            tree.dependency_path_labels(t3, t3),
+            Some("TERM3".to_owned())
+        );
+        assert_eq!(tree.dependency_path_labels(t3, NodeIndex::new(100)), None);
     }
 
     #[test]
@@ -459,7 +3175,6 @@ mod tests {
         // NodeIndex(1) is Node::Inner("FIRST" ..)
         let siblings = tree
             .siblings(NodeIndex::new(1))
-            .into_iter()
             .map(|sibling| match tree.graph()[sibling] {
                 Node::NonTerminal(ref nt) => nt.label().to_string(),
                 Node::Terminal(ref t) => t.form().to_string(),
@@ -513,33 +3228,46 @@ mod tests {
         assert_eq!(some_tree, other_tree);
     }
 
+    // tree whose terminal indices have a gap after removing a terminal without reindexing.
+    fn gapped_tree() -> Tree {
+        let mut tree = PTBFormat::Simple
+            .string_to_tree("(S (NP (DT the) (NN dog)) (, ,) (VP (VBZ barks)))")
+            .unwrap();
+        let comma = tree.terminal_at(2).unwrap();
+        tree.graph_mut().remove_node(comma);
+        tree.index_terminals();
+        tree
+    }
+
     fn some_tree() -> Tree {
         //(ROOT (FIRST (TERM1 t1) (TERM2 t2)) (TERM3 t3) (SECOND (TERM4 t4)) (TERM5 t5))";
-        let mut g = StableGraph::new();
-        let root = NonTerminal::new("ROOT", Span::new_continuous(0, 5));
-        let first = NonTerminal::new("FIRST", Span::new_continuous(0, 2));
-        let term1 = Terminal::new("t1", "TERM1", 0);
-        let term2 = Terminal::new("t2", "TERM1", 1);
-        let term3 = Terminal::new("t3", "TERM3", 2);
-        let second = NonTerminal::new("SECOND", Span::new_continuous(3, 4));
-        let term4 = Terminal::new("t4", "TERM4", 3);
-        let term5 = Terminal::new("t5", "TERM5", 4);
-        let root_idx = g.add_node(Node::NonTerminal(root));
-        let first_idx = g.add_node(Node::NonTerminal(first));
-        let term1_idx = g.add_node(Node::Terminal(term1));
-        let term2_idx = g.add_node(Node::Terminal(term2));
-        g.add_edge(root_idx, first_idx, Edge::default());
-        g.add_edge(first_idx, term1_idx, Edge::default());
-        g.add_edge(first_idx, term2_idx, Edge::default());
-        let term3_idx = g.add_node(Node::Terminal(term3));
-        g.add_edge(root_idx, term3_idx, Edge::default());
-        let second_idx = g.add_node(Node::NonTerminal(second));
-        g.add_edge(root_idx, second_idx, Edge::default());
-        let term4_idx = g.add_node(Node::Terminal(term4));
-        g.add_edge(second_idx, term4_idx, Edge::default());
-        let term5_idx = g.add_node(Node::Terminal(term5));
-        g.add_edge(root_idx, term5_idx, Edge::default());
+        Tree::from_nested((
+            "ROOT",
+            vec![
+                ("FIRST", vec![leaf("t1", "TERM1"), leaf("t2", "TERM1")]).into(),
+                leaf("t3", "TERM3"),
+                ("SECOND", vec![leaf("t4", "TERM4")]).into(),
+                leaf("t5", "TERM5"),
+            ],
+        ))
+        .unwrap()
+    }
 
-        Tree::new(g, 5, root_idx, Projectivity::Projective)
+    fn nonprojective_some_tree() -> Tree {
+        // (ROOT (FIRST (TERM1 t1) (TERM3 t3)) (TERM2 t2) (SECOND (TERM4 t4)) (TERM5 t5))
+        let mut tree = some_tree();
+        let first = NodeIndex::new(1);
+        let root = tree.root();
+        for (node, parent) in [(NodeIndex::new(3), root), (NodeIndex::new(4), first)] {
+            let (_, edge) = tree.parent(node).unwrap();
+            tree.graph_mut().remove_edge(edge);
+            tree.graph_mut().add_edge(parent, node, Edge::default());
+        }
+        tree[first]
+            .nonterminal_mut()
+            .unwrap()
+            .set_span(Span::from_vec(vec![0, 2]).unwrap());
+        tree.set_projectivity(Projectivity::Nonprojective);
+        tree
     }
 }
@@ -1,8 +1,18 @@
+use std::collections::HashSet;
+
 use failure::Error;
-use petgraph::prelude::DfsPostOrder;
+use petgraph::prelude::{Bfs, DfsPostOrder, Direction, EdgeRef, NodeIndex};
+
+use crate::head::HEAD_FEATURE_KEY;
+use crate::io::{
+    encode_empty_element, PTBFormat, PTBFormatter, EMPTY_ELEMENTS_FEATURE_KEY,
+    NODE_ANNOTATION_FEATURE_KEY,
+};
+use crate::util::{Climber, FilterMode, LabelSet};
+use crate::{Edge, HeadRules, LumberjackError, Node, NonTerminal, Projectivity, Span, Tree};
 
-use crate::util::{Climber, LabelSet};
-use crate::{Edge, Node, NonTerminal, Projectivity, Span, Tree};
+/// Part-of-speech tag of empty elements.
+static EMPTY_ELEMENT_POS: &str = "-NONE-";
 
 /// Trait to annotate Part of Speech tags.
 ///
@@ -21,18 +31,26 @@ impl AnnotatePOS for Tree {
         S: Into<String>,
     {
         let terminals = self.terminals().collect::<Vec<_>>();
+        let n_terminals = terminals.len();
         let mut pos_iter = pos_iter.into_iter();
-        for terminal in terminals {
+        for (n_tags, terminal) in terminals.into_iter().enumerate() {
             if let Some(pos) = pos_iter.next() {
                 self[terminal].terminal_mut().unwrap().set_label(pos);
             } else {
-                return Err(format_err!("Not enough POS tags were provided"));
+                return Err(LumberjackError::PosCountMismatch {
+                    n_tags,
+                    n_terminals,
+                }
+                .into());
             }
         }
-        if pos_iter.next().is_some() {
-            return Err(format_err!(
-                "Number of POS tags is greater than number of terminals."
-            ));
+        let n_remaining = pos_iter.count();
+        if n_remaining != 0 {
+            return Err(LumberjackError::PosCountMismatch {
+                n_tags: n_terminals + n_remaining,
+                n_terminals,
+            }
+            .into());
         }
         Ok(())
     }
@@ -47,6 +65,43 @@ pub trait TreeOps {
     /// Returns `Error` if the tree contains `Terminal`s without a parent node.
     fn annotate_parent_tag(&mut self, feature_name: &str) -> Result<(), Error>;
 
+    /// Inherit feature `key` from ancestors.
+    ///
+    /// Each node without feature `key` receives the value of its nearest ancestor with feature
+    /// `key`, e.g. to propagate a clause type annotated on a clause node to all nodes in the
+    /// clause. Nodes that already have the feature keep their value and pass it on to their
+    /// descendants. Nodes without an annotated ancestor are left untouched.
+    ///
+    /// Returns `Error` if no node in the tree has feature `key`, e.g. because of a misspelled key.
+    fn inherit_feature(&mut self, key: &str) -> Result<(), Error>;
+
+    /// Annotate the labels of adjacent siblings as features.
+    ///
+    /// Annotates each terminal with the labels of its immediate left and right siblings in
+    /// surface order under the feature names `left_feature` and `right_feature`. If a terminal
+    /// is the first or last child of its parent, `"NONE"` is annotated.
+    ///
+    /// Returns `Error` if the tree contains `Terminal`s without a parent node.
+    fn annotate_siblings(&mut self, left_feature: &str, right_feature: &str) -> Result<(), Error>;
+
+    /// Annotate head children.
+    ///
+    /// The head child of each `NonTerminal` is selected through `rules` and annotated with the
+    /// feature `head:true`.
+    ///
+    /// Returns `Error` if the tree contains `NonTerminal`s without children.
+    fn annotate_head(&mut self, rules: &HeadRules) -> Result<(), Error>;
+
+    /// Annotate the word shape of terminals as a feature.
+    ///
+    /// The shape of a form replaces each uppercase character with `X`, each other alphabetic
+    /// character with `x` and each numeric character with `d`, all other characters are kept.
+    /// E.g. `Haus` has shape `Xxxx`, `1984` has shape `dddd` and `U-2` has shape `X-d`.
+    /// Characters are classified by their Unicode properties.
+    ///
+    /// Existing values of `feature` are overwritten.
+    fn annotate_word_shape(&mut self, feature: &str);
+
     /// Insert an intermediate node above terminals.
     ///
     /// If a terminal is not dominated by a node with label matched by `tag_set` a new non-terminal
@@ -64,8 +119,145 @@ pub trait TreeOps {
     ///
     /// The root node will never be removed. Root node is determined by the `tree::is_root()`
     /// method. Detached material is re-attached above the removed node.
+    ///
+    /// Removing nodes can turn a nonprojective tree projective, the projectivity of
+    /// nonprojective trees is reset to `Projectivity::Unknown`.
     fn filter_nonterminals(&mut self, tag_set: &LabelSet) -> Result<(), Error>;
 
+    /// Remove non-terminals according to `tag_set` and `mode`.
+    ///
+    /// With `FilterMode::Keep`, this is equivalent to `TreeOps::filter_nonterminals`. With
+    /// `FilterMode::Remove`, non-terminals matched by `tag_set` are removed and all others are
+    /// kept, e.g. removing `PRN` deletes parenthetical nodes while keeping their content.
+    ///
+    /// The root node is never removed, regardless of `mode`. Detached material is re-attached
+    /// above the removed node.
+    fn filter_nonterminals_mode(
+        &mut self,
+        tag_set: &LabelSet,
+        mode: FilterMode,
+    ) -> Result<(), Error>;
+
+    /// Remove a single non-terminal.
+    ///
+    /// The children of `node` are attached to the parent of `node`, keeping the `Edge` that
+    /// connected them to `node`. The span of the parent is not affected.
+    ///
+    /// Removing a node can turn a nonprojective tree projective, the projectivity of
+    /// nonprojective trees is reset to `Projectivity::Unknown`.
+    ///
+    /// Returns `Error` if `node` is the root or a `Terminal`.
+    fn splice_out(&mut self, node: NodeIndex) -> Result<(), Error>;
+
+    /// Remove nodes for which `keep` returns `false`.
+    ///
+    /// Generalizes `TreeOps::filter_nonterminals` to arbitrary conditions on `Terminal`s and
+    /// `NonTerminal`s, e.g. removing nonterminals with a specific feature:
+    ///
+    /// * Removed `NonTerminal`s are spliced out as in `TreeOps::splice_out`, their children are
+    ///   re-attached to their parent.
+    /// * Removed `Terminal`s are deleted. `NonTerminal`s left without children are deleted as
+    ///   well.
+    ///
+    /// The root node is never removed. After removal, terminals are reindexed to cover
+    /// `0..n_terminals` in their original order and the spans of all `NonTerminal`s are
+    /// recomputed. The projectivity of nonprojective trees is reset to
+    /// `Projectivity::Unknown`.
+    ///
+    /// Returns `Error` if `keep` would remove all terminals.
+    fn prune<F>(&mut self, keep: F) -> Result<(), Error>
+    where
+        F: Fn(&Node) -> bool;
+
+    /// Remove empty elements.
+    ///
+    /// Removes all `Terminal`s with part-of-speech tag `-NONE-` and all `NonTerminal`s that
+    /// only dominate such terminals, e.g. `(S (NP-SBJ (-NONE- *)) (VP (VB go)))` becomes
+    /// `(S (VP (VB go)))`. Terminals are reindexed afterwards.
+    ///
+    /// The removed subtrees are stored in their PTB representation under the feature
+    /// `empty_elements` of their parent together with their position among the remaining
+    /// children. Labels, function tags and coindices are preserved, the empty elements can be
+    /// reinserted through `Tree::to_penn_treebank_with_empties` or
+    /// `PTBFormatter::empty_elements`. Whitespace, brackets and feature delimiters in the stored
+    /// representation are percent-encoded, e.g. `0:%28-NONE-%20*T*-1%29`, so the feature can be
+    /// written and read like other features.
+    ///
+    /// Returns `Error` if the tree consists of empty elements only.
+    fn remove_traces(&mut self) -> Result<(), Error>;
+
+    /// Renumber coindices densely.
+    ///
+    /// Coindices are trailing integers separated by `delim` from the rest of a `NonTerminal`'s
+    /// label or of the form of an empty element, e.g. `NP-SBJ-3` and `*T*-3` with
+    /// `delim == '-'`. For `NonTerminal`s read from PTB, the label is considered together with
+    /// its node annotation, e.g. `NP-SBJ-3` is read as label `NP` and annotation `-SBJ-3`. The
+    /// distinct indices are mapped to `1..=k` in ascending order, nodes that shared an index
+    /// before share an index afterwards. Forms of other `Terminal`s are left untouched so that
+    /// e.g. `1990-91` is not mistaken for a coindexed form.
+    ///
+    /// Meant to be applied after removing coindexed nodes, e.g. through
+    /// `TreeOps::remove_traces`.
+    fn renumber_coindices(&mut self, delim: char);
+
+    /// Merge nonterminals with their parent if they share the same label.
+    ///
+    /// Nonterminals with the same label as their parent are removed through
+    /// `TreeOps::splice_out`, e.g. `(NP (NP x))` becomes `(NP x)`. If `delim` is given, the
+    /// label of the removed node is appended to the label of the remaining node, delimited by
+    /// `delim`, e.g. assuming `delim == Some("_")`, `(NP (NP x))` becomes `(NP_NP x)`.
+    ///
+    /// Labels are compared before any node is merged. Edge labels and features of the removed
+    /// nodes are lost.
+    fn merge_same_label(&mut self, delim: Option<&str>) -> Result<(), Error>;
+
+    /// Move function tags from node labels to edge labels.
+    ///
+    /// Labels of `NonTerminal`s are split on the first `"-"`, the part before is kept as node
+    /// label and the function is moved to the label of the node's incoming edge, e.g. `NP-SBJ`
+    /// becomes a node `NP` attached through an edge labeled `SBJ`. This matches TueBa's
+    /// separation of node labels and grammatical functions.
+    ///
+    /// Labels starting with `"-"`, e.g. `-NONE-`, are left untouched. If the incoming edge
+    /// already has a label, the edge label takes precedence and the function is discarded. The
+    /// function of the root is discarded since the root has no incoming edge.
+    fn strip_functions_to_edges(&mut self);
+
+    /// Limit the number of children per node.
+    ///
+    /// For each `NonTerminal` with more than `max` children, all but the first `max - 1`
+    /// children are grouped under a new `NonTerminal`. This is repeated for the new node until
+    /// no node has more than `max` children. The inserted nodes are labeled with the label of
+    /// the original node followed by `delim`, e.g. `NP|` assuming `delim == "|"`.
+    ///
+    /// Nodes with at most `max` children are left untouched.
+    ///
+    /// Returns `Error` if `max < 2`.
+    fn limit_arity(&mut self, max: usize, delim: &str) -> Result<(), Error>;
+
+    /// Flatten the tree.
+    ///
+    /// Removes all `NonTerminal`s except for the root and attaches all `Terminal`s directly to
+    /// the root, e.g. `(S (NP (DT the) (NN dog)) (VP (VBZ barks)))` is flattened into
+    /// `(S (DT the) (NN dog) (VBZ barks))`. If `root_label` is given, the root is relabeled.
+    ///
+    /// Terminals keep their part-of-speech tags, indices and incoming edge labels. The tree is
+    /// projective afterwards. Trees consisting of a single `Terminal` are left unchanged.
+    fn flatten(&mut self, root_label: Option<&str>);
+
+    /// Split discontinuous `NonTerminal`s into continuous pieces.
+    ///
+    /// Each `NonTerminal` covering a discontinuous span is replaced by one `NonTerminal` per run
+    /// of its span, see `Span::runs`. The pieces are attached to the parent of the original
+    /// node with its edge label and get its label and features, the children are distributed
+    /// over the pieces according to their spans. If `label_suffix` is given, it is appended to
+    /// the labels of the pieces, e.g. `NP*` for `label_suffix == Some("*")`.
+    ///
+    /// Nodes are split bottom-up, every child thus falls into a single run of its parent. Unlike
+    /// `Projectivize::projectivize`, no material is re-attached. The tree is projective
+    /// afterwards.
+    fn split_discontinuous(&mut self, label_suffix: Option<&str>);
+
     /// Collapse unary chains.
     ///
     /// Collapses unary chains into the node label of the lowest node in the chain, delimiting each
@@ -77,6 +269,14 @@ pub trait TreeOps {
     /// nonterminals are lost.
     fn collapse_unary_chains(&mut self, delim: &str) -> Result<(), Error>;
 
+    /// Collapse unary chains below `root`.
+    ///
+    /// Like `TreeOps::collapse_unary_chains`, but restricted to the descendants of `root`. `root`
+    /// itself is never collapsed, e.g. assuming `delim == "_"` and `root` being `NP`,
+    /// `(S (NP (UC (T t))))` is collapsed into `(S (NP (T t)))` with the unary chain `UC`
+    /// stored on `T`.
+    fn collapse_unary_below(&mut self, root: NodeIndex, delim: &str) -> Result<(), Error>;
+
     /// Restore unary chains.
     ///
     /// Inverse of `collapse_unary_chains`. Expands the unary chains collapsed into node labels.
@@ -89,9 +289,11 @@ impl TreeOps for Tree {
     fn annotate_parent_tag(&mut self, feature_name: &str) -> Result<(), Error> {
         let terminals = self.terminals().collect::<Vec<_>>();
         for terminal in terminals.into_iter() {
-            let (parent, _) = self
-                .parent(terminal)
-                .ok_or_else(|| format_err!("Terminal without parent:\n{}", self[terminal]))?;
+            let (parent, _) =
+                self.parent(terminal)
+                    .ok_or_else(|| LumberjackError::TerminalWithoutParent {
+                        terminal: self[terminal].to_string(),
+                    })?;
 
             let label = self[parent].label().to_owned();
             self[terminal]
@@ -101,18 +303,114 @@ impl TreeOps for Tree {
         Ok(())
     }
 
+    fn inherit_feature(&mut self, key: &str) -> Result<(), Error> {
+        let has_feature = |node: &Node| {
+            node.features()
+                .is_some_and(|features| features.inner().contains_key(key))
+        };
+        if !self
+            .graph()
+            .node_indices()
+            .any(|node| has_feature(&self[node]))
+        {
+            return Err(format_err!("No node with feature: {}", key));
+        }
+        let mut inherited = Vec::new();
+        for node in self.graph().node_indices() {
+            if has_feature(&self[node]) {
+                continue;
+            }
+            let mut climber = Climber::new(node);
+            while let Some(ancestor) = climber.next(self) {
+                if has_feature(&self[ancestor]) {
+                    let value = self[ancestor].features().unwrap().inner()[key].clone();
+                    inherited.push((node, value));
+                    break;
+                }
+            }
+        }
+
+        for (node, value) in inherited {
+            self[node].features_mut().insert(key, value);
+        }
+        Ok(())
+    }
+
+    fn annotate_siblings(&mut self, left_feature: &str, right_feature: &str) -> Result<(), Error> {
+        let terminals = self.terminals().collect::<Vec<_>>();
+        for terminal in terminals.into_iter() {
+            let (parent, _) =
+                self.parent(terminal)
+                    .ok_or_else(|| LumberjackError::TerminalWithoutParent {
+                        terminal: self[terminal].to_string(),
+                    })?;
+
+            let mut children = self.children(parent).collect::<Vec<_>>();
+            children.sort_by(|child_1, child_2| self[*child_1].span().cmp(self[*child_2].span()));
+            // safe to unwrap, terminal is a child of its parent
+            let position = children
+                .iter()
+                .position(|&child| child == terminal)
+                .unwrap();
+            let left = position
+                .checked_sub(1)
+                .map(|left| self[children[left]].label())
+                .unwrap_or("NONE")
+                .to_owned();
+            let right = children
+                .get(position + 1)
+                .map(|&right| self[right].label())
+                .unwrap_or("NONE")
+                .to_owned();
+
+            let features = self[terminal].features_mut();
+            features.insert(left_feature, Some(left));
+            features.insert(right_feature, Some(right));
+        }
+        Ok(())
+    }
+
+    fn annotate_head(&mut self, rules: &HeadRules) -> Result<(), Error> {
+        let nonterminals = self.nonterminals().collect::<Vec<_>>();
+        for nonterminal in nonterminals {
+            let head = rules.head_child(self, nonterminal).ok_or_else(|| {
+                format_err!("NonTerminal without children: {}", self[nonterminal])
+            })?;
+            self[head]
+                .features_mut()
+                .insert(HEAD_FEATURE_KEY, Some("true"));
+        }
+        Ok(())
+    }
+
+    fn annotate_word_shape(&mut self, feature: &str) {
+        let terminals = (0..self.n_terminals())
+            .filter_map(|idx| self.terminal_at(idx))
+            .collect::<Vec<_>>();
+        for terminal in terminals {
+            let shape = word_shape(self[terminal].terminal().unwrap().form());
+            self[terminal].features_mut().insert(feature, Some(shape));
+        }
+    }
+
     fn insert_intermediate(
         &mut self,
         tag_set: &LabelSet,
         insertion_label: &str,
     ) -> Result<(), Error> {
-        let terminals = self.terminals().collect::<Vec<_>>();
+        // runs are determined in surface order, the order of the terminals in the graph is
+        // arbitrary.
+        let mut terminals = self.terminals().collect::<Vec<_>>();
+        terminals.sort_by_key(|&terminal| self[terminal].span().lower());
         let mut prev_attachment = None;
 
-        for (position, terminal) in terminals.into_iter().enumerate() {
-            let (parent, edge_id) = self
-                .parent(terminal)
-                .ok_or_else(|| format_err!("Terminal without parent:\n{}", self[terminal]))?;
+        for terminal in terminals {
+            let position = self[terminal].span().lower();
+            let (parent, edge_id) =
+                self.parent(terminal)
+                    .ok_or_else(|| LumberjackError::TerminalWithoutParent {
+                        terminal: self[terminal].to_string(),
+                    })?;
 
             if tag_set.matches(self[parent].label()) {
                 continue;
@@ -120,9 +418,13 @@ impl TreeOps for Tree {
 
             let weight = self.graph_mut().remove_edge(edge_id).unwrap();
             if let Some((prev_position, prev_insert)) = prev_attachment {
-                if prev_position == position - 1 && self.parent(prev_insert).unwrap().0 == parent {
+                if prev_position + 1 == position && self.parent(prev_insert).unwrap().0 == parent {
                     self.graph_mut().add_edge(prev_insert, terminal, weight);
-                    self[prev_insert].extend_span()?;
+                    let coverage = self
+                        .children(prev_insert)
+                        .map(|child| self[child].span().lower())
+                        .collect::<Vec<_>>();
+                    self[prev_insert].set_span(Span::from_vec(coverage)?)?;
                     prev_attachment = Some((position, prev_insert));
                     continue;
                 }
@@ -140,6 +442,15 @@ impl TreeOps for Tree {
     }
 
     fn filter_nonterminals(&mut self, tag_set: &LabelSet) -> Result<(), Error> {
+        self.filter_nonterminals_mode(tag_set, FilterMode::Keep)
+    }
+
+    fn filter_nonterminals_mode(
+        &mut self,
+        tag_set: &LabelSet,
+        mode: FilterMode,
+    ) -> Result<(), Error> {
+        let retain = |label: &str| tag_set.matches(label) == (mode == FilterMode::Keep);
         // divide indices into keep- and delete-list, root is excluded as we don't want to break the
         // tree. Collecting is necessary because .node_indices() borrows from the graph
         let (keep, delete) = self
@@ -148,7 +459,7 @@ impl TreeOps for Tree {
             .filter(|node| *node != self.root())
             .fold((Vec::new(), Vec::new()), |(mut keep, mut delete), node| {
                 if let Node::NonTerminal(ref nt) = self[node] {
-                    if tag_set.matches(nt.label()) {
+                    if retain(nt.label()) {
                         keep.push(node)
                     } else {
                         delete.push(node)
@@ -163,17 +474,21 @@ impl TreeOps for Tree {
         for node in keep {
             // get id of the incoming edge of the node currently looking for re-attachment, since
             // EdgeReference borrows from graph only clone edge index
-            let (_, id) = self.parent(node).ok_or_else(|| {
-                format_err!("Non-root node without incoming edge: {}", self[node])
-            })?;
+            let (_, id) = self
+                .parent(node)
+                .ok_or_else(|| LumberjackError::NodeWithoutParent {
+                    node: self[node].to_string(),
+                })?;
 
             // climb up until field specified in tag_set or root is found
             let mut climber = Climber::new(node);
-            while let Some(parent_idx) = climber.next(&self) {
-                let parent = self[parent_idx]
-                    .nonterminal()
-                    .ok_or_else(|| format_err!("Terminal as parent: {}", self[parent_idx]))?;
-                if tag_set.matches(parent.label()) || parent_idx == self.root() {
+            while let Some(parent_idx) = climber.next(self) {
+                let parent = self[parent_idx].nonterminal().ok_or_else(|| {
+                    LumberjackError::TerminalAsParent {
+                        terminal: self[parent_idx].to_string(),
+                    }
+                })?;
+                if retain(parent.label()) || parent_idx == self.root() {
                     // safe to unwrap, id is guaranteed to be valid (line 138)
                     let weight = self.graph_mut().remove_edge(id).unwrap();
                     self.graph_mut().update_edge(parent_idx, node, weight);
@@ -184,63 +499,374 @@ impl TreeOps for Tree {
         for node in delete {
             self.graph_mut().remove_node(node);
         }
+        if self.projectivity() == Projectivity::Nonprojective {
+            self.set_projectivity(Projectivity::Unknown);
+        }
         Ok(())
     }
 
-    fn collapse_unary_chains(&mut self, delim: &str) -> Result<(), Error> {
-        let terminals = self.terminals().collect::<Vec<_>>();
+    fn splice_out(&mut self, node: NodeIndex) -> Result<(), Error> {
+        if node == self.root() {
+            return Err(format_err!("Can't splice out root node: {}", self[node]));
+        }
+        if self[node].is_terminal() {
+            return Err(format_err!("Can't splice out terminal: {}", self[node]));
+        }
+        let (parent, _) = self
+            .parent(node)
+            .ok_or_else(|| LumberjackError::NodeWithoutParent {
+                node: self[node].to_string(),
+            })?;
+
+        let children = self
+            .graph()
+            .edges_directed(node, Direction::Outgoing)
+            .map(|edge_ref| (edge_ref.target(), edge_ref.id()))
+            .collect::<Vec<_>>();
+        for (child, id) in children {
+            // safe to unwrap, id was just retrieved from the graph
+            let weight = self.graph_mut().remove_edge(id).unwrap();
+            self.graph_mut().add_edge(parent, child, weight);
+        }
+        self.graph_mut().remove_node(node);
+        if self.projectivity() == Projectivity::Nonprojective {
+            self.set_projectivity(Projectivity::Unknown);
+        }
+        Ok(())
+    }
+
+    fn prune<F>(&mut self, keep: F) -> Result<(), Error>
+    where
+        F: Fn(&Node) -> bool,
+    {
+        let root = self.root();
+        let (terminals, nonterminals): (Vec<_>, Vec<_>) = self
+            .graph()
+            .node_indices()
+            .filter(|&node| node != root && !keep(&self[node]))
+            .partition(|&node| self[node].is_terminal());
+        if terminals.len() == self.terminals().count() {
+            return Err(format_err!("Can't remove all terminals."));
+        }
+
+        for node in nonterminals {
+            self.splice_out(node)?;
+        }
+        if terminals.is_empty() {
+            return Ok(());
+        }
         for terminal in terminals {
-            let mut cur = terminal;
-            // tree of form (S (T t)) has 2 nodes, 1 terminal, S will be removed.
-            // node_count - n_terminals specifies number of removed nodes.
-            let mut del = Vec::with_capacity(self.graph().node_count() - self.n_terminals());
-            let mut climber = Climber::new(terminal);
-            let mut prev_span = self[terminal].span().clone();
-            let mut chain = Vec::new();
-
-            while let Some(node) = climber.next(self) {
-                if self[node].span() == &prev_span {
-                    // spans are equal in unary branches.
-                    del.push(node);
-                    match self[node].nonterminal() {
-                        Some(nt) => {
-                            chain.push(nt.label().to_owned());
+            self.graph_mut().remove_node(terminal);
+        }
+        // delete nonterminals without children bottom-up, the root keeps at least one terminal.
+        let mut dfs = DfsPostOrder::new(self.graph(), root);
+        while let Some(node) = dfs.next(self.graph()) {
+            if !self[node].is_terminal() && self.children(node).next().is_none() {
+                self.graph_mut().remove_node(node);
+            }
+        }
+        self.reindex_terminals()?;
+        if self.projectivity() == Projectivity::Nonprojective {
+            self.set_projectivity(Projectivity::Unknown);
+        }
+        Ok(())
+    }
+
+    fn remove_traces(&mut self) -> Result<(), Error> {
+        let root = self.root();
+        let mut empty = HashSet::new();
+        let mut dfs = DfsPostOrder::new(self.graph(), root);
+        while let Some(node) = dfs.next(self.graph()) {
+            let is_empty = match self[node] {
+                Node::Terminal(ref terminal) => terminal.label() == EMPTY_ELEMENT_POS,
+                Node::NonTerminal(_) => self.children(node).all(|child| empty.contains(&child)),
+            };
+            if is_empty {
+                empty.insert(node);
+            }
+        }
+        if empty.contains(&root) {
+            return Err(format_err!("Can't remove all terminals."));
+        }
+        if empty.is_empty() {
+            return Ok(());
+        }
+
+        let formatter = PTBFormatter::new(PTBFormat::PTB);
+        let parents = self
+            .nonterminals()
+            .filter(|&nt| !empty.contains(&nt))
+            .collect::<Vec<_>>();
+        for parent in parents {
+            let mut children = self.children(parent).collect::<Vec<_>>();
+            children.sort_by(|&c1, &c2| self[c1].span().cmp(self[c2].span()));
+            let mut position = 0;
+            let mut empty_elements = Vec::new();
+            for child in children {
+                if empty.contains(&child) {
+                    let (_, edge) = self.parent(child).unwrap();
+                    let subtree = formatter.format_sub_tree(self, child, self[edge].label());
+                    empty_elements.push(format!("{}:{}", position, encode_empty_element(&subtree)));
+                } else {
+                    position += 1;
+                }
+            }
+            if !empty_elements.is_empty() {
+                self[parent]
+                    .features_mut()
+                    .insert(EMPTY_ELEMENTS_FEATURE_KEY, Some(empty_elements.join(";")));
+            }
+        }
+
+        self.prune(|node| node.label() != EMPTY_ELEMENT_POS)
+    }
+
+    fn renumber_coindices(&mut self, delim: char) {
+        // PTB readers move everything from the first "-" of a label to the node annotation,
+        // coindices are therefore looked up in the label and in label plus annotation.
+        let coindexed = self
+            .graph()
+            .node_indices()
+            .filter_map(|node| {
+                let text = match self[node] {
+                    Node::NonTerminal(ref nt) => match nt
+                        .features()
+                        .and_then(|f| f.get_val(NODE_ANNOTATION_FEATURE_KEY))
+                    {
+                        Some(annotation) if annotation.starts_with('-') => {
+                            format!("{}{}", nt.label(), annotation)
+                        }
+                        Some(annotation) => format!("{}-{}", nt.label(), annotation),
+                        None => nt.label().to_owned(),
+                    },
+                    Node::Terminal(ref t) if t.label() == EMPTY_ELEMENT_POS => t.form().to_owned(),
+                    Node::Terminal(_) => return None,
+                };
+                split_coindex(&text, delim).map(|(_, index)| (node, index))
+            })
+            .collect::<Vec<_>>();
+
+        let mut indices = coindexed
+            .iter()
+            .map(|&(_, index)| index)
+            .collect::<Vec<_>>();
+        indices.sort();
+        indices.dedup();
+
+        for (node, index) in coindexed {
+            let new_index = indices.binary_search(&index).unwrap() + 1;
+            match self[node] {
+                Node::NonTerminal(ref mut nt) => {
+                    let annotation = nt
+                        .features()
+                        .and_then(|f| f.get_val(NODE_ANNOTATION_FEATURE_KEY))
+                        .map(ToOwned::to_owned);
+                    match annotation {
+                        Some(ref annotation) if split_coindex(annotation, delim).is_some() => {
+                            let annotation = renumbered(annotation, delim, new_index);
+                            nt.features_mut()
+                                .insert(NODE_ANNOTATION_FEATURE_KEY, Some(annotation));
+                        }
+                        Some(_) if delim == '-' => {
+                            nt.features_mut().insert(
+                                NODE_ANNOTATION_FEATURE_KEY,
+                                Some(format!("-{}", new_index)),
+                            );
+                        }
+                        _ => {
+                            let label = renumbered(nt.label(), delim, new_index);
+                            nt.set_label(label);
                         }
-                        None => return Err(format_err!("Terminal dominating NT.")),
                     }
-                } else if chain.is_empty() {
-                    // no chain and non-matching spans means current node is branching.
-                    prev_span = self[node].span().clone();
-                    cur = node;
-                } else {
-                    // non-matching spans and non-empty label means that a unary chain has ended
-                    self[cur]
-                        .features_mut()
-                        .insert("unary_chain", Some(chain.join(delim)));
-                    chain.clear();
-                    // add new node bridging the node-to-be-removed
-                    self.graph_mut().add_edge(node, cur, Edge::default());
-                    prev_span = self[node].span().clone();
-                    cur = node;
+                }
+                Node::Terminal(ref mut t) => {
+                    let form = renumbered(t.form(), delim, new_index);
+                    t.set_form(form);
                 }
             }
+        }
+    }
 
-            if !chain.is_empty() {
-                // empty label means, root is attached via unary chain.
-                self.set_root(cur);
-                self[cur]
-                    .features_mut()
-                    .insert("unary_chain", Some(chain.join(delim)));
+    fn merge_same_label(&mut self, delim: Option<&str>) -> Result<(), Error> {
+        let merge = self
+            .nonterminals()
+            .filter(|&nt| {
+                self.parent(nt)
+                    .map(|(parent, _)| self[parent].label() == self[nt].label())
+                    .unwrap_or(false)
+            })
+            .collect::<Vec<_>>();
+
+        for node in merge {
+            let (parent, _) =
+                self.parent(node)
+                    .ok_or_else(|| LumberjackError::NodeWithoutParent {
+                        node: self[node].to_string(),
+                    })?;
+            if let Some(delim) = delim {
+                let label = format!("{}{}{}", self[parent].label(), delim, self[node].label());
+                self[parent].nonterminal_mut().unwrap().set_label(label);
+            }
+            self.splice_out(node)?;
+        }
+        Ok(())
+    }
+
+    fn strip_functions_to_edges(&mut self) {
+        let nonterminals = self.nonterminals().collect::<Vec<_>>();
+        for nt in nonterminals {
+            let (label, function) = match self[nt].label().find('-') {
+                Some(idx) if idx > 0 => {
+                    let label = self[nt].label();
+                    (label[..idx].to_owned(), label[idx + 1..].to_owned())
+                }
+                _ => continue,
+            };
+            self[nt].set_label(label);
+            if let Some((_, edge)) = self.parent(nt) {
+                let edge = &mut self[edge];
+                if edge.label().is_none() && !function.is_empty() {
+                    edge.set_label(Some(function));
+                }
             }
+        }
+    }
+
+    fn limit_arity(&mut self, max: usize, delim: &str) -> Result<(), Error> {
+        if max < 2 {
+            return Err(format_err!(
+                "Maximum arity has to be at least 2, got {}.",
+                max
+            ));
+        }
 
-            // remove unary chain nodes.
-            for del_node in del {
-                self.graph_mut().remove_node(del_node);
+        let mut queue = self
+            .nonterminals()
+            .map(|nt| (nt, format!("{}{}", self[nt].label(), delim)))
+            .collect::<Vec<_>>();
+        while let Some((node, label)) = queue.pop() {
+            let mut children = self
+                .graph()
+                .edges_directed(node, Direction::Outgoing)
+                .map(|edge_ref| (edge_ref.target(), edge_ref.id()))
+                .collect::<Vec<_>>();
+            if children.len() <= max {
+                continue;
+            }
+            // group the rightmost children
+            children.sort_by(|(child_1, _), (child_2, _)| {
+                self[*child_1].span().cmp(self[*child_2].span())
+            });
+            let excess = children.split_off(max - 1);
+            let coverage = excess
+                .iter()
+                .flat_map(|(child, _)| self[*child].span().into_iter())
+                .collect::<Vec<_>>();
+            let span = Span::from_vec(coverage)?;
+            let nt = Node::NonTerminal(NonTerminal::new(label.clone(), span));
+            let inserted = self.graph_mut().add_node(nt);
+            self.graph_mut().add_edge(node, inserted, Edge::default());
+            for (child, id) in excess {
+                // safe to unwrap, id was just retrieved from the graph
+                let weight = self.graph_mut().remove_edge(id).unwrap();
+                self.graph_mut().add_edge(inserted, child, weight);
             }
+            queue.push((inserted, label));
         }
         Ok(())
     }
 
+    fn flatten(&mut self, root_label: Option<&str>) {
+        let root = self.root();
+        if self[root].is_terminal() {
+            return;
+        }
+
+        let terminals = self.terminals().collect::<Vec<_>>();
+        for terminal in terminals {
+            let (parent, edge) = self.parent(terminal).unwrap();
+            if parent != root {
+                let weight = self.graph_mut().remove_edge(edge).unwrap();
+                self.graph_mut().add_edge(root, terminal, weight);
+            }
+        }
+        let nonterminals = self
+            .nonterminals()
+            .filter(|&nt| nt != root)
+            .collect::<Vec<_>>();
+        for nonterminal in nonterminals {
+            self.graph_mut().remove_node(nonterminal);
+        }
+
+        let n_terminals = self.n_terminals();
+        let root_nt = self[root].nonterminal_mut().unwrap();
+        root_nt.set_span(Span::new_continuous(0, n_terminals));
+        if let Some(root_label) = root_label {
+            root_nt.set_label(root_label);
+        }
+        self.set_projectivity(Projectivity::Projective);
+    }
+
+    fn split_discontinuous(&mut self, label_suffix: Option<&str>) {
+        let root = self.root();
+        let mut discontinuous = Vec::new();
+        let mut dfs = DfsPostOrder::new(self.graph(), root);
+        while let Some(node) = dfs.next(self.graph()) {
+            if node != root && self[node].span().discontinuous().is_some() {
+                discontinuous.push(node);
+            }
+        }
+
+        for node in discontinuous {
+            // safe to unwrap, the root is never split
+            let (parent, edge) = self.parent(node).unwrap();
+            let weight = self[edge].clone();
+            let mut nt = self[node].nonterminal().unwrap().clone();
+            if let Some(suffix) = label_suffix {
+                let label = format!("{}{}", nt.label(), suffix);
+                nt.set_label(label);
+            }
+            let children = self
+                .graph()
+                .edges_directed(node, Direction::Outgoing)
+                .map(|edge_ref| (edge_ref.target(), edge_ref.id()))
+                .collect::<Vec<_>>();
+            for (lower, upper) in self[node].span().runs() {
+                let mut piece = nt.clone();
+                piece.set_span(Span::new_continuous(lower, upper));
+                let piece = self.graph_mut().add_node(Node::NonTerminal(piece));
+                self.graph_mut().add_edge(parent, piece, weight.clone());
+                for &(child, id) in children.iter() {
+                    let child_lower = self[child].span().lower();
+                    if child_lower >= lower && child_lower < upper {
+                        // safe to unwrap, each child is moved once
+                        let weight = self.graph_mut().remove_edge(id).unwrap();
+                        self.graph_mut().add_edge(piece, child, weight);
+                    }
+                }
+            }
+            self.graph_mut().remove_node(node);
+        }
+        let projectivity = self.compute_projectivity();
+        self.set_projectivity(projectivity);
+    }
+
+    fn collapse_unary_chains(&mut self, delim: &str) -> Result<(), Error> {
+        let terminals = self.terminals().collect::<Vec<_>>();
+        collapse_unary(self, terminals, None, delim)
+    }
+
+    fn collapse_unary_below(&mut self, root: NodeIndex, delim: &str) -> Result<(), Error> {
+        let mut terminals = Vec::new();
+        let mut bfs = Bfs::new(self.graph(), root);
+        while let Some(node) = bfs.next(self.graph()) {
+            if self[node].is_terminal() && node != root {
+                terminals.push(node);
+            }
+        }
+        collapse_unary(self, terminals, Some(root), delim)
+    }
+
     fn restore_unary_chains(&mut self, delim: &str) -> Result<(), Error> {
         let nodes = self.graph().node_indices().collect::<Vec<_>>();
         for node in nodes {
@@ -271,106 +897,316 @@ impl TreeOps for Tree {
                 self.graph_mut()
                     .add_edge(attachment_handle, cur, Edge::default());
             } else {
-                self.set_root(cur);
+                self.set_root(cur)?;
             };
         }
         Ok(())
     }
 }
 
+fn word_shape(form: &str) -> String {
+    form.chars()
+        .map(|c| {
+            if c.is_uppercase() {
+                'X'
+            } else if c.is_alphabetic() {
+                'x'
+            } else if c.is_numeric() {
+                'd'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+// Collapse the unary chains above `terminals`.
+//
+// If `bound` is given, climbing stops at `bound` which is never part of a chain.
+fn collapse_unary(
+    tree: &mut Tree,
+    terminals: Vec<NodeIndex>,
+    bound: Option<NodeIndex>,
+    delim: &str,
+) -> Result<(), Error> {
+    for terminal in terminals {
+        let mut cur = terminal;
+        // tree of form (S (T t)) has 2 nodes, 1 terminal, S will be removed.
+        // node_count - n_terminals specifies number of removed nodes.
+        let mut del = Vec::with_capacity(tree.graph().node_count() - tree.n_terminals());
+        let mut climber = Climber::new(terminal);
+        let mut prev_span = tree[terminal].span().clone();
+        let mut chain = Vec::new();
+
+        while let Some(node) = climber.next(tree) {
+            if Some(node) != bound && tree[node].span() == &prev_span {
+                // spans are equal in unary branches.
+                del.push(node);
+                match tree[node].nonterminal() {
+                    Some(nt) => {
+                        chain.push(nt.label().to_owned());
+                    }
+                    None => {
+                        return Err(LumberjackError::TerminalDominatingNonTerminal {
+                            terminal: tree[node].to_string(),
+                        }
+                        .into())
+                    }
+                }
+            } else if chain.is_empty() {
+                // no chain and non-matching spans means current node is branching.
+                prev_span = tree[node].span().clone();
+                cur = node;
+            } else {
+                // non-matching spans and non-empty label means that a unary chain has ended
+                tree[cur]
+                    .features_mut()
+                    .insert("unary_chain", Some(chain.join(delim)));
+                chain.clear();
+                // add new node bridging the node-to-be-removed
+                tree.graph_mut().add_edge(node, cur, Edge::default());
+                prev_span = tree[node].span().clone();
+                cur = node;
+            }
+            if Some(node) == bound {
+                break;
+            }
+        }
+
+        if !chain.is_empty() {
+            // empty label means, root is attached via unary chain.
+            tree.set_root(cur)?;
+            tree[cur]
+                .features_mut()
+                .insert("unary_chain", Some(chain.join(delim)));
+        }
+
+        // remove unary chain nodes.
+        for del_node in del {
+            tree.graph_mut().remove_node(del_node);
+        }
+    }
+    Ok(())
+}
+
 /// Projectivization Trait.
 ///
 /// Projectivization is done by re-attaching the non-projective content at the highest point
 /// allowing non-crossing edges while maintaining the linear order of the sentence.
 pub trait Projectivize {
     fn projectivize(&mut self);
+
+    /// Reduce the number of gaps of each node to at most `max_gaps`.
+    ///
+    /// Unlike `Projectivize::projectivize`, this keeps discontinuities within the limit. Nodes
+    /// are visited bottom-up, for a node with more than `max_gaps` gaps, the children covering
+    /// its first or last run of covered indices are lifted to the node's parent, whichever
+    /// covers fewer indices, preferring the last run on ties. This is repeated until the node
+    /// has at most `max_gaps` gaps. Lifting leaves the spans of all other nodes unchanged, nodes
+    /// within the limit keep their children.
+    ///
+    /// `reduce_gap_degree(0)` results in a projective tree.
+    fn reduce_gap_degree(&mut self, max_gaps: usize);
+
+    /// Projectivize only the nodes with labels matched by `set`.
+    ///
+    /// Applies the reattachment of `Projectivize::projectivize` to the discontinuous
+    /// `NonTerminal`s whose label is matched by `set`, all other nodes keep their children. The
+    /// spans of all nodes are recomputed afterwards. Since unmatched nodes may remain
+    /// discontinuous, the projectivity of the tree is recomputed rather than set to
+    /// `Projective`.
+    fn projectivize_labels(&mut self, set: &LabelSet);
 }
 
 impl Projectivize for Tree {
     fn projectivize(&mut self) {
         if !self.projective() {
-            let terminals = self.terminals().collect::<Vec<_>>();;
-            let mut dfs = DfsPostOrder::new(self.graph(), self.root());
-            let mut log = vec![None; terminals.len()];
-
-            while let Some(attachment_point_candidate) = dfs.next(self.graph()) {
-                let span = if let Node::NonTerminal(nt) = &self[attachment_point_candidate] {
-                    if let Span::Discontinuous(span) = nt.span() {
-                        span.to_owned()
-                    } else {
-                        continue;
-                    }
-                } else {
-                    continue;
-                };
+            reattach_nonprojective(self, |_| true);
+        }
+        self.set_projectivity(Projectivity::Projective);
+    }
 
-                let mut skips = span.skips().to_owned();
-                while let Some(&skipped) = skips.iter().next() {
-                    // check if terminal at idx skipped has already been reattached. We're
-                    // doing a postorder traversal, generally if something has been
-                    // reattached it ends up in the correct place, unless there are
-                    // multiple non-terminals covering the span. In that case, the correct
-                    // attachment is that non-terminal starting at the higher index.
-                    if let Some(claimed) = log[skipped] {
-                        if claimed >= span.lower() {
-                            // remove skipped idx so the loop can terminate
-                            skips.remove(&skipped);
-                            continue;
-                        }
-                    }
+    fn projectivize_labels(&mut self, set: &LabelSet) {
+        if self.projective() {
+            return;
+        }
+        reattach_nonprojective(self, |label| set.matches(label));
+        // nodes that lost reattached material are not updated while reattaching.
+        self.reset_nt_spans();
+        let projectivity = self.compute_projectivity();
+        self.set_projectivity(projectivity);
+    }
 
-                    let mut climber = Climber::new(terminals[skipped]);
-
-                    // cheap clone since terminal span is continuous (actually copy)
-                    let mut reattach_span = self[terminals[skipped]].span().clone();
-                    // keep track of which node is used to reattach non-projective material
-                    let mut attachment_handle = terminals[skipped];
-
-                    'a: while let Some(attachment_handle_candidate) = climber.next(&self) {
-                        // spans being eq implies unary chain, keep higher node as handle
-                        // for reattachment
-                        if self[attachment_handle_candidate].span() != &reattach_span {
-                            for covered in self[attachment_handle_candidate].span() {
-                                if !span.skips().contains(&covered) {
-                                    for covered in self[attachment_handle].span() {
-                                        skips.remove(&covered);
-                                        log[covered] = Some(span.lower());
-                                    }
-                                    let rm_edge = self.parent(attachment_handle).unwrap().1;
-                                    let edge = self.graph_mut().remove_edge(rm_edge).unwrap();
-                                    self.graph_mut().update_edge(
-                                        attachment_point_candidate,
-                                        attachment_handle,
-                                        edge,
-                                    );
-                                    break 'a;
-                                }
-                            }
-                            reattach_span = self[attachment_handle_candidate].span().clone();
-                        }
-                        attachment_handle = attachment_handle_candidate;
-                    }
-                }
-                self[attachment_point_candidate]
-                    .nonterminal_mut()
-                    .unwrap()
-                    .set_span(Span::new_continuous(span.lower(), span.upper()));
+    fn reduce_gap_degree(&mut self, max_gaps: usize) {
+        let root = self.root();
+        let mut nonterminals = Vec::new();
+        let mut dfs = DfsPostOrder::new(self.graph(), root);
+        while let Some(node) = dfs.next(self.graph()) {
+            if node != root && !self[node].is_terminal() {
+                nonterminals.push(node);
             }
-            self.set_projectivity(Projectivity::Projective);
         }
-    }
-}
 
-#[cfg(test)]
+        for node in nonterminals {
+            let (parent, _) = self.parent(node).unwrap();
+            loop {
+                let runs = self[node].span().runs();
+                if runs.len() <= max_gaps + 1 {
+                    break;
+                }
+                // lift the children of the outermost run that cover fewer indices
+                let covering = |run: (usize, usize)| {
+                    self.children(node)
+                        .filter(|&child| (run.0..run.1).any(|idx| self[child].span().contains(idx)))
+                        .collect::<Vec<_>>()
+                };
+                let coverage = |children: &[NodeIndex]| {
+                    children
+                        .iter()
+                        .map(|&child| self[child].span().n_indices())
+                        .sum::<usize>()
+                };
+                let first = covering(runs[0]);
+                let last = covering(runs[runs.len() - 1]);
+                let lift = if coverage(&first) < coverage(&last) {
+                    first
+                } else {
+                    last
+                };
+                for child in lift {
+                    let (_, edge) = self.parent(child).unwrap();
+                    let edge = self.graph_mut().remove_edge(edge).unwrap();
+                    self.graph_mut().add_edge(parent, child, edge);
+                }
+                let coverage = self
+                    .children(node)
+                    .flat_map(|child| self[child].span().into_iter())
+                    .collect::<Vec<_>>();
+                let span = Span::from_vec(coverage).unwrap();
+                self[node].nonterminal_mut().unwrap().set_span(span);
+            }
+        }
+
+        let projective = self
+            .nonterminals()
+            .all(|nt| self[nt].span().discontinuous().is_none());
+        if projective {
+            self.set_projectivity(Projectivity::Projective);
+        } else {
+            self.set_projectivity(Projectivity::Nonprojective);
+        }
+    }
+}
+
+// Reattach the material in the gaps of discontinuous `NonTerminal`s whose label is matched by
+// `lift`.
+//
+// The spans of matched nodes are made continuous, the spans of nodes that lose material are not
+// updated.
+fn reattach_nonprojective<F>(tree: &mut Tree, lift: F)
+where
+    F: Fn(&str) -> bool,
+{
+    let terminals = tree.terminals().collect::<Vec<_>>();
+    let mut dfs = DfsPostOrder::new(tree.graph(), tree.root());
+    let mut log = vec![None; terminals.len()];
+
+    while let Some(attachment_point_candidate) = dfs.next(tree.graph()) {
+        let span = if let Node::NonTerminal(nt) = &tree[attachment_point_candidate] {
+            if !lift(nt.label()) {
+                continue;
+            }
+            if let Span::Discontinuous(span) = nt.span() {
+                span.to_owned()
+            } else {
+                continue;
+            }
+        } else {
+            continue;
+        };
+
+        let mut skips = span.skips().to_owned();
+        while let Some(&skipped) = skips.iter().next() {
+            // check if terminal at idx skipped has already been reattached. We're
+            // doing a postorder traversal, generally if something has been
+            // reattached it ends up in the correct place, unless there are
+            // multiple non-terminals covering the span. In that case, the correct
+            // attachment is that non-terminal starting at the higher index.
+            if let Some(claimed) = log[skipped] {
+                if claimed >= span.lower() {
+                    // remove skipped idx so the loop can terminate
+                    skips.remove(&skipped);
+                    continue;
+                }
+            }
+
+            let mut climber = Climber::new(terminals[skipped]);
+
+            // cheap clone since terminal span is continuous (actually copy)
+            let mut reattach_span = tree[terminals[skipped]].span().clone();
+            // keep track of which node is used to reattach non-projective material
+            let mut attachment_handle = terminals[skipped];
+
+            'a: while let Some(attachment_handle_candidate) = climber.next(tree) {
+                // spans being eq implies unary chain, keep higher node as handle
+                // for reattachment
+                if tree[attachment_handle_candidate].span() != &reattach_span {
+                    for covered in tree[attachment_handle_candidate].span() {
+                        if !span.skips().contains(&covered) {
+                            for covered in tree[attachment_handle].span() {
+                                skips.remove(&covered);
+                                log[covered] = Some(span.lower());
+                            }
+                            let rm_edge = tree.parent(attachment_handle).unwrap().1;
+                            let edge = tree.graph_mut().remove_edge(rm_edge).unwrap();
+                            tree.graph_mut().update_edge(
+                                attachment_point_candidate,
+                                attachment_handle,
+                                edge,
+                            );
+                            break 'a;
+                        }
+                    }
+                    reattach_span = tree[attachment_handle_candidate].span().clone();
+                }
+                attachment_handle = attachment_handle_candidate;
+            }
+        }
+        tree[attachment_point_candidate]
+            .nonterminal_mut()
+            .unwrap()
+            .set_span(Span::new_continuous(span.lower(), span.upper()));
+    }
+}
+
+// Split a trailing integer separated by `delim` off `text`.
+fn split_coindex(text: &str, delim: char) -> Option<(&str, usize)> {
+    let pos = text.rfind(delim)?;
+    let (prefix, index) = (&text[..pos], &text[pos + delim.len_utf8()..]);
+    if prefix.is_empty() || index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    index.parse().ok().map(|index| (prefix, index))
+}
+
+fn renumbered(text: &str, delim: char, index: usize) -> String {
+    let (prefix, _) = split_coindex(text, delim).unwrap();
+    format!("{}{}{}", prefix, delim, index)
+}
+
+#[cfg(test)]
 mod tests {
     use std::collections::HashSet;
 
     use petgraph::prelude::StableGraph;
 
-    use super::{AnnotatePOS, TreeOps};
-    use crate::io::PTBFormat;
-    use crate::util::LabelSet;
-    use crate::{Edge, Features, Node, NonTerminal, Projectivity, Span, Terminal, Tree};
+    use super::{AnnotatePOS, Projectivize, TreeOps};
+    use crate::io::{PTBFormat, PTBFormatter};
+    use crate::util::{FilterMode, LabelSet};
+    use crate::{
+        Edge, Features, HeadDirection, HeadRules, LumberjackError, Node, NonTerminal, Projectivity,
+        Span, Terminal, Tree,
+    };
 
     #[test]
     fn un_collapse_unary() {
@@ -413,6 +1249,352 @@ mod tests {
         assert_eq!(input, PTBFormat::Simple.tree_to_string(&t).unwrap());
     }
 
+    #[test]
+    fn collapse_unary_below() {
+        let input = "(ROOT (A (UA (UB (T1 t1)) (T2 t2))) (B (UC (T3 t3))))";
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        let a = t.nonterminals().find(|&nt| t[nt].label() == "A").unwrap();
+        t.collapse_unary_below(a, "_").unwrap();
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&t).unwrap(),
+            "(ROOT (A (UA (T1 t1) (T2 t2))) (B (UC (T3 t3))))"
+        );
+        let t1 = t.terminal_at(0).unwrap();
+        assert_eq!(t[t1].features(), Some(&Features::from("unary_chain:UB")));
+        t.restore_unary_chains("_").unwrap();
+        assert_eq!(PTBFormat::Simple.tree_to_string(&t).unwrap(), input);
+
+        // the chain ending at the given node is collapsed, the node itself is kept
+        let b = t.nonterminals().find(|&nt| t[nt].label() == "B").unwrap();
+        t.collapse_unary_below(b, "_").unwrap();
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&t).unwrap(),
+            "(ROOT (A (UA (UB (T1 t1)) (T2 t2))) (B (T3 t3)))"
+        );
+        t.restore_unary_chains("_").unwrap();
+        assert_eq!(PTBFormat::Simple.tree_to_string(&t).unwrap(), input);
+    }
+
+    #[test]
+    fn splice_out() {
+        let input = "(ROOT (NP-SBJ (DT the) (NN dog)) (VP-HD (VBZ barks)))";
+        let mut t = PTBFormat::PTB.string_to_tree(input).unwrap();
+        let np = t.nonterminals().find(|&nt| t[nt].label() == "NP").unwrap();
+        let dt = t.terminals().find(|&t_| t[t_].label() == "DT").unwrap();
+        let (_, edge) = t.parent(dt).unwrap();
+        t[edge] = Edge::from(Some("NK"));
+        t.splice_out(np).unwrap();
+        assert_eq!(
+            "(ROOT (DT the) (NN dog) (VP (VBZ barks)))",
+            PTBFormat::Simple.tree_to_string(&t).unwrap()
+        );
+        let (parent, edge) = t.parent(dt).unwrap();
+        assert_eq!(parent, t.root());
+        assert_eq!(t[edge], Edge::from(Some("NK")));
+
+        let root = t.root();
+        assert!(t.splice_out(root).is_err());
+        assert!(t.splice_out(dt).is_err());
+    }
+
+    #[test]
+    fn merge_same_label() {
+        let input = "(NP (NP (NP (NN x))))";
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        t.merge_same_label(None).unwrap();
+        assert_eq!("(NP (NN x))", PTBFormat::Simple.tree_to_string(&t).unwrap());
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        t.merge_same_label(Some("_")).unwrap();
+        assert_eq!(
+            "(NP_NP_NP (NN x))",
+            PTBFormat::Simple.tree_to_string(&t).unwrap()
+        );
+
+        let input = "(S (NP (NP (DT the) (NN dog)) (PP (IN of) (NP (NN mine)))) (VP (VBZ barks)))";
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        t.merge_same_label(None).unwrap();
+        assert_eq!(
+            "(S (NP (DT the) (NN dog) (PP (IN of) (NP (NN mine)))) (VP (VBZ barks)))",
+            PTBFormat::Simple.tree_to_string(&t).unwrap()
+        );
+
+        let input = "(NN (NN x))";
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        t.merge_same_label(None).unwrap();
+        assert_eq!(input, PTBFormat::Simple.tree_to_string(&t).unwrap());
+    }
+
+    #[test]
+    fn strip_functions_to_edges() {
+        let mut tree = PTBFormat::TueBa
+            .string_to_tree(
+                "(S-TPC (NP-SBJ (DT the) (NN dog)) (VP-PRD:HD (VBZ barks)) (-NONE- *) (ADVP- (RB now)))",
+            )
+            .unwrap();
+        tree.strip_functions_to_edges();
+        assert_eq!(
+            PTBFormat::TueBa.tree_to_string(&tree).unwrap(),
+            "(S:--(NP:SBJ(DT:-- the)(NN:-- dog))(VP:HD(VBZ:-- barks))(-NONE-:-- *)(ADVP:--(RB:-- now)))"
+        );
+    }
+
+    #[test]
+    fn remove_traces() {
+        let input = "(S (NP-SBJ (NNS People)) (VP (VBD said) (SBAR (-NONE- 0) (S (NP-SBJ-2 \
+                     (-NONE- *)) (VP (TO to) (VP (VB go) (NP (-NONE- *T*-1)) (-NONE- *?*)))))) \
+                     (-LRB- -LRB-) (. .))";
+        let mut tree = PTBFormat::PTB.string_to_tree(input).unwrap();
+        tree.remove_traces().unwrap();
+        assert_eq!(tree.n_terminals(), 6);
+        tree.validate().unwrap();
+        assert_eq!(
+            PTBFormat::PTB.tree_to_string(&tree).unwrap(),
+            "(S (NP-SBJ (NNS People)) (VP (VBD said) (SBAR (S (VP (TO to) (VP (VB go)))))) \
+             (-LRB- -LRB-) (. .))"
+        );
+        assert_eq!(tree.to_penn_treebank_with_empties().unwrap(), input);
+
+        let formatter = PTBFormatter::new(PTBFormat::PTB).features(true);
+        let written = formatter.tree_to_string(&tree).unwrap();
+        let read = formatter.string_to_tree(&written).unwrap();
+        assert_eq!(read, tree);
+        assert_eq!(
+            formatter
+                .empty_elements(true)
+                .tree_to_string(&read)
+                .unwrap(),
+            input
+        );
+
+        let mut tree = PTBFormat::PTB
+            .string_to_tree("(S (NP (-NONE- *)))")
+            .unwrap();
+        assert!(tree.remove_traces().is_err());
+    }
+
+    #[test]
+    fn renumber_coindices() {
+        let input = "(S (NP-SBJ-4 (NNP John)) (VP (VBD seemed) (S (NP-SBJ (-NONE- *-4)) \
+                     (VP (TO to) (VP (VB win) (NP-7 (CD 1990-91)) (NP (-NONE- *T*-12)))))) \
+                     (-LRB- -LRB-))";
+        let mut tree = PTBFormat::PTB.string_to_tree(input).unwrap();
+        tree.renumber_coindices('-');
+        assert_eq!(
+            PTBFormat::PTB.tree_to_string(&tree).unwrap(),
+            "(S (NP-SBJ-1 (NNP John)) (VP (VBD seemed) (S (NP-SBJ (-NONE- *-1)) (VP (TO to) \
+             (VP (VB win) (NP-2 (CD 1990-91)) (NP (-NONE- *T*-3)))))) (-LRB- -LRB-))"
+        );
+
+        let input = "(S (NP=5 (NN a)) (NP-SBJ=3 (NN b)) (VP-5 (VB c)))";
+        let mut tree = PTBFormat::PTB.string_to_tree(input).unwrap();
+        tree.renumber_coindices('=');
+        assert_eq!(
+            PTBFormat::PTB.tree_to_string(&tree).unwrap(),
+            "(S (NP=2 (NN a)) (NP-SBJ=1 (NN b)) (VP-5 (VB c)))"
+        );
+    }
+
+    #[test]
+    fn limit_arity() {
+        let input = "(ROOT (A a) (B b) (NP (C c) (D d) (E e) (F f)) (G g))";
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        t.limit_arity(5, "|").unwrap();
+        assert_eq!(input, PTBFormat::Simple.tree_to_string(&t).unwrap());
+
+        t.limit_arity(3, "|").unwrap();
+        assert_eq!(
+            "(ROOT (A a) (B b) (ROOT| (NP (C c) (D d) (NP| (E e) (F f))) (G g)))",
+            PTBFormat::Simple.tree_to_string(&t).unwrap()
+        );
+        let inserted = t
+            .nonterminals()
+            .find(|&nt| t[nt].label() == "ROOT|")
+            .unwrap();
+        assert_eq!(t[inserted].span(), &Span::new_continuous(2, 7));
+
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        t.limit_arity(2, "|").unwrap();
+        assert_eq!(
+            "(ROOT (A a) (ROOT| (B b) (ROOT| (NP (C c) (NP| (D d) (NP| (E e) (F f)))) (G g))))",
+            PTBFormat::Simple.tree_to_string(&t).unwrap()
+        );
+        assert!(t.limit_arity(1, "|").is_err());
+    }
+
+    #[test]
+    fn annotate_siblings() {
+        let input = "(ROOT (NP (DT the) (NN dog)) (VBZ barks) (ADVP (RB loudly)))";
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        t.annotate_siblings("left", "right").unwrap();
+        let features = t
+            .terminals()
+            .map(|terminal| {
+                let features = t[terminal].features().unwrap();
+                (
+                    t[terminal].terminal().unwrap().form(),
+                    features.get_val("left").unwrap(),
+                    features.get_val("right").unwrap(),
+                )
+            })
+            .collect::<HashSet<_>>();
+        let target = vec![
+            ("the", "NONE", "NN"),
+            ("dog", "DT", "NONE"),
+            ("barks", "NP", "ADVP"),
+            ("loudly", "NONE", "NONE"),
+        ]
+        .into_iter()
+        .collect::<HashSet<_>>();
+        assert_eq!(features, target);
+
+        let mut t = PTBFormat::Simple.string_to_tree("(T t)").unwrap();
+        assert!(t.annotate_siblings("left", "right").is_err());
+    }
+
+    #[test]
+    fn flatten() {
+        let input = "(ROOT (S (NP (DT the) (NN dog)) (VP:HD (VBZ:HD barks) (ADVP (RB loudly)))))";
+        let mut t = PTBFormat::TueBa.string_to_tree(input).unwrap();
+        let mut flat = t.clone();
+        flat.flatten(None);
+        assert_eq!(
+            PTBFormat::TueBa.tree_to_string(&flat).unwrap(),
+            "(ROOT:--(DT:-- the)(NN:-- dog)(VBZ:HD barks)(RB:-- loudly))"
+        );
+        assert_eq!(flat.projectivity(), Projectivity::Projective);
+        assert_eq!(flat.n_terminals(), 4);
+        flat.validate().unwrap();
+
+        t.flatten(Some("FLAT"));
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&t).unwrap(),
+            "(FLAT (DT the) (NN dog) (VBZ barks) (RB loudly))"
+        );
+
+        let mut t = PTBFormat::Simple.string_to_tree("(T t)").unwrap();
+        t.flatten(Some("FLAT"));
+        assert_eq!(PTBFormat::Simple.tree_to_string(&t).unwrap(), "(T t)");
+    }
+
+    #[test]
+    fn prune() {
+        let input = "(ROOT (S (NP (DT the) (NN dog)) (PRN (LRB -LRB-) (NN pet) (RRB -RRB-)) (VP (VBZ barks))))";
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        let prn = t.nonterminals().find(|&nt| t[nt].label() == "PRN").unwrap();
+        t[prn].features_mut().insert("remove", None::<String>);
+        t.prune(|node| {
+            node.features()
+                .is_none_or(|features| !features.inner().contains_key("remove"))
+                && !node.label().ends_with("RB")
+        })
+        .unwrap();
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&t).unwrap(),
+            "(ROOT (S (NP (DT the) (NN dog)) (NN pet) (VP (VBZ barks))))"
+        );
+        assert_eq!(t.n_terminals(), 4);
+        t.validate().unwrap();
+
+        // nonterminals left without terminals are removed, the root is kept
+        t.prune(|node| node.label() != "NN" && node.label() != "VBZ")
+            .unwrap();
+        assert_eq!(
+            PTBFormat::Simple.tree_to_string(&t).unwrap(),
+            "(ROOT (S (NP (DT the))))"
+        );
+        t.validate().unwrap();
+
+        assert!(t.prune(|node| !node.is_terminal()).is_err());
+        assert_eq!(t.n_terminals(), 1);
+    }
+
+    #[test]
+    fn annotate_head() {
+        let input = "(S (NP (DT the) (NN dog)) (VP (VBZ barks)))";
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        let rules = HeadRules::new(HeadDirection::RightToLeft).rule(
+            "S",
+            HeadDirection::LeftToRight,
+            vec!["VP"],
+        );
+        t.annotate_head(&rules).unwrap();
+        let mut heads = t
+            .graph()
+            .node_indices()
+            .filter(|&node| {
+                t[node]
+                    .features()
+                    .is_some_and(|f| f.get_val("head") == Some("true"))
+            })
+            .map(|node| t[node].label().to_owned())
+            .collect::<Vec<_>>();
+        heads.sort();
+        assert_eq!(heads, vec!["NN", "VBZ", "VP"]);
+    }
+
+    #[test]
+    fn inherit_feature() {
+        let mut tree = PTBFormat::Simple
+            .string_to_tree("(S (NN a) (SBAR (KOUS b) (S (NP (NN c)) (VVFIN d))))")
+            .unwrap();
+        let sbar = tree.find_terminals(&LabelSet::Positive(
+            vec!["KOUS".to_owned()].into_iter().collect(),
+        ))[0];
+        let (sbar, _) = tree.parent(sbar).unwrap();
+        tree[sbar].features_mut().insert("clause", Some("sub"));
+        let d = tree.terminal_at(3).unwrap();
+        tree[d].features_mut().insert("clause", None::<&str>);
+        tree.inherit_feature("clause").unwrap();
+
+        let clause = tree
+            .preorder()
+            .map(|node| {
+                (
+                    tree[node].label(),
+                    tree[node]
+                        .features()
+                        .and_then(|features| features.inner().get("clause").cloned()),
+                )
+            })
+            .collect::<Vec<_>>();
+        let sub = Some(Some("sub".to_owned()));
+        assert_eq!(
+            clause,
+            vec![
+                ("S", None),
+                ("NN", None),
+                ("SBAR", sub.clone()),
+                ("KOUS", sub.clone()),
+                ("S", sub.clone()),
+                ("NP", sub.clone()),
+                ("NN", sub),
+                ("VVFIN", Some(None)),
+            ]
+        );
+
+        let inherited = tree.clone();
+        assert!(tree.inherit_feature("missing").is_err());
+        assert_eq!(tree, inherited);
+    }
+
+    #[test]
+    fn annotate_word_shape() {
+        let input = "(ROOT (NN Haus) (CD 1984) (NE U-2) (NN Straße) (NE ÖBB) (NN 東京))";
+        let mut t = PTBFormat::Simple.string_to_tree(input).unwrap();
+        t.annotate_word_shape("shape");
+        let shapes = (0..t.n_terminals())
+            .map(|idx| {
+                let terminal = t.terminal_at(idx).unwrap();
+                t[terminal].features().unwrap().get_val("shape").unwrap()
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(shapes, vec!["Xxxx", "dddd", "X-d", "Xxxxxx", "XXX", "xx"]);
+
+        let annotated = t.clone();
+        t.annotate_word_shape("shape");
+        assert_eq!(t, annotated);
+    }
+
     #[test]
     pub fn annotate_pos() {
         let input = "(NX (NN Nounphrase) (PX (PP on) (NX (DET a) (ADJ single) (NX line))))";
@@ -423,9 +1605,28 @@ mod tests {
         assert_eq!(target, PTBFormat::Simple.tree_to_string(&tree).unwrap());
 
         let pos_too_short = vec!["A"];
-        assert!(tree.annotate_pos(pos_too_short).is_err());
+        let err = tree.annotate_pos(pos_too_short).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LumberjackError>(),
+            Some(&LumberjackError::PosCountMismatch {
+                n_tags: 1,
+                n_terminals: 5
+            })
+        );
+        assert_eq!(err.to_string(), "Not enough POS tags were provided");
         let pos_too_long = vec!["A", "B", "C", "D", "E", "F"];
-        assert!(tree.annotate_pos(pos_too_long).is_err());
+        let err = tree.annotate_pos(pos_too_long).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<LumberjackError>(),
+            Some(&LumberjackError::PosCountMismatch {
+                n_tags: 6,
+                n_terminals: 5
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "Number of POS tags is greater than number of terminals."
+        );
     }
 
     #[test]
@@ -522,6 +1723,285 @@ mod tests {
         g.add_edge(root_idx, term5_idx, Edge::default());
         let target = Tree::new(g, 5, root_idx, Projectivity::Projective);
         assert_eq!(target, filtered_tree);
+        assert_eq!(filtered_tree.projectivity(), Projectivity::Unknown);
+        assert!(filtered_tree.projective());
+    }
+
+    #[test]
+    fn filter_nonproj_remove() {
+        let mut g = StableGraph::new();
+        let root = NonTerminal::new("ROOT", Span::new_continuous(0, 6));
+        let root_idx = g.add_node(Node::NonTerminal(root));
+        let first = NonTerminal::new("L", Span::from_vec(vec![0, 2]).unwrap());
+        let first_idx = g.add_node(Node::NonTerminal(first));
+        g.add_edge(root_idx, first_idx, Edge::default());
+        let second = NonTerminal::new("L1", Span::new_continuous(1, 2));
+        let second_idx = g.add_node(Node::NonTerminal(second));
+        g.add_edge(root_idx, second_idx, Edge::default());
+        let third = NonTerminal::new("L", Span::new_continuous(3, 4));
+        let third_idx = g.add_node(Node::NonTerminal(third));
+        g.add_edge(root_idx, third_idx, Edge::default());
+        let term1 = Terminal::new("t1", "TERM1", 0);
+        let term1_idx = g.add_node(Node::Terminal(term1));
+        g.add_edge(first_idx, term1_idx, Edge::default());
+        let term2 = Terminal::new("t2", "TERM1", 1);
+        let term2_idx = g.add_node(Node::Terminal(term2));
+        g.add_edge(second_idx, term2_idx, Edge::default());
+        let term3 = Terminal::new("t3", "TERM3", 2);
+        let term3_idx = g.add_node(Node::Terminal(term3));
+        g.add_edge(first_idx, term3_idx, Edge::default());
+        let term4 = Terminal::new("t4", "TERM4", 3);
+        let term4_idx = g.add_node(Node::Terminal(term4));
+        g.add_edge(third_idx, term4_idx, Edge::default());
+        let term5 = Terminal::new("t5", "TERM5", 4);
+        let term5_idx = g.add_node(Node::Terminal(term5));
+        g.add_edge(root_idx, term5_idx, Edge::default());
+
+        let tree = Tree::new(g, 5, root_idx, Projectivity::Nonprojective);
+        let mut tags = HashSet::new();
+        tags.insert("L1".into());
+        let mut filtered_tree = tree.clone();
+        filtered_tree
+            .filter_nonterminals_mode(&LabelSet::Positive(tags), FilterMode::Remove)
+            .unwrap();
+
+        let mut g = StableGraph::new();
+        let root = NonTerminal::new("ROOT", Span::new_continuous(0, 6));
+        let root_idx = g.add_node(Node::NonTerminal(root));
+        let first = NonTerminal::new("L", Span::from_vec(vec![0, 2]).unwrap());
+        let first_idx = g.add_node(Node::NonTerminal(first));
+        g.add_edge(root_idx, first_idx, Edge::default());
+        let third = NonTerminal::new("L", Span::new_continuous(3, 4));
+        let third_idx = g.add_node(Node::NonTerminal(third));
+        g.add_edge(root_idx, third_idx, Edge::default());
+        let term1 = Terminal::new("t1", "TERM1", 0);
+        let term1_idx = g.add_node(Node::Terminal(term1));
+        g.add_edge(first_idx, term1_idx, Edge::default());
+        let term2 = Terminal::new("t2", "TERM1", 1);
+        let term2_idx = g.add_node(Node::Terminal(term2));
+        g.add_edge(root_idx, term2_idx, Edge::default());
+        let term3 = Terminal::new("t3", "TERM3", 2);
+        let term3_idx = g.add_node(Node::Terminal(term3));
+        g.add_edge(first_idx, term3_idx, Edge::default());
+        let term4 = Terminal::new("t4", "TERM4", 3);
+        let term4_idx = g.add_node(Node::Terminal(term4));
+        g.add_edge(third_idx, term4_idx, Edge::default());
+        let term5 = Terminal::new("t5", "TERM5", 4);
+        let term5_idx = g.add_node(Node::Terminal(term5));
+        g.add_edge(root_idx, term5_idx, Edge::default());
+        let target = Tree::new(g, 5, root_idx, Projectivity::Nonprojective);
+        assert_eq!(target, filtered_tree);
+
+        // the root is never removed
+        let mut tags = HashSet::new();
+        tags.insert("ROOT".into());
+        tags.insert("L".into());
+        tags.insert("L1".into());
+        let mut filtered_tree = tree.clone();
+        filtered_tree
+            .filter_nonterminals_mode(&LabelSet::Positive(tags), FilterMode::Remove)
+            .unwrap();
+        assert_eq!(filtered_tree.root(), tree.root());
+        assert_eq!(filtered_tree.nonterminals().count(), 1);
+        assert_eq!(filtered_tree.children(tree.root()).count(), 5);
+    }
+
+    #[test]
+    fn split_discontinuous() {
+        let input = "#BOS 1
+A\ta\tNN\t--\tHD\t500
+B\tb\tVVFIN\t--\tHD\t0
+C\tc\tNN\t--\t--\t501
+D\td\tNN\t--\tHD\t501
+#500\t--\tNP\t--\tSB\t0
+#501\t--\tNP\t--\tAPP\t500
+#EOS 1
+";
+        // the outer NP covers A, C and D in two runs.
+        let tree = crate::io::negra_to_tree(input).unwrap();
+        assert!(!tree.projective());
+
+        let mut split = tree.clone();
+        split.split_discontinuous(Some("*"));
+        assert_eq!(split.projectivity(), Projectivity::Projective);
+        split.validate().unwrap();
+        assert_eq!(
+            PTBFormat::TueBa.tree_to_string(&split).unwrap(),
+            "(VROOT:--(NP*:SB(NN:HD A))(VVFIN:HD B)(NP*:SB(NP:APP(NN:-- C)(NN:HD D))))"
+        );
+
+        let mut split = tree.clone();
+        split.split_discontinuous(None);
+        assert_eq!(split.n_terminals(), 4);
+        assert_eq!(
+            split
+                .nonterminals()
+                .filter(|&nt| split[nt].label() == "NP")
+                .count(),
+            3
+        );
+
+        let input = std::fs::read_to_string("testdata/long_single.negra").unwrap();
+        let mut tree = crate::io::negra_to_tree(&input).unwrap();
+        tree.split_discontinuous(None);
+        assert!(tree.projective());
+        tree.validate().unwrap();
+    }
+
+    #[test]
+    fn reduce_gap_degree() {
+        let input = "#BOS 1
+A\ta\tNN\t--\tHD\t500
+B\tb\tVVFIN\t--\tHD\t0
+C\tc\tNN\t--\tHD\t501
+D\td\tNN\t--\t--\t0
+E\te\tNN\t--\tHD\t501
+F\tf\tNN\t--\t--\t0
+G\tg\tNN\t--\tHD\t500
+#500\t--\tNP\t--\tSB\t0
+#501\t--\tNP\t--\tOA\t500
+#EOS 1
+";
+        // NP covering A, C, E and G has three gaps, the embedded NP covering C and E has one.
+        let tree = crate::io::negra_to_tree(input).unwrap();
+        let n_gaps = |tree: &Tree, label: &str| {
+            tree.nonterminals()
+                .filter(|&nt| tree[nt].label() == label)
+                .map(|nt| tree[nt].span().gaps().len())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(n_gaps(&tree, "NP"), vec![3, 1]);
+
+        let mut reduced = tree.clone();
+        reduced.reduce_gap_degree(3);
+        assert_eq!(reduced, tree);
+
+        let mut reduced = tree.clone();
+        reduced.reduce_gap_degree(1);
+        let mut gaps = n_gaps(&reduced, "NP");
+        gaps.sort();
+        assert_eq!(gaps, vec![1, 1]);
+        assert_eq!(reduced.projectivity(), Projectivity::Nonprojective);
+        // G is lifted to the root, the embedded NP is untouched.
+        let g = reduced.terminal_at(6).unwrap();
+        assert_eq!(reduced.parent(g).unwrap().0, reduced.root());
+        let c = reduced.terminal_at(2).unwrap();
+        let (inner, _) = reduced.parent(c).unwrap();
+        assert_eq!(
+            reduced[inner].span().into_iter().collect::<Vec<_>>(),
+            vec![2, 4]
+        );
+        reduced.validate().unwrap();
+
+        let mut reduced = tree.clone();
+        reduced.reduce_gap_degree(0);
+        assert!(reduced.projective());
+        assert_eq!(reduced.projectivity(), Projectivity::Projective);
+        reduced.validate().unwrap();
+        assert!(PTBFormat::Simple.tree_to_string(&reduced).is_ok());
+
+        let input = std::fs::read_to_string("testdata/long_single.negra").unwrap();
+        let mut tree = crate::io::negra_to_tree(&input).unwrap();
+        tree.reduce_gap_degree(0);
+        assert!(tree
+            .nonterminals()
+            .all(|nt| tree[nt].span().gaps().is_empty()));
+        tree.validate().unwrap();
+    }
+
+    #[test]
+    fn projectivize_labels() {
+        let input = "#BOS 1
+A\ta\tNN\t--\tHD\t500
+B\tb\tVVFIN\t--\tHD\t0
+C\tc\tNN\t--\tNK\t500
+D\td\tVVPP\t--\tHD\t501
+E\te\tNN\t--\t--\t0
+F\tf\tPTKVZ\t--\tSVP\t501
+#500\t--\tNP\t--\tSB\t0
+#501\t--\tVP\t--\tOC\t0
+#EOS 1
+";
+        let tree = crate::io::negra_to_tree(input).unwrap();
+        let discontinuous = |tree: &Tree| {
+            tree.nonprojective_nodes()
+                .into_iter()
+                .map(|nt| tree[nt].label().to_owned())
+                .collect::<Vec<_>>()
+        };
+        let mut vp = HashSet::new();
+        vp.insert("VP".to_owned());
+        let vp = LabelSet::Positive(vp);
+
+        let mut partial = tree.clone();
+        partial.projectivize_labels(&vp);
+        assert_eq!(discontinuous(&partial), vec!["NP"]);
+        assert_eq!(partial.projectivity(), Projectivity::Nonprojective);
+        let e = partial.terminal_at(4).unwrap();
+        assert_eq!(partial[partial.parent(e).unwrap().0].label(), "VP");
+        let b = partial.terminal_at(1).unwrap();
+        assert_eq!(partial.parent(b).unwrap().0, partial.root());
+        partial.validate().unwrap();
+
+        partial.projectivize_labels(&LabelSet::Negative(HashSet::new()));
+        assert_eq!(partial.projectivity(), Projectivity::Projective);
+        let mut full = tree.clone();
+        full.projectivize();
+        assert_eq!(partial, full);
+    }
+
+    #[test]
+    fn projectivize_unknown() {
+        let input = std::fs::read_to_string("testdata/long_single.negra").unwrap();
+        let mut tree = crate::io::negra_to_tree(&input).unwrap();
+        assert!(!tree.projective());
+        tree.set_projectivity(Projectivity::Unknown);
+        assert!(!tree.projective());
+        tree.projectivize();
+        assert_eq!(tree.projectivity(), Projectivity::Projective);
+        assert!(tree.validate().is_ok());
+    }
+
+    #[test]
+    fn insert_intermediate_spans() {
+        // (ROOT (X t0 t1 t2 t4) (L t3)), terminals are added to the graph out of order.
+        let mut g = StableGraph::new();
+        let root = NonTerminal::new("ROOT", Span::new_continuous(0, 5));
+        let root_idx = g.add_node(Node::NonTerminal(root));
+        let x = NonTerminal::new("X", Span::from_vec(vec![0, 1, 2, 4]).unwrap());
+        let x_idx = g.add_node(Node::NonTerminal(x));
+        g.add_edge(root_idx, x_idx, Edge::default());
+        let l = NonTerminal::new("L", Span::new_continuous(3, 4));
+        let l_idx = g.add_node(Node::NonTerminal(l));
+        g.add_edge(root_idx, l_idx, Edge::default());
+        for &idx in &[2, 4, 0, 3, 1] {
+            let terminal = Terminal::new(format!("t{}", idx), "T", idx);
+            let terminal_idx = g.add_node(Node::Terminal(terminal));
+            let parent = if idx == 3 { l_idx } else { x_idx };
+            g.add_edge(parent, terminal_idx, Edge::default());
+        }
+        let mut tree = Tree::new(g, 5, root_idx, Projectivity::Nonprojective);
+        let set = LabelSet::Positive(vec!["L".to_owned()].into_iter().collect());
+        tree.insert_intermediate(&set, "UNK").unwrap();
+
+        let mut inserted = tree
+            .children(x_idx)
+            .map(|child| (tree[child].label().to_owned(), tree[child].span().clone()))
+            .collect::<Vec<_>>();
+        inserted.sort_by(|(_, s1), (_, s2)| s1.cmp(s2));
+        assert_eq!(
+            inserted,
+            vec![
+                ("UNK".to_owned(), Span::new_continuous(0, 3)),
+                ("UNK".to_owned(), Span::new_continuous(4, 5)),
+            ]
+        );
+        assert_eq!(
+            tree[x_idx].span(),
+            &Span::from_vec(vec![0, 1, 2, 4]).unwrap()
+        );
+        assert_eq!(tree.children(l_idx).count(), 1);
+        assert!(tree.validate().is_ok());
     }
 
     #[test]
@@ -58,6 +58,14 @@ pub enum LabelSet {
     Positive(HashSet<String>),
     /// Variant used for negative matching.
     Negative(HashSet<String>),
+    /// Variant used for case-insensitive positive matching.
+    ///
+    /// Labels are expected to be lowercased, see `LabelSet::ignore_case`.
+    PositiveIgnoreCase(HashSet<String>),
+    /// Variant used for case-insensitive negative matching.
+    ///
+    /// Labels are expected to be lowercased, see `LabelSet::ignore_case`.
+    NegativeIgnoreCase(HashSet<String>),
 }
 
 impl LabelSet {
@@ -69,10 +77,36 @@ impl LabelSet {
         match self {
             LabelSet::Positive(ref set) => set.contains(q.borrow()),
             LabelSet::Negative(ref set) => !set.contains(q.borrow()),
+            LabelSet::PositiveIgnoreCase(ref set) => set.contains(&q.borrow().to_lowercase()),
+            LabelSet::NegativeIgnoreCase(ref set) => !set.contains(&q.borrow().to_lowercase()),
+        }
+    }
+
+    /// Turns `self` into a case-insensitive `LabelSet`.
+    ///
+    /// The labels are lowercased, queries are lowercased before lookup. Case-insensitive sets
+    /// are returned unchanged.
+    pub fn ignore_case(self) -> Self {
+        let lowercase = |set: HashSet<String>| set.into_iter().map(|l| l.to_lowercase()).collect();
+        match self {
+            LabelSet::Positive(set) => LabelSet::PositiveIgnoreCase(lowercase(set)),
+            LabelSet::Negative(set) => LabelSet::NegativeIgnoreCase(lowercase(set)),
+            set => set,
         }
     }
 }
 
+/// FilterMode.
+///
+/// Determines whether nodes matched by a `LabelSet` are kept or removed when filtering.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FilterMode {
+    /// Keep matched nodes, remove all others.
+    Keep,
+    /// Remove matched nodes, keep all others.
+    Remove,
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
@@ -99,6 +133,26 @@ mod tests {
         assert!(positive_label_set.matches("e"));
     }
 
+    #[test]
+    fn label_set_case() {
+        let set = vec!["np".to_string(), "Vp".to_string()]
+            .into_iter()
+            .collect::<HashSet<_>>();
+        let positive = LabelSet::Positive(set.clone());
+        assert!(positive.matches("np"));
+        assert!(!positive.matches("NP"));
+        assert!(!positive.matches("vp"));
+        let positive = positive.ignore_case();
+        assert!(positive.matches("np"));
+        assert!(positive.matches("NP"));
+        assert!(positive.matches("vP"));
+        assert!(!positive.matches("PP"));
+        let negative = LabelSet::Negative(set).ignore_case();
+        assert!(!negative.matches("Np"));
+        assert!(!negative.matches("VP"));
+        assert!(negative.matches("pp"));
+    }
+
     #[test]
     fn climber_test() {
         let input = "(NX (NN Nounphrase) (PX (PP on) (NX (DET a) (ADJ single) (NX line))))";